@@ -0,0 +1,52 @@
+#!/usr/bin/env bash
+# Times `add`, `commit` and `checkout` on a generated tree of small files.
+#
+#   scripts/bench.sh [file count] [ink binary]
+#
+# Defaults to 100000 files and target/release/ink; build with
+# `cargo build --release` first. Pass -z through INK_INIT_FLAGS to benchmark
+# a compressed store.
+set -euo pipefail
+
+FILES=${1:-100000}
+INK=$(realpath "${2:-target/release/ink}")
+WORK=$(mktemp -d)
+trap 'rm -rf "$WORK"' EXIT
+
+export INK_AUTHOR_NAME=bench INK_AUTHOR_EMAIL=bench@example.com
+export INK_COMMITTER_NAME=bench INK_COMMITTER_EMAIL=bench@example.com
+export INK_CONFIG_GLOBAL="$WORK/.inkconfig"
+
+cd "$WORK"
+mkdir repo && cd repo
+"$INK" init ${INK_INIT_FLAGS:-} >/dev/null
+echo bench > README
+"$INK" add README >/dev/null
+"$INK" commit -m base >/dev/null
+"$INK" branch base >/dev/null
+
+# 100 directories sharing the files evenly
+per_dir=$(( (FILES + 99) / 100 ))
+for d in $(seq 0 99); do
+    mkdir "d$d"
+    for f in $(seq 0 $(( per_dir - 1 ))); do
+        echo "file $d/$f" > "d$d/f$f.txt"
+    done
+done
+
+# Wall-clock, user and system time of one command
+step() {
+    local name=$1
+    shift
+    local TIMEFORMAT="%R %U %S"
+    local times
+    times=$( { time "$@" >/dev/null 2>&1; } 2>&1 )
+    read -r real user sys <<< "$times"
+    printf '%-26s %7ss real %7ss user %7ss sys\n' "$name" "$real" "$user" "$sys"
+}
+
+echo "$(( per_dir * 100 )) files"
+step "add ." "$INK" add .
+step "commit" "$INK" commit -m tree
+step "checkout (remove files)" "$INK" checkout base
+step "checkout (write files)" "$INK" checkout main
@@ -1,44 +1,33 @@
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use anyhow::Result;
-use ignore::WalkBuilder;
+use std::collections::{HashSet, VecDeque};
+use std::fs::read;
+use std::io::{stdin, stdout, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use colored::control::SHOULD_COLORIZE;
+use colored::Colorize;
 use rayon::prelude::*;
 
+use crate::utils::dir::list_worktree_files;
 use crate::utils::enums::AddMode;
-use crate::utils::index::{add_files_to_index, save_index_for_current_branch, Index};
+use crate::utils::diff::{apply_hunks, format_hunk, is_binary, make_hunks, unified_diff, Hunk};
+use crate::utils::editor::edit_file;
+use crate::utils::index::{add_files_to_index, save_index_for_current_branch, Index, IndexEntry};
+use crate::utils::lfs;
+use crate::utils::object::{create_blob_for, read_blob_object};
 use crate::utils::ignore::is_ignored;
 use crate::utils::dir::is_in_ink;
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
 
 /// Main `add` command dispatcher
 pub fn run(mode: AddMode) -> Result<()> {
+    let repo = Repo::load()?;
+
     match mode {
         AddMode::All => {
-            let files = Arc::new(Mutex::new(Vec::new()));
-
-            WalkBuilder::new(".")
-                .add_custom_ignore_filename(".inkignore")
-                .standard_filters(false)
-                .hidden(false)
-                .build_parallel()
-                .run(|| {
-                    let files = Arc::clone(&files);
-                    Box::new(move |res| {
-                        if let Ok(entry) = res {
-                            let path = entry.path();
-
-                            if path.is_file() && !is_in_ink(path) {
-                                files.lock().unwrap().push(path.to_path_buf());
-                            }
-                        }
-                        ignore::WalkState::Continue
-                    })
-                });
-
-            let files_to_add = Arc::try_unwrap(files)
-                .map(|mutex| mutex.into_inner().unwrap())
-                .unwrap_or_else(|arc| (*arc.lock().unwrap()).clone());
-
-            add_files_to_index(&files_to_add)?;
+            let files_to_add = list_worktree_files();
+
+            add_files_to_index(&repo, &files_to_add)?;
             save_index_for_current_branch()?;
         }
 
@@ -55,20 +44,213 @@ pub fn run(mode: AddMode) -> Result<()> {
             }
 
             index.save()?; // save deletions
-            add_files_to_index(&existing)?; // only add valid ones
+            add_files_to_index(&repo, &existing)?; // only add valid ones
             save_index_for_current_branch()?;
         }
 
         AddMode::Files(files) => {
-            let filtered: Vec<PathBuf> = files
+            let spec = Pathspec::from_paths(&files)?;
+            let mut index = Index::load()?;
+
+            // Candidates are untracked files on disk plus everything already tracked,
+            // so that a directory pathspec also stages deletions below it
+            let mut candidates: HashSet<PathBuf> = index.tracked_files().into_iter().collect();
+            candidates.extend(list_worktree_files());
+
+            let matched: Vec<PathBuf> = candidates.into_iter().filter(|p| spec.matches(p)).collect();
+
+            let unmatched = spec.unmatched(&matched);
+            if let Some(first) = unmatched.first() {
+                if is_ignored(Path::new(first)) && Path::new(first).exists() {
+                    return Err(anyhow!("The path '{}' is ignored by one of your .inkignore files", first));
+                }
+                return Err(anyhow!("pathspec '{}' did not match any files", first));
+            }
+
+            let (existing, deleted): (Vec<_>, Vec<_>) = matched
                 .into_par_iter()
-                .filter(|f| f.is_file() && !is_in_ink(f) && !is_ignored(f))
+                .partition(|path| path.is_file());
+
+            for path in &deleted {
+                index.remove(path);
+            }
+            index.save()?;
+
+            let filtered: Vec<PathBuf> = existing
+                .into_par_iter()
+                .filter(|f| !is_in_ink(f) && (index.entries.contains_key(f) || !is_ignored(f)))
                 .collect();
 
-            add_files_to_index(&filtered)?;
+            add_files_to_index(&repo, &filtered)?;
+            save_index_for_current_branch()?;
+        }
+
+        AddMode::Patch(files) => {
+            let spec = Pathspec::from_paths(&files)?;
+            add_patch(&repo, &spec)?;
             save_index_for_current_branch()?;
         }
     }
 
     Ok(())
 }
+
+/// What the user decided for the remaining hunks of a file
+enum Choice {
+    Continue,
+    /// Stop prompting but keep the decisions made so far
+    Quit,
+}
+
+/// `ink add -p`: walks modified tracked files hunk by hunk
+fn add_patch(repo: &Repo, spec: &Pathspec) -> Result<()> {
+    let mut index = Index::load()?;
+    let color = SHOULD_COLORIZE.should_colorize();
+
+    let mut paths: Vec<PathBuf> = index.tracked_files().into_iter().filter(|p| spec.matches(p)).collect();
+    paths.sort();
+
+    for path in paths {
+        let staged_hash = index.entries[&path].hash.clone();
+
+        if !path.is_file() {
+            println!("{}", unified_diff(&path.to_string_lossy(), Some(&read_blob_object(&staged_hash)?), None, color));
+            match prompt("Stage deletion [y,n,q,?]? ", "y - stage the deletion\nn - keep the file staged\nq - quit")? {
+                'y' => index.remove(&path),
+                'q' => break,
+                _ => {}
+            }
+            continue;
+        }
+
+        // Large files are compared by content, since their pointers would make meaningless hunks
+        let old = match lfs::read_pointer_blob(&staged_hash)? {
+            Some(pointer) if lfs::is_tracked(&path) => {
+                if !pointer.is_present() {
+                    println!("Skipping {}: its staged content has not been fetched", path.display());
+                    continue;
+                }
+                lfs::read_content(&pointer)?
+            }
+            _ => read_blob_object(&staged_hash)?,
+        };
+        let new = read(&path)?;
+        if old == new {
+            continue;
+        }
+        if is_binary(&old) || is_binary(&new) {
+            println!("Skipping binary file {}", path.display());
+            continue;
+        }
+
+        let (old, new) = (String::from_utf8_lossy(&old).into_owned(), String::from_utf8_lossy(&new).into_owned());
+        let (selected, choice) = select_hunks(&path, make_hunks(&old, &new, 3), color)?;
+
+        if !selected.is_empty() {
+            let refs: Vec<&Hunk> = selected.iter().collect();
+            let staged = apply_hunks(&old, &refs);
+            let hash = create_blob_for(repo, &path, staged.as_bytes())?;
+            index.add(IndexEntry { path: path.clone(), hash });
+        }
+
+        if let Choice::Quit = choice {
+            break;
+        }
+    }
+
+    index.save()
+}
+
+const PATCH_HELP: &str = "y - stage this hunk
+n - do not stage this hunk
+q - quit; do not stage this hunk or any of the remaining ones
+a - stage this hunk and all later hunks in the file
+d - do not stage this hunk or any of the later hunks in the file
+s - split the current hunk into smaller hunks
+e - manually edit the current hunk
+? - print help";
+
+/// Prompts for each hunk of one file and returns the ones to stage, in order
+fn select_hunks(path: &Path, hunks: Vec<Hunk>, color: bool) -> Result<(Vec<Hunk>, Choice)> {
+    let mut queue: VecDeque<Hunk> = hunks.into();
+    let mut selected = Vec::new();
+    let header = format!("diff --ink a/{0} b/{0}", path.display());
+    println!("{}", if color { header.bold().to_string() } else { header });
+
+    while let Some(hunk) = queue.pop_front() {
+        print!("{}", format_hunk(&hunk, color));
+        let splittable = hunk.split().is_some();
+        let options = if splittable { "y,n,q,a,d,s,e,?" } else { "y,n,q,a,d,e,?" };
+
+        match prompt(&format!("Stage this hunk [{}]? ", options), PATCH_HELP)? {
+            'y' => selected.push(hunk),
+            'n' => {}
+            'q' => return Ok((selected, Choice::Quit)),
+            'a' => {
+                selected.push(hunk);
+                selected.extend(queue.drain(..));
+            }
+            'd' => break,
+            's' if splittable => {
+                let parts = hunk.split().unwrap_or_default();
+                println!("Split into {} hunks.", parts.len());
+                for part in parts.into_iter().rev() {
+                    queue.push_front(part);
+                }
+            }
+            'e' => match edit_hunk(&hunk)? {
+                Some(edited) => selected.push(edited),
+                None => queue.push_front(hunk),
+            },
+            _ => {
+                println!("{}", PATCH_HELP);
+                queue.push_front(hunk);
+            }
+        }
+    }
+
+    Ok((selected, Choice::Continue))
+}
+
+/// Opens the hunk in the editor; returns `None` (re-prompt) if the edit cannot be applied
+fn edit_hunk(hunk: &Hunk) -> Result<Option<Hunk>> {
+    let mut text = String::from("# Manual hunk edit mode -- see bottom for a quick guide.\n");
+    text += &format_hunk(hunk, false)
+        .lines()
+        .skip(1)
+        .filter(|l| !l.starts_with('\\'))
+        .map(|l| format!("{}\n", l))
+        .collect::<String>();
+    text += "# ---\n";
+    text += "# To remove '-' lines, make them ' ' lines (context).\n";
+    text += "# To remove '+' lines, delete them.\n";
+    text += "# Lines starting with # will be removed.\n";
+
+    let edited = edit_file(Path::new(".ink/ADD_EDIT.patch"), &text)?;
+    match hunk.with_edit(&edited) {
+        Ok(h) => Ok(Some(h)),
+        Err(e) => {
+            println!("Your edited hunk does not apply: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Reads a single-letter answer; EOF is treated as "quit"
+fn prompt(question: &str, help: &str) -> Result<char> {
+    loop {
+        print!("{}", question.blue().bold());
+        stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin().read_line(&mut line)? == 0 {
+            println!();
+            return Ok('q');
+        }
+
+        match line.trim().chars().next() {
+            Some(c) => return Ok(c.to_ascii_lowercase()),
+            None => println!("{}", help),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::read;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use crate::commands::commit::read_current_commit;
+use crate::utils::diff::{diff, is_binary, split_lines, Edit};
+use crate::utils::history::commit_files;
+use crate::utils::object::{read_blob_object, read_commit_object, Commit, ZERO_HASH};
+use crate::utils::revision::resolve_revision;
+
+/// Where a line of the blamed file came from
+#[derive(Clone)]
+struct Origin {
+    /// `None` for lines changed in the working tree but not committed yet
+    commit: Option<String>,
+    path: PathBuf,
+}
+
+/// `(line in some version, line in the blamed file)` pairs
+type LineMap = Vec<(usize, usize)>;
+
+/// Lines of one version of a file still waiting for an owner
+struct Suspect {
+    path: PathBuf,
+    blob: String,
+    lines: LineMap,
+}
+
+/// `ink blame [-L <start>,<end>] <file> [<revision>]`
+pub fn run(file: PathBuf, rev: Option<String>, range: Option<String>) -> Result<()> {
+    let start = match &rev {
+        Some(rev) => resolve_revision(rev)?,
+        None => read_current_commit()?,
+    };
+    if start == ZERO_HASH {
+        return Err(anyhow!("The current branch does not have any commits yet"));
+    }
+
+    let committed = commit_files(&read_commit_object(&start)?)?.get(&file).cloned();
+
+    // Without a revision, blame the working tree copy like `git blame` does
+    let content = match (&rev, file.is_file()) {
+        (None, true) => read(&file)?,
+        _ => match &committed {
+            Some(blob) => read_blob_object(blob)?,
+            None => return Err(anyhow!("no such path '{}' in {}", file.display(), rev.as_deref().unwrap_or("HEAD"))),
+        },
+    };
+    if is_binary(&content) {
+        return Err(anyhow!("Cannot blame binary file '{}'", file.display()));
+    }
+
+    let text = String::from_utf8_lossy(&content).into_owned();
+    let lines = split_lines(&text);
+    let (first, last) = parse_range(range.as_deref(), lines.len())?;
+    let wanted: LineMap = (first..=last).map(|n| (n, n)).collect();
+
+    let mut origins: Vec<Option<Origin>> = vec![None; lines.len() + 1];
+    let pending = match &committed {
+        Some(blob) if rev.is_none() => {
+            // Lines the working tree added or changed belong to nobody yet
+            let old = read_blob_object(blob)?;
+            let (moved, unmatched) = follow_lines(&old, &content, wanted);
+            for (_, final_line) in unmatched {
+                origins[final_line] = Some(Origin { commit: None, path: file.clone() });
+            }
+            moved
+        }
+        Some(_) => wanted,
+        None => {
+            for (_, final_line) in wanted {
+                origins[final_line] = Some(Origin { commit: None, path: file.clone() });
+            }
+            Vec::new()
+        }
+    };
+
+    if let Some(blob) = committed
+        && !pending.is_empty()
+    {
+        assign(&start, Suspect { path: file.clone(), blob, lines: pending }, &mut origins)?;
+    }
+
+    print(&file, &lines, first, last, &origins)
+}
+
+/// Walks history from `start`, handing lines down to the parents that already
+/// had them and blaming the rest on the commit where they stop matching
+fn assign(start: &str, suspect: Suspect, origins: &mut [Option<Origin>]) -> Result<()> {
+    let mut commits: HashMap<String, Commit> = HashMap::new();
+    let mut queue: BinaryHeap<(i64, String)> = BinaryHeap::new();
+    let mut suspects: HashMap<String, Vec<Suspect>> = HashMap::new();
+
+    let commit = read_commit_object(start)?;
+    queue.push((commit.committer.timestamp, start.to_string()));
+    commits.insert(start.to_string(), commit);
+    suspects.entry(start.to_string()).or_default().push(suspect);
+
+    // Newest first, so every child has passed its lines down before a parent is visited
+    while let Some((_, hash)) = queue.pop() {
+        let Some(list) = suspects.remove(&hash) else { continue };
+        let commit = commits[&hash].clone();
+        let files = commit_files(&commit)?;
+
+        for suspect in merge_suspects(list) {
+            let mut lines = suspect.lines;
+
+            for parent in &commit.parents {
+                if lines.is_empty() {
+                    break;
+                }
+                let parent_commit = match commits.get(parent) {
+                    Some(c) => c.clone(),
+                    None => read_commit_object(parent)?,
+                };
+                let parent_files = commit_files(&parent_commit)?;
+                let Some((path, blob)) = find_in_parent(&suspect.path, &suspect.blob, &files, &parent_files)? else {
+                    continue;
+                };
+
+                let (moved, rest) = if blob == suspect.blob {
+                    (std::mem::take(&mut lines), Vec::new())
+                } else {
+                    follow_lines(&read_blob_object(&blob)?, &read_blob_object(&suspect.blob)?, lines)
+                };
+                lines = rest;
+
+                if !moved.is_empty() {
+                    if !commits.contains_key(parent) {
+                        queue.push((parent_commit.committer.timestamp, parent.clone()));
+                        commits.insert(parent.clone(), parent_commit);
+                    }
+                    suspects.entry(parent.clone()).or_default().push(Suspect { path, blob, lines: moved });
+                }
+            }
+
+            for (_, final_line) in lines {
+                origins[final_line] = Some(Origin { commit: Some(hash.clone()), path: suspect.path.clone() });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Combines suspects that reached the same commit and path through different children
+fn merge_suspects(list: Vec<Suspect>) -> Vec<Suspect> {
+    let mut merged: Vec<Suspect> = Vec::new();
+    for suspect in list {
+        match merged.iter_mut().find(|s| s.path == suspect.path) {
+            Some(existing) => existing.lines.extend(suspect.lines),
+            None => merged.push(suspect),
+        }
+    }
+    merged
+}
+
+/// The path and blob a file had in a parent, following a rename when the path is
+/// gone: an identical blob wins, otherwise the most similar deleted file
+fn find_in_parent(
+    path: &Path,
+    blob: &str,
+    files: &HashMap<PathBuf, String>,
+    parent_files: &HashMap<PathBuf, String>,
+) -> Result<Option<(PathBuf, String)>> {
+    if let Some(parent_blob) = parent_files.get(path) {
+        return Ok(Some((path.to_path_buf(), parent_blob.clone())));
+    }
+
+    let removed: Vec<(&PathBuf, &String)> = parent_files.iter().filter(|(p, _)| !files.contains_key(*p)).collect();
+    if let Some((p, b)) = removed.iter().find(|(_, b)| b.as_str() == blob) {
+        return Ok(Some(((*p).clone(), (*b).clone())));
+    }
+
+    let content = read_blob_object(blob)?;
+    let ours = split_lines_bytes(&content);
+    let mut best: Option<(usize, &PathBuf, &String)> = None;
+    for (p, b) in removed {
+        let theirs = read_blob_object(b)?;
+        let theirs = split_lines_bytes(&theirs);
+        let common = diff(&theirs, &ours).iter().filter(|e| matches!(e, Edit::Equal(..))).count();
+        // At least half the lines must survive for it to count as a rename
+        if common * 2 >= ours.len().max(theirs.len()) && common > 0 && best.is_none_or(|(c, _, _)| common > c) {
+            best = Some((common, p, b));
+        }
+    }
+
+    Ok(best.map(|(_, p, b)| (p.clone(), b.clone())))
+}
+
+/// Maps `lines` of `new` onto `old`, returning the lines `old` already had (with
+/// their line numbers there) and the ones it did not
+fn follow_lines(old: &[u8], new: &[u8], lines: LineMap) -> (LineMap, LineMap) {
+    let old_lines = split_lines_bytes(old);
+    let new_lines = split_lines_bytes(new);
+
+    let mut to_old: HashMap<usize, usize> = HashMap::new();
+    for edit in diff(&old_lines, &new_lines) {
+        if let Edit::Equal(i, j) = edit {
+            to_old.insert(j + 1, i + 1);
+        }
+    }
+
+    let (moved, rest): (Vec<_>, Vec<_>) = lines.into_iter().partition(|(n, _)| to_old.contains_key(n));
+    (moved.into_iter().map(|(n, f)| (to_old[&n], f)).collect(), rest)
+}
+
+fn split_lines_bytes(data: &[u8]) -> Vec<&[u8]> {
+    data.split_inclusive(|b| *b == b'\n').collect()
+}
+
+/// Parses `-L <start>,<end>` (or `<start>,+<count>`) into an inclusive 1-based range
+fn parse_range(range: Option<&str>, len: usize) -> Result<(usize, usize)> {
+    let Some(range) = range else {
+        return Ok((1, len));
+    };
+    if len == 0 {
+        return Err(anyhow!("file is empty, -L {} is out of range", range));
+    }
+
+    let invalid = || anyhow!("Invalid -L range '{}'", range);
+    let (start, end) = range.split_once(',').unwrap_or((range, ""));
+    let first: usize = if start.is_empty() { 1 } else { start.parse().map_err(|_| invalid())? };
+    if first == 0 {
+        return Err(anyhow!("-L {}: lines are numbered from 1", range));
+    }
+    let last: usize = if end.is_empty() {
+        len
+    } else if let Some(count) = end.strip_prefix('+') {
+        let count: usize = count.parse().map_err(|_| invalid())?;
+        let span = count.checked_sub(1).ok_or_else(|| anyhow!("-L {}: the line count must be at least 1", range))?;
+        first.checked_add(span).ok_or_else(invalid)?
+    } else {
+        end.parse().map_err(|_| invalid())?
+    };
+
+    if first > len || last < first {
+        return Err(anyhow!("-L {} is out of range; the file has {} lines", range, len));
+    }
+    Ok((first, last.min(len)))
+}
+
+fn print(file: &Path, lines: &[&str], first: usize, last: usize, origins: &[Option<Origin>]) -> Result<()> {
+    let mut commits: HashMap<String, Commit> = HashMap::new();
+    for origin in origins.iter().flatten() {
+        if let Some(hash) = &origin.commit
+            && !commits.contains_key(hash)
+        {
+            commits.insert(hash.clone(), read_commit_object(hash)?);
+        }
+    }
+
+    let show_path = origins.iter().flatten().any(|o| o.path != file);
+    let author_of = |o: &Origin| match &o.commit {
+        Some(hash) => commits[hash].author.name.clone(),
+        None => "Not Committed Yet".to_string(),
+    };
+    let author_width = origins.iter().flatten().map(|o| author_of(o).chars().count()).max().unwrap_or(0);
+    let path_width = origins.iter().flatten().map(|o| o.path.to_string_lossy().chars().count()).max().unwrap_or(0);
+    let number_width = last.to_string().len();
+
+    for n in first..=last {
+        let origin = origins[n].as_ref().ok_or_else(|| anyhow!("Line {} was not attributed", n))?;
+        let (hash, date) = match &origin.commit {
+            Some(hash) => (hash[..8].yellow(), commits[hash].author.datetime().format("%Y-%m-%d %H:%M:%S %z").to_string()),
+            None => ("0".repeat(8).normal(), String::new()),
+        };
+        let path = if show_path { format!(" {:<w$}", origin.path.to_string_lossy(), w = path_width) } else { String::new() };
+        let date = if date.is_empty() { " ".repeat(25) } else { date };
+
+        println!(
+            "{}{} ({:<aw$} {} {:>nw$}) {}",
+            hash,
+            path,
+            author_of(origin),
+            date,
+            n,
+            lines[n - 1].trim_end_matches('\n'),
+            aw = author_width,
+            nw = number_width,
+        );
+    }
+
+    Ok(())
+}
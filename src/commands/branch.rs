@@ -1,11 +1,15 @@
 use std::fs::{create_dir_all, read_to_string, write};
 use std::path::Path;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use colored::Colorize;
-use rayon::prelude::*;
 
-use crate::commands::commit::read_current_commit;
+use crate::commands::commit::{get_branch_commit, read_current_commit};
+use crate::commands::remote::is_remote;
+use crate::utils::config::{get_config, set_config, unset_config};
+use crate::utils::history::ahead_behind;
 use crate::utils::log::log_branch;
+use crate::utils::object::{read_commit_object, ZERO_HASH};
+use crate::utils::revision::resolve_ref;
 
 pub fn run(name: Option<String>) -> Result<()> {
     match name {
@@ -26,27 +30,143 @@ pub fn run(name: Option<String>) -> Result<()> {
             log_branch(&n)?;
         }
 
-        None => {
-            let root = Path::new(".ink");
-            let branches_path = root.join("refs").join("heads");
-            let current_branch = read_current_branch()?;
-            let entries: Vec<_> = branches_path.read_dir()?.collect::<Result<_, _>>()?;
-
-            entries.par_iter().for_each(|entry| {
-                let name = entry.file_name().to_string_lossy().to_string();
-
-                if name == current_branch {
-                    println!("{}", format!("* {}", name.underline()).green()); // current branch
-                } else {
-                    println!("  {}", name);
-                }
-            });
+        None => list(0)?,
+    }
+
+    Ok(())
+}
+
+/// Lists branches; `-v` adds each tip, `-vv` also its upstream and ahead/behind counts
+pub fn list(verbose: u8) -> Result<()> {
+    let current_branch = read_current_branch()?;
+    let branches_path = Path::new(".ink").join("refs").join("heads");
+
+    let mut names: Vec<String> = branches_path
+        .read_dir()?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+        .collect::<Result<_>>()?;
+    names.sort();
+    let width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+
+    for name in names {
+        let mut line = if name == current_branch {
+            format!("* {}", name.underline()).green().to_string()
+        } else {
+            format!("  {}", name)
+        };
+
+        let commit = get_branch_commit(&name)?;
+        if verbose > 0 && commit != ZERO_HASH {
+            let subject = read_commit_object(&commit)?.message.lines().next().unwrap_or("").to_string();
+            let tracking = match tracking_status(&name)? {
+                Some(t) if verbose > 1 => format!("[{}] ", t.describe().blue()),
+                _ => String::new(),
+            };
+            let pad = " ".repeat(width - name.len());
+            line = format!("{}{} {} {}{}", line, pad, &commit[..7].yellow(), tracking, subject);
         }
+
+        println!("{}", line);
     }
 
     Ok(())
 }
 
+/// A branch's remote-tracking counterpart and how far the two have drifted apart
+pub struct Tracking {
+    /// Short name such as `origin/main`
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+    /// The upstream is configured but its remote-tracking ref no longer exists
+    pub gone: bool,
+}
+
+impl Tracking {
+    /// `origin/main: ahead 1, behind 2`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.gone {
+            parts.push("gone".to_string());
+        }
+        if self.ahead > 0 {
+            parts.push(format!("ahead {}", self.ahead));
+        }
+        if self.behind > 0 {
+            parts.push(format!("behind {}", self.behind));
+        }
+
+        if parts.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}: {}", self.name, parts.join(", "))
+        }
+    }
+}
+
+/// Upstream of a branch as `(remote, remote branch)`, from `branch.<name>.remote` and `.merge`
+pub fn upstream_of(branch: &str) -> Result<Option<(String, String)>> {
+    let remote = get_config(&format!("branch.{}.remote", branch))?;
+    let merge = get_config(&format!("branch.{}.merge", branch))?;
+
+    Ok(remote.zip(merge).map(|(remote, merge)| {
+        let merge = merge.strip_prefix("refs/heads/").unwrap_or(&merge).to_string();
+        (remote, merge)
+    }))
+}
+
+pub fn set_upstream(branch: &str, remote: &str, remote_branch: &str) -> Result<()> {
+    set_config(&format!("branch.{}.remote", branch), remote, false)?;
+    set_config(&format!("branch.{}.merge", branch), &format!("refs/heads/{}", remote_branch), false)
+}
+
+pub fn unset_upstream(branch: &str) -> Result<bool> {
+    let had_remote = unset_config(&format!("branch.{}.remote", branch), false)?;
+    let had_merge = unset_config(&format!("branch.{}.merge", branch), false)?;
+    Ok(had_remote || had_merge)
+}
+
+/// `ink branch -u <remote>/<branch> [<name>]`
+pub fn set_upstream_to(upstream: &str, branch: Option<String>) -> Result<()> {
+    let branch = match branch {
+        Some(b) => b,
+        None => read_current_branch()?,
+    };
+
+    let (remote, remote_branch) = upstream
+        .split_once('/')
+        .filter(|(remote, _)| is_remote(remote).unwrap_or(false))
+        .ok_or_else(|| anyhow!("'{}' is not a remote-tracking branch (expected <remote>/<branch>)", upstream))?;
+
+    if resolve_ref(&format!("remotes/{}", upstream))?.is_none() {
+        return Err(anyhow!("The requested upstream branch '{}' does not exist; fetch it first", upstream));
+    }
+
+    set_upstream(&branch, remote, remote_branch)?;
+    println!("Branch '{}' set up to track '{}'.", branch, upstream);
+    Ok(())
+}
+
+/// Compares a branch with its upstream's remote-tracking ref, if it has one
+pub fn tracking_status(branch: &str) -> Result<Option<Tracking>> {
+    let Some((remote, remote_branch)) = upstream_of(branch)? else {
+        return Ok(None);
+    };
+    let name = format!("{}/{}", remote, remote_branch);
+
+    let local = get_branch_commit(branch)?;
+    let (ahead, behind, gone) = match resolve_ref(&format!("remotes/{}", name))? {
+        Some(upstream) if local != ZERO_HASH => {
+            let (ahead, behind) = ahead_behind(&local, &upstream)?;
+            (ahead, behind, false)
+        }
+        Some(_) => (0, 0, false),
+        None => (0, 0, true),
+    };
+
+    Ok(Some(Tracking { name, ahead, behind, gone }))
+}
+
 pub fn read_current_branch() -> Result<String> {
     let root = Path::new(".ink");
     let head_path = root.join("HEAD");
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+use crate::commands::branch::read_current_branch;
+use crate::utils::bundle::Bundle;
+use crate::utils::hash::load_hash_algo;
+use crate::utils::history::missing_objects;
+use crate::utils::lfs::pointer_ids;
+use crate::utils::repo::Repo;
+use crate::utils::revision::{list_refs, resolve_revision};
+
+/// Subcommands of `ink bundle`
+pub enum BundleAction {
+    Create { file: PathBuf, revs: Vec<String>, all: bool },
+    Verify { file: PathBuf },
+    ListHeads { file: PathBuf },
+}
+
+pub fn run(action: BundleAction) -> Result<()> {
+    match action {
+        BundleAction::Create { file, revs, all } => create(&file, &revs, all),
+        BundleAction::Verify { file } => verify(&file),
+        BundleAction::ListHeads { file } => {
+            for (name, hash) in Bundle::read(&file)?.refs {
+                println!("{} {}", hash, name);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `ink bundle create <file> [--all] [<rev-range>...]`, where a range is
+/// `<branch>`, `^<rev>` or `<rev>..<branch>`
+fn create(file: &Path, revs: &[String], all: bool) -> Result<()> {
+    let mut refs: Vec<(String, String)> = Vec::new();
+    let mut excludes: Vec<String> = Vec::new();
+
+    if all {
+        refs.extend(list_refs("refs/heads")?);
+    }
+
+    for rev in revs {
+        if let Some(base) = rev.strip_prefix('^') {
+            excludes.push(resolve_revision(base)?);
+        } else if let Some((base, tip)) = rev.split_once("..") {
+            excludes.push(resolve_revision(base)?);
+            refs.push(branch_ref(tip)?);
+        } else {
+            refs.push(branch_ref(rev)?);
+        }
+    }
+
+    if refs.is_empty() {
+        return Err(anyhow!("Refusing to create an empty bundle; name at least one branch or use --all"));
+    }
+    refs.sort();
+    refs.dedup();
+
+    let tips: Vec<String> = refs.iter().map(|(_, hash)| hash.clone()).collect();
+    let objects = missing_objects(&tips, &excludes)?;
+    if objects.is_empty() {
+        return Err(anyhow!("Refusing to create an empty bundle; the excluded revisions already contain everything"));
+    }
+
+    let bundle = Bundle::new(load_hash_algo()?.to_string(), refs, &objects)?;
+    // Large files travel with the pointers that name them
+    let lfs = pointer_ids(&objects)?;
+    bundle.write(file, &objects, &lfs)?;
+
+    println!(
+        "Wrote {} objects and {} refs to {}",
+        objects.len(),
+        bundle.refs.len(),
+        file.display()
+    );
+    if !lfs.is_empty() {
+        println!("The bundle carries {} large file(s)", lfs.len());
+    }
+    Ok(())
+}
+
+/// Resolves a branch name (or HEAD) to its full ref name and commit
+fn branch_ref(name: &str) -> Result<(String, String)> {
+    let branch = if name == "HEAD" || name == "@" { read_current_branch()? } else { name.to_string() };
+
+    list_refs("refs/heads")?
+        .into_iter()
+        .find(|(full, _)| *full == format!("refs/heads/{}", branch))
+        .ok_or_else(|| anyhow!("'{}' is not a branch; bundles can only record branches", name))
+}
+
+/// `ink bundle verify <file>`: checks the bundle is intact and that this
+/// repository has everything it builds on
+fn verify(file: &Path) -> Result<()> {
+    let bundle = Bundle::read(file)?;
+    let local = load_hash_algo()?.to_string();
+    if bundle.hash_algo != local {
+        return Err(anyhow!("The bundle uses the {} hash algorithm but this repository uses {}", bundle.hash_algo, local));
+    }
+
+    bundle.check(&Repo::load()?).map_err(|e| anyhow!("{} is corrupt: {}", file.display(), e))?;
+
+    println!("The bundle contains {} ref(s):", bundle.refs.len());
+    for (name, hash) in &bundle.refs {
+        println!("{} {}", hash, name);
+    }
+
+    if bundle.prerequisites.is_empty() {
+        println!("The bundle records a complete history.");
+    } else {
+        println!("The bundle requires {} commit(s):", bundle.prerequisites.len());
+        for hash in &bundle.prerequisites {
+            println!("{}", hash);
+        }
+    }
+
+    let missing = bundle.missing_prerequisites();
+    if !missing.is_empty() {
+        let list: Vec<String> = missing.iter().map(|h| format!("\t{}", h)).collect();
+        return Err(anyhow!("This repository lacks these prerequisite commits:\n{}", list.join("\n")));
+    }
+
+    println!("{} is okay", file.display());
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::io::{stdout, Write};
+use anyhow::{anyhow, Result};
+
+use crate::utils::object::{object_exists, parse_raw_object, read_raw_object};
+use crate::utils::revision::resolve_object;
+
+/// What `ink cat-file` prints about the object
+pub enum CatMode {
+    /// `-t`: the object's type
+    Type,
+    /// `-s`: the body size in bytes
+    Size,
+    /// `-p`: the body, whatever the type
+    Pretty,
+    /// `-e`: nothing; the exit status says whether the object exists
+    Exists,
+    /// `<type> <object>`: the body, failing unless the object has that type
+    Expect(String),
+}
+
+/// `ink cat-file (-t | -s | -p | -e | <type>) <object>`
+pub fn run(mode: CatMode, object: String) -> Result<()> {
+    if let CatMode::Exists = mode {
+        let found = resolve_object(&object).is_ok_and(|hash| object_exists(&hash));
+        if !found {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let hash = resolve_object(&object)?;
+    let full = read_raw_object(&hash)?;
+    let (kind, body) = parse_raw_object(&full)?;
+
+    let mut out = stdout().lock();
+    match mode {
+        CatMode::Type => writeln!(out, "{}", kind)?,
+        CatMode::Size => writeln!(out, "{}", body.len())?,
+        CatMode::Expect(expected) if expected != kind => {
+            return Err(anyhow!("Object {} is a {}, not a {}", hash, kind, expected));
+        }
+        // Every object type is stored in its printable form already
+        _ => out.write_all(body)?,
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+use anyhow::Result;
+
+use crate::utils::ignore::matcher;
+
+/// Prints which of `paths` are ignored, and with `-v` the pattern responsible
+pub fn run(paths: Vec<PathBuf>, verbose: bool, non_matching: bool) -> Result<()> {
+    let matcher = matcher();
+    let mut any_ignored = false;
+
+    for path in paths {
+        let found = matcher.check(&path, path.is_dir());
+        let ignored = found.as_ref().is_some_and(|m| !m.negated);
+        any_ignored |= ignored;
+
+        match found {
+            Some(m) if verbose => {
+                println!("{}:{}:{}\t{}", m.source.display(), m.line, m.pattern, path.display());
+            }
+            Some(_) if ignored => println!("{}", path.display()),
+            _ if verbose && non_matching => println!("::\t{}", path.display()),
+            _ => {}
+        }
+    }
+
+    // Like git, signal "nothing ignored" through the exit status
+    if !any_ignored {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
-use std::fs::{create_dir_all, read, remove_file, File};
-use std::io::Write;
+use std::fs::{create_dir_all, remove_file, write, File};
+use std::io::{copy, Read};
 use std::path::{Path, PathBuf};
 use std::str::from_utf8;
 
@@ -12,25 +12,41 @@ use crate::commands;
 use crate::commands::branch::{read_current_branch, update_current_branch};
 use crate::commands::commit::{get_branch_commit, read_current_commit, read_tree_of_commit};
 use crate::utils::dir::remove_empty_parents_up_to;
-use crate::utils::hash::hash_object;
+use crate::utils::hooks::{run_post_hook, run_pre_hook};
 use crate::utils::index::{Index, IndexEntry};
+use crate::utils::lfs::{smudge, Pointer, POINTER_MAX};
 use crate::utils::log::log_checkout;
-use crate::utils::zip::decompress;
+use crate::utils::object::{hash_file, open_object, read_raw_object, ZERO_HASH};
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
 use crate::utils::ignore::is_ignored;
 
-pub fn run(b: bool, force: bool, name: String) -> Result<()> {
-    // If -b flag set, create the branch now
-    if b {
-        commands::branch::run(Some(name.clone()))?;
-    }
-
+pub fn run(b: bool, force: bool, no_verify: bool, name: String) -> Result<()> {
+    let repo = Repo::load()?;
     let current_branch = read_current_branch()?;
     let current_commit = read_current_commit()?;
     let current_index = Index::load()?;
     current_index.save_for_branch(&current_branch)?;
 
-    // Target commit and branch info
-    let target_commit = get_branch_commit(&name)?;
+    // Target commit and branch info; with -b the branch will start at HEAD
+    let creating = b && !Path::new(".ink/refs/heads").join(&name).exists();
+    let target_commit = if creating { current_commit.clone() } else { get_branch_commit(&name)? };
+
+    let hook_env = [
+        ("INK_BRANCH", name.as_str()),
+        ("INK_OLD_BRANCH", current_branch.as_str()),
+        ("INK_OLD_COMMIT", current_commit.as_str()),
+        ("INK_NEW_COMMIT", target_commit.as_str()),
+    ];
+
+    if !no_verify {
+        run_pre_hook("pre-checkout", &[&current_commit, &target_commit, &name], &hook_env)?;
+    }
+
+    // Only now that the hook has accepted the checkout
+    if b {
+        commands::branch::run(Some(name.clone()))?;
+    }
 
     let current_tree = if &current_commit != "0000000000000000000000000000000000000000000000000000000000000000" {
         get_tree_entries(&read_tree_of_commit(&current_commit)?)?
@@ -69,7 +85,7 @@ pub fn run(b: bool, force: bool, name: String) -> Result<()> {
             let current_hash = current_tree.get(path);
             let target_hash = target_tree.get(path);
 
-            let clean = is_clean(path, index_hash, current_hash, target_hash)?;
+            let clean = is_clean(&repo, path, index_hash, current_hash, target_hash)?;
             if !clean {
                 return Err(anyhow!(
                     "Uncommitted changes in '{}', please commit or stash them first.",
@@ -131,6 +147,9 @@ pub fn run(b: bool, force: bool, name: String) -> Result<()> {
     println!("Switched to branch '{}'", name);
     log_checkout(current_commit.clone(), target_commit.clone(), &name)?;
 
+    // The trailing "1" marks a branch checkout, matching git's convention
+    run_post_hook("post-checkout", &[&current_commit, &target_commit, "1"], &hook_env)?;
+
     Ok(())
 }
 
@@ -145,16 +164,10 @@ pub fn read_tree_recursive(
     hash: &str,
     out: &DashMap<PathBuf, String>,
 ) -> Result<()> {
-    let obj_path = Path::new(".ink")
-        .join("objects")
-        .join(&hash[..2])
-        .join(&hash[2..]);
-
-    let compressed = read(&obj_path)?;
-    let data = decompress(compressed)?;
-    let null_pos = data.iter().position(|&b| b == 0).ok_or_else(|| anyhow!("Invalid tree object"))?;
+    let data = read_raw_object(hash)?;
+    let null_pos = data.iter().position(|&b| b == 0).ok_or_else(|| anyhow!("Invalid tree object {}", hash))?;
     let content = &data[(null_pos + 1)..];
-    let text = from_utf8(content)?;
+    let text = from_utf8(content).map_err(|_| anyhow!("Tree {} is not valid UTF-8; run 'ink fsck'", hash))?;
 
     let subtasks: Vec<(PathBuf, String)> = text
         .lines()
@@ -184,26 +197,40 @@ pub fn read_tree_recursive(
 }
 
 pub fn restore_blob(path: &Path, hash: &str) -> Result<()> {
-    let obj_path = Path::new(".ink")
-        .join("objects")
-        .join(&hash[..2])
-        .join(&hash[2..]);
-
-    let compressed = read(&obj_path)?;
-    let data = decompress(compressed)?;
-    let null_pos = data.iter().position(|&b| b == 0).ok_or_else(|| anyhow!("Invalid blob object"))?;
-    let content = &data[(null_pos + 1)..];
+    let (kind, size, mut reader) = open_object(hash)?;
+    if kind != "blob" {
+        return Err(anyhow!("Object {} is a {}, not a blob", hash, kind));
+    }
 
     if let Some(parent) = path.parent() {
         create_dir_all(parent)?;
     }
 
+    // Pointer blobs are replaced by the large file they stand for
+    if size <= POINTER_MAX {
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        if let Some(pointer) = Pointer::parse(&body) {
+            if !smudge(&pointer, path)? {
+                eprintln!("warning: '{}' has not been fetched; run 'ink lfs fetch'", path.display());
+            }
+            return Ok(());
+        }
+        write(path, body)?;
+        return Ok(());
+    }
+
+    // Copy in pieces so large files never have to fit in memory
     let mut file = File::create(path)?;
-    file.write_all(content)?;
+    let written = copy(&mut reader, &mut file)?;
+    if written != size {
+        return Err(anyhow!("Object {} is truncated; run 'ink fsck'", hash));
+    }
     Ok(())
 }
 
 pub fn is_clean(
+    repo: &Repo,
     path: &Path,
     index_hash: Option<&String>,
     current_hash: Option<&String>,
@@ -213,10 +240,7 @@ pub fn is_clean(
         return Ok(index_hash.is_none() && current_hash.is_none());
     }
 
-    let data = read(path)?;
-    let header = format!("blob {}\0", data.len());
-    let full = [header.as_bytes(), &data].concat();
-    let working_hash = hash_object(&full)?;
+    let working_hash = hash_file(repo, path)?;
 
     match target_hash {
         #[allow(unused_variables)]
@@ -251,3 +275,78 @@ pub fn is_clean(
         }
     }
 }
+
+/// Moves the working tree and index of the current branch from commit `from` to `to`,
+/// e.g. for a fast-forward. Refuses if tracked files have changes or if an untracked
+/// file would be overwritten.
+pub fn switch_tree(repo: &Repo, from: &str, to: &str) -> Result<()> {
+    let report = commands::status::collect(repo, &Pathspec::default())?;
+    if !report.staged.is_empty() || !report.unstaged.is_empty() {
+        return Err(anyhow!("Your local changes would be overwritten; please commit or stash them first."));
+    }
+
+    let tree_of = |hash: &str| -> Result<HashMap<PathBuf, String>> {
+        if hash == ZERO_HASH {
+            return Ok(HashMap::new());
+        }
+        get_tree_entries(&read_tree_of_commit(hash)?)
+    };
+    let old_files = tree_of(from)?;
+    let new_files = tree_of(to)?;
+
+    for path in new_files.keys().filter(|p| !old_files.contains_key(*p)) {
+        if path.exists() {
+            return Err(anyhow!(
+                "Untracked file '{}' would be overwritten; please move or remove it first.",
+                path.display()
+            ));
+        }
+    }
+
+    for path in old_files.keys().filter(|p| !new_files.contains_key(*p)) {
+        if path.exists() {
+            remove_file(path)?;
+            remove_empty_parents_up_to(path, Path::new("."))?;
+        }
+    }
+
+    for (path, hash) in &new_files {
+        if old_files.get(path) != Some(hash) {
+            restore_blob(path, hash)?;
+        }
+    }
+
+    let mut index = Index::default();
+    for (path, hash) in new_files {
+        index.add(IndexEntry { path, hash });
+    }
+    index.save()?;
+    index.save_for_branch(&read_current_branch()?)
+}
+
+/// Forces the working tree and index of the current branch to match commit `to`,
+/// discarding changes to tracked files. Untracked files are left alone.
+pub fn reset_tree(repo: &Repo, to: &str) -> Result<()> {
+    let new_files = if to == ZERO_HASH { HashMap::new() } else { get_tree_entries(&read_tree_of_commit(to)?)? };
+
+    for path in Index::load()?.entries.keys().filter(|p| !new_files.contains_key(*p)) {
+        if path.exists() {
+            remove_file(path)?;
+            remove_empty_parents_up_to(path, Path::new("."))?;
+        }
+    }
+
+    for (path, hash) in &new_files {
+        let current = if path.is_file() { Some(hash_file(repo, path)?) } else { None };
+        if current.as_ref() != Some(hash) {
+            restore_blob(path, hash)?;
+        }
+    }
+
+    let mut index = Index::default();
+    for (path, hash) in new_files {
+        index.add(IndexEntry { path, hash });
+    }
+    index.save()?;
+    index.save_for_branch(&read_current_branch()?)
+}
@@ -0,0 +1,96 @@
+use std::env::set_current_dir;
+use std::fs::{create_dir_all, read_dir, remove_file, write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+
+use crate::commands;
+use crate::commands::branch::{set_upstream, update_current_branch};
+use crate::commands::checkout::switch_tree;
+use crate::commands::fetch::{fetch_with, Fetched};
+use crate::utils::config::set_config;
+use crate::utils::enums::HashAlgo;
+use crate::utils::lfs::{fetch_missing, files_in, Pointer};
+use crate::utils::log::log_action;
+use crate::utils::object::ZERO_HASH;
+use crate::utils::repo::Repo;
+use crate::utils::transport::connect;
+
+/// `ink clone <url> [<directory>]`
+pub fn run(url: String, directory: Option<PathBuf>) -> Result<()> {
+    // Local paths are stored absolute so the remote still resolves from the new repository
+    let url = if url.contains("://") {
+        url
+    } else {
+        Path::new(&url).canonicalize()?.to_string_lossy().into_owned()
+    };
+
+    let directory = match directory {
+        Some(dir) => dir,
+        None => default_directory(&url)?,
+    };
+    if directory.exists() && read_dir(&directory)?.next().is_some() {
+        return Err(anyhow!(
+            "Destination path '{}' already exists and is not an empty directory",
+            directory.display()
+        ));
+    }
+
+    let mut transport = connect(&url)?;
+    let hash_algo = HashAlgo::from_str(&transport.advertise()?.hash_algo)?;
+
+    println!("Cloning into '{}'...", directory.display());
+    create_dir_all(&directory)?;
+    set_current_dir(&directory)?;
+
+    commands::init::run(hash_algo, false)?;
+    set_config("remote.origin.url", &url, false)?;
+    let repo = Repo::load()?;
+
+    let Fetched { adv, received, .. } = fetch_with(&repo, transport.as_mut(), &url, Some("origin"))?;
+    println!("Received {} objects", received);
+
+    let Some(head) = adv.head.clone().or_else(|| adv.refs.first().map(|(name, _)| name.clone())) else {
+        println!("warning: You appear to have cloned an empty repository.");
+        return Ok(());
+    };
+    let branch = head.strip_prefix("refs/heads/").unwrap_or(&head);
+    let commit = adv.get(&head).cloned().unwrap_or_else(|| ZERO_HASH.to_string());
+
+    if branch != "main" {
+        remove_file(Path::new(".ink").join("refs").join("INDEXES").join("main"))?;
+        update_current_branch(branch)?;
+    }
+
+    // Large files arrive before checkout so they are written instead of their pointers
+    if commit != ZERO_HASH {
+        let pointers: Vec<Pointer> = files_in(&commit)?.into_iter().map(|(_, pointer)| pointer).collect();
+        let fetched = fetch_missing(&repo, transport.as_mut(), &pointers)?;
+        if fetched > 0 {
+            println!("Received {} large files", fetched);
+        }
+    }
+
+    // Check out while the branch is still unborn, so the empty index matches HEAD
+    switch_tree(&repo, ZERO_HASH, &commit)?;
+
+    let heads = Path::new(".ink").join("refs").join("heads");
+    create_dir_all(heads.join(branch).parent().unwrap())?;
+    write(heads.join(branch), &commit)?;
+    set_upstream(branch, "origin", branch)?;
+    log_action(ZERO_HASH.to_string(), commit, "clone", &format!("from {}", url))?;
+
+    Ok(())
+}
+
+/// `../repos/project/` -> `project`
+fn default_directory(url: &str) -> Result<PathBuf> {
+    let trimmed = url.trim_end_matches('/');
+    let name = trimmed.rsplit(['/', '\\']).next().unwrap_or(trimmed);
+    let name = name.strip_suffix(".ink").or_else(|| name.strip_suffix(".bundle")).unwrap_or(name);
+
+    if name.is_empty() || name.contains(':') {
+        return Err(anyhow!("Cannot guess a directory name from '{}'; please give one", url));
+    }
+    Ok(PathBuf::from(name))
+}
@@ -1,39 +1,223 @@
-use std::fs::{read, read_to_string, write};
-use std::path::Path;
+use std::collections::HashMap;
+use std::fs::{read_to_string, write};
+use std::io::{stdin, Read};
+use std::path::{Path, PathBuf};
 use anyhow::{ anyhow, Result };
 
 use crate::commands;
 use crate::commands::branch::read_current_branch;
+use crate::commands::checkout::get_tree_entries;
+use crate::utils::editor::{edit_file, strip_comments};
 use crate::utils::enums::AddMode;
-use crate::utils::log::log_commit;
-use crate::utils::object::{create_commit, create_tree };
-use crate::utils::zip::decompress;
+use crate::utils::hooks::{find_hook, run_post_hook, run_pre_hook};
+use crate::utils::index::Index;
+use crate::utils::log::{log_action, log_commit};
+use crate::utils::object::{create_commit, create_tree, read_commit_object, Commit, ZERO_HASH};
+use crate::utils::repo::Repo;
+use crate::utils::signature::Signature;
 
-pub fn run(message: String, a: bool) -> Result<()> {
-    if a {
+/// Options accepted by `ink commit`
+#[derive(Debug, Default)]
+pub struct CommitOptions {
+    /// Positional message, kept for compatibility with `ink commit "msg"`
+    pub message: Option<String>,
+    /// Each `-m` becomes its own paragraph
+    pub paragraphs: Vec<String>,
+    pub file: Option<PathBuf>,
+    pub all: bool,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub allow_empty: bool,
+    pub amend: bool,
+    pub no_edit: bool,
+    /// Skip the pre-commit and commit-msg hooks
+    pub no_verify: bool,
+}
+
+pub fn run(opts: CommitOptions) -> Result<()> {
+    let repo = Repo::load()?;
+
+    if opts.all {
         commands::add::run(AddMode::Update)?
     }
 
-    let tree_hash = create_tree()?;
+    let branch = read_current_branch()?;
+    let head = read_current_commit()?;
+    let hook_env = [("INK_BRANCH", branch.as_str()), ("INK_OLD_COMMIT", head.as_str())];
+
+    // Runs before the tree is built so the hook may still stage fixes
+    if !opts.no_verify {
+        run_pre_hook("pre-commit", &[], &hook_env)?;
+    }
+
+    let tree_hash = create_tree(&repo)?;
+
+    let amended = if opts.amend {
+        if head == ZERO_HASH {
+            return Err(anyhow!("Nothing to amend: the current branch has no commits yet."));
+        }
+        Some(read_commit_object(&head)?)
+    } else {
+        None
+    };
 
-    // Try reading the previous commit's tree hash
-    let parent_hash = read_current_commit()?;
+    // An amended commit keeps the parents of the commit it replaces
+    let parents = match &amended {
+        Some(commit) => commit.parents.clone(),
+        None if head == ZERO_HASH => Vec::new(),
+        None => vec![head.clone()],
+    };
 
-    if parent_hash != "0000000000000000000000000000000000000000000000000000000000000000" {
-        let parent_tree = read_tree_of_commit(&parent_hash)?;
+    if amended.is_none() && !opts.allow_empty && head != ZERO_HASH {
+        let parent_tree = read_tree_of_commit(&head)?;
         if tree_hash == parent_tree {
             println!("Nothing to commit — working tree matches last commit.");
             return Ok(());
         }
     }
 
-    let commit_hash = create_commit(&tree_hash, &parent_hash, &message, "Nilotpal Gupta")?;
+    let mut message = resolve_message(&opts, amended.as_ref(), parents.first().map(String::as_str))?;
+
+    // commit-msg may rewrite the message file in place
+    if !opts.no_verify && find_hook("commit-msg")?.is_some() {
+        let msg_path = Path::new(".ink/COMMIT_EDITMSG");
+        write(msg_path, format!("{}\n", message))?;
+        run_pre_hook("commit-msg", &[".ink/COMMIT_EDITMSG"], &hook_env)?;
+
+        message = strip_comments(&read_to_string(msg_path)?);
+        if message.is_empty() {
+            return Err(anyhow!("Aborting commit due to empty commit message."));
+        }
+    }
+
+    let committer = Signature::committer()?;
+    let author = match (&amended, &opts.author) {
+        (Some(old), None) => match &opts.date {
+            Some(date) => old.author.clone().with_date(date)?,
+            None => old.author.clone(),
+        },
+        _ => resolve_author(opts.author.as_deref(), opts.date.as_deref())?,
+    };
+
+    let commit_hash = create_commit(&repo, &tree_hash, &parents, &message, &author, &committer)?;
     update_current_commit(&commit_hash)?;
-    log_commit(&message)?;
+
+    let subject = message.lines().next().unwrap_or("");
+    if amended.is_some() {
+        log_action(head.clone(), commit_hash.clone(), "commit (amend)", subject)?;
+    } else {
+        log_commit(subject)?;
+    }
+
+    println!("[{} {}] {}", branch, &commit_hash[..7], subject);
+
+    run_post_hook(
+        "post-commit",
+        &[],
+        &[("INK_BRANCH", branch.as_str()), ("INK_OLD_COMMIT", head.as_str()), ("INK_NEW_COMMIT", commit_hash.as_str())],
+    )?;
 
     Ok(())
 }
 
+/// Picks the message from `-m`, `-F`, `--no-edit` or the editor, in that order
+fn resolve_message(opts: &CommitOptions, amended: Option<&Commit>, parent: Option<&str>) -> Result<String> {
+    let mut paragraphs: Vec<String> = opts.message.iter().cloned().collect();
+    paragraphs.extend(opts.paragraphs.iter().cloned());
+
+    let message = if !paragraphs.is_empty() {
+        paragraphs
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    } else if let Some(file) = &opts.file {
+        let text = if file == Path::new("-") {
+            let mut buf = String::new();
+            stdin().read_to_string(&mut buf)?;
+            buf
+        } else {
+            read_to_string(file)?
+        };
+        text.trim().to_string()
+    } else if let (true, Some(old)) = (opts.no_edit, amended) {
+        old.message.clone()
+    } else {
+        let initial = amended.map(|c| c.message.as_str()).unwrap_or("");
+        let template = commit_template(initial, parent)?;
+        strip_comments(&edit_file(Path::new(".ink/COMMIT_EDITMSG"), &template)?)
+    };
+
+    if message.is_empty() {
+        return Err(anyhow!("Aborting commit due to empty commit message."));
+    }
+
+    Ok(message)
+}
+
+/// Builds the editor template, listing the staged changes as comments
+fn commit_template(initial: &str, parent: Option<&str>) -> Result<String> {
+    let mut template = format!("{}\n\n", initial);
+    template += "# Please enter the commit message for your changes. Lines starting\n";
+    template += "# with '#' will be ignored, and an empty message aborts the commit.\n";
+    template += "#\n";
+    template += &format!("# On branch {}\n", read_current_branch()?);
+
+    let changes = staged_changes(parent.unwrap_or(ZERO_HASH))?;
+    if changes.is_empty() {
+        template += "# No changes\n";
+    } else {
+        template += "# Changes to be committed:\n";
+        for (status, path) in changes {
+            template += &format!("#\t{:<12}{}\n", format!("{}:", status), path.display());
+        }
+    }
+
+    Ok(template)
+}
+
+/// Lists index entries that differ from the tree of `base_commit`
+pub fn staged_changes(base_commit: &str) -> Result<Vec<(&'static str, PathBuf)>> {
+    let base = if base_commit == ZERO_HASH {
+        HashMap::new()
+    } else {
+        get_tree_entries(&read_tree_of_commit(base_commit)?)?
+    };
+    let index = Index::load()?;
+
+    let mut changes = Vec::new();
+    for (path, entry) in &index.entries {
+        match base.get(path) {
+            None => changes.push(("new file", path.clone())),
+            Some(hash) if *hash != entry.hash => changes.push(("modified", path.clone())),
+            _ => {}
+        }
+    }
+    for path in base.keys() {
+        if !index.entries.contains_key(path) {
+            changes.push(("deleted", path.clone()));
+        }
+    }
+
+    changes.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(changes)
+}
+
+/// Builds the author signature, applying `--author` and `--date` overrides
+fn resolve_author(ident: Option<&str>, date: Option<&str>) -> Result<Signature> {
+    let mut author = match ident {
+        Some(ident) => Signature::author_as(ident)?,
+        None => Signature::author()?,
+    };
+
+    if let Some(date) = date {
+        author = author.with_date(date)?;
+    }
+
+    Ok(author)
+}
+
 pub fn read_current_commit() -> Result<String> {
     let root = Path::new(".ink");
     let head_path = root.join("HEAD");
@@ -46,7 +230,7 @@ pub fn read_current_commit() -> Result<String> {
         let ref_path = head_contents.trim_start_matches("ref:").trim();
         let ref_file = root.join(ref_path);
         if !ref_file.exists() {
-            return Ok(String::from("0000000000000000000000000000000000000000000000000000000000000000"));
+            return Ok(ZERO_HASH.to_string());
         }
         read_to_string(ref_file)?.trim().to_string()
     } else {
@@ -67,26 +251,13 @@ pub fn update_current_commit(new_hash: &str) -> Result<()> {
 }
 
 pub fn read_tree_of_commit(commit_hash: &str) -> Result<String> {
-    let commit_path = Path::new(".ink").join("objects").join(&commit_hash[..2]).join(&commit_hash[2..]); // use your existing logic
-    let content = read(commit_path)?;
-    let decompressed = decompress(content)?;
-    
-    let (_, body) = std::str::from_utf8(&decompressed)?
-        .split_once('\0')
-        .ok_or_else(|| anyhow!("Invalid commit object format: no null byte"))?;
-
-    for line in body.lines() {
-        if line.starts_with("tree ") {
-            return Ok(line[5..].trim().to_string());
-        }
-    }
-    Err(anyhow!("No tree found in commit {}", commit_hash))
+    Ok(read_commit_object(commit_hash)?.tree)
 }
 
 pub fn get_branch_commit(branch: &str) -> Result<String> {
     let path = Path::new(".ink").join("refs").join("heads").join(branch);
     if !path.exists() {
-        return Ok("0000000000000000000000000000000000000000000000000000000000000000".to_string());
+        return Ok(ZERO_HASH.to_string());
     }
 
     let content = std::fs::read_to_string(path)?;
@@ -0,0 +1,47 @@
+use std::fs::read_to_string;
+use std::io::{stdin, Read};
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+
+use crate::utils::object::{create_commit, parse_raw_object, read_commit_object, read_raw_object};
+use crate::utils::repo::Repo;
+use crate::utils::revision::{resolve_object, resolve_revision};
+use crate::utils::signature::Signature;
+
+/// `ink commit-tree <tree> [-p <parent>]... [-m <message>]... [-F <file>]`:
+/// creates a commit object without touching HEAD or the index and prints its hash.
+/// The message is read from stdin when neither `-m` nor `-F` is given.
+pub fn run(tree: String, parents: Vec<String>, paragraphs: Vec<String>, file: Option<PathBuf>) -> Result<()> {
+    let hash = resolve_object(&tree)?;
+    let tree = match parse_raw_object(&read_raw_object(&hash)?)?.0 {
+        "tree" => hash,
+        "commit" => read_commit_object(&hash)?.tree,
+        other => return Err(anyhow!("'{}' is a {}, not a tree", tree, other)),
+    };
+
+    let mut resolved = Vec::new();
+    for parent in &parents {
+        let hash = resolve_revision(parent)?;
+        read_commit_object(&hash).map_err(|_| anyhow!("Parent '{}' is not a commit", parent))?;
+        if !resolved.contains(&hash) {
+            resolved.push(hash);
+        }
+    }
+
+    let message = if !paragraphs.is_empty() {
+        paragraphs.iter().map(|p| p.trim()).collect::<Vec<_>>().join("\n\n")
+    } else {
+        let mut text = String::new();
+        match file {
+            Some(file) if file.as_os_str() != "-" => text = read_to_string(file)?,
+            _ => {
+                stdin().read_to_string(&mut text)?;
+            }
+        }
+        text.trim_end().to_string()
+    };
+
+    let commit = create_commit(&Repo::load()?, &tree, &resolved, &message, &Signature::author()?, &Signature::committer()?)?;
+    println!("{}", commit);
+    Ok(())
+}
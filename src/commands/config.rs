@@ -0,0 +1,23 @@
+use anyhow::{anyhow, Result};
+
+use crate::utils::config::{get_config, set_config, unset_config};
+
+/// Reads, writes or removes a config entry
+pub fn run(key: String, value: Option<String>, global: bool, unset: bool) -> Result<()> {
+    if unset {
+        if !unset_config(&key, global)? {
+            return Err(anyhow!("No such config key: {}", key));
+        }
+        return Ok(());
+    }
+
+    match value {
+        Some(v) => set_config(&key, &v, global)?,
+        None => match get_config(&key)? {
+            Some(v) => println!("{}", v),
+            None => return Err(anyhow!("No such config key: {}", key)),
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::read;
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use colored::control::SHOULD_COLORIZE;
+
+use crate::commands::commit::read_current_commit;
+use crate::utils::diff::unified_diff;
+use crate::utils::history::commit_files;
+use crate::utils::index::Index;
+use crate::utils::object::{hash_file, read_blob_object, read_commit_object, ZERO_HASH};
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
+use crate::utils::revision::{resolve_revision, split_revisions};
+
+/// One side of a comparison: stored blobs, or tracked files read from disk
+pub enum Side {
+    Blobs(HashMap<PathBuf, String>),
+    Worktree(HashSet<PathBuf>),
+}
+
+impl Side {
+    pub fn of_commit(hash: &str) -> Result<Self> {
+        if hash == ZERO_HASH {
+            return Ok(Side::Blobs(HashMap::new()));
+        }
+        Ok(Side::Blobs(commit_files(&read_commit_object(hash)?)?))
+    }
+
+    pub fn of_index() -> Result<Self> {
+        let index = Index::load()?;
+        Ok(Side::Blobs(index.entries.into_iter().map(|(p, e)| (p, e.hash)).collect()))
+    }
+
+    /// Tracked files as they currently are on disk
+    pub fn of_worktree() -> Result<Self> {
+        Ok(Side::Worktree(Index::load()?.tracked_files().into_iter().filter(|p| p.is_file()).collect()))
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        match self {
+            Side::Blobs(map) => map.keys().cloned().collect(),
+            Side::Worktree(paths) => paths.iter().cloned().collect(),
+        }
+    }
+
+    fn hash(&self, repo: &Repo, path: &PathBuf) -> Result<Option<String>> {
+        match self {
+            Side::Blobs(map) => Ok(map.get(path).cloned()),
+            Side::Worktree(paths) if paths.contains(path) => Ok(Some(hash_file(repo, path)?)),
+            Side::Worktree(_) => Ok(None),
+        }
+    }
+
+    pub fn content(&self, path: &PathBuf) -> Result<Option<Vec<u8>>> {
+        match self {
+            Side::Blobs(map) => map.get(path).map(|h| read_blob_object(h)).transpose(),
+            Side::Worktree(paths) if paths.contains(path) => Ok(Some(read(path)?)),
+            Side::Worktree(_) => Ok(None),
+        }
+    }
+}
+
+/// Paths that differ between two sides, in sorted order
+pub fn changed_paths(repo: &Repo, old: &Side, new: &Side, spec: &Pathspec) -> Result<Vec<PathBuf>> {
+    let all: BTreeSet<PathBuf> = old.paths().into_iter().chain(new.paths()).collect();
+
+    let mut changed = Vec::new();
+    for path in all.into_iter().filter(|p| spec.matches(p)) {
+        if old.hash(repo, &path)? != new.hash(repo, &path)? {
+            changed.push(path);
+        }
+    }
+    Ok(changed)
+}
+
+/// `ink diff [--staged] [<commit> [<commit>]] [[--] <pathspec>...]`
+pub fn run(args: Vec<String>, paths: Vec<String>, staged: bool, name_only: bool) -> Result<()> {
+    let (revs, mut specs) = split_revisions(&args)?;
+    specs.extend(paths);
+    let spec = Pathspec::parse(&specs)?;
+    let repo = Repo::load()?;
+
+    let (old, new) = match (revs.as_slice(), staged) {
+        ([], false) => (Side::of_index()?, Side::of_worktree()?),
+        ([], true) => (Side::of_commit(&read_current_commit()?)?, Side::of_index()?),
+        ([rev], true) => (Side::of_commit(&resolve_revision(rev)?)?, Side::of_index()?),
+        ([rev], false) => (Side::of_commit(&resolve_revision(rev)?)?, Side::of_worktree()?),
+        ([a, b], false) => (Side::of_commit(&resolve_revision(a)?)?, Side::of_commit(&resolve_revision(b)?)?),
+        _ => return Err(anyhow!("Usage: ink diff [--staged] [<commit> [<commit>]] [<pathspec>...]")),
+    };
+
+    let color = SHOULD_COLORIZE.should_colorize();
+    for path in changed_paths(&repo, &old, &new, &spec)? {
+        if name_only {
+            println!("{}", path.display());
+            continue;
+        }
+
+        let before = old.content(&path)?;
+        let after = new.content(&path)?;
+        print!("{}", unified_diff(&path.to_string_lossy(), before.as_deref(), after.as_deref(), color));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use std::fs::{create_dir_all, write};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+
+use crate::commands::remote::{default_remote, is_remote};
+use crate::utils::hash::load_hash_algo;
+use crate::utils::object::{object_exists, ZERO_HASH};
+use crate::utils::remote::{Advertisement, FetchRequest};
+use crate::utils::repo::Repo;
+use crate::utils::revision::{check_ref_name, list_refs, resolve_ref};
+use crate::utils::transport::{connect, remote_url, Transport};
+
+/// What a fetch brought in
+pub struct Fetched {
+    pub adv: Advertisement,
+    pub received: usize,
+    /// Remote-tracking refs that were created or moved, as `(branch, old, new)`
+    pub updated: Vec<(String, String, String)>,
+}
+
+/// `ink fetch [<remote>]`
+pub fn run(remote: Option<String>) -> Result<()> {
+    let remote = match remote {
+        Some(r) => r,
+        None => default_remote()?,
+    };
+    let url = remote_url(&remote)?;
+    let tracking = is_remote(&remote)?.then_some(remote.as_str());
+    let fetched = fetch_with(&Repo::load()?, connect(&url)?.as_mut(), &url, tracking)?;
+
+    if fetched.updated.is_empty() && tracking.is_some() {
+        return Ok(());
+    }
+
+    println!("From {}", url);
+    match tracking {
+        Some(remote) => print_updates(remote, &fetched.updated),
+        None => {
+            for (name, _) in &fetched.adv.refs {
+                let branch = name.strip_prefix("refs/heads/").unwrap_or(name);
+                println!(" * branch            {:<16} -> FETCH_HEAD", branch);
+            }
+        }
+    }
+    if fetched.received > 0 {
+        println!("Received {} objects", fetched.received);
+    }
+
+    Ok(())
+}
+
+pub fn print_updates(remote: &str, updated: &[(String, String, String)]) {
+    for (branch, old, new) in updated {
+        let range = if old == ZERO_HASH {
+            "* [new branch]     ".to_string()
+        } else {
+            format!("  {}..{}", &old[..7], &new[..7])
+        };
+        println!(" {}  {:<16} -> {}/{}", range, branch, remote, branch);
+    }
+}
+
+/// Downloads every object the remote's branches need that we don't have yet,
+/// records the tips in FETCH_HEAD and, for a named remote, updates its
+/// remote-tracking refs under `refs/remotes/<remote>/`.
+pub fn fetch_with(repo: &Repo, transport: &mut dyn Transport, url: &str, remote: Option<&str>) -> Result<Fetched> {
+    let adv = transport.advertise()?;
+    check_hash_algo(&adv)?;
+    // Names come from the remote and become paths under .ink, so check them before anything is written
+    for name in adv.refs.iter().map(|(name, _)| name).chain(&adv.head) {
+        check_ref_name(name)?;
+    }
+
+    let mut wants: Vec<String> = adv.refs.iter().map(|(_, hash)| hash.clone()).filter(|h| !object_exists(h)).collect();
+    wants.sort();
+    wants.dedup();
+
+    let mut received = 0;
+    if !wants.is_empty() {
+        let haves = list_refs("refs/heads")?
+            .into_iter()
+            .chain(list_refs("refs/remotes")?)
+            .map(|(_, hash)| hash)
+            .collect();
+        received = transport.fetch(repo, &FetchRequest { wants: wants.clone(), haves })?.objects;
+
+        if let Some(missing) = wants.iter().find(|w| !object_exists(w)) {
+            return Err(anyhow!("Remote did not send commit {}", missing));
+        }
+    }
+
+    write_fetch_head(&adv, url)?;
+
+    let mut updated = Vec::new();
+    if let Some(remote) = remote {
+        for (name, hash) in &adv.refs {
+            let branch = name.strip_prefix("refs/heads/").unwrap_or(name);
+            if let Some(old) = update_tracking_ref(remote, branch, hash)? {
+                updated.push((branch.to_string(), old, hash.clone()));
+            }
+        }
+    }
+
+    Ok(Fetched { adv, received, updated })
+}
+
+/// Points `refs/remotes/<remote>/<branch>` at `hash`, returning the old value if it changed
+pub fn update_tracking_ref(remote: &str, branch: &str, hash: &str) -> Result<Option<String>> {
+    let name = format!("remotes/{}/{}", remote, branch);
+    check_ref_name(&name)?;
+    let old = resolve_ref(&name)?.unwrap_or_else(|| ZERO_HASH.to_string());
+    if old == hash {
+        return Ok(None);
+    }
+
+    let path = Path::new(".ink").join("refs").join(&name);
+    create_dir_all(path.parent().unwrap())?;
+    write(path, hash)?;
+    Ok(Some(old))
+}
+
+pub fn check_hash_algo(adv: &Advertisement) -> Result<()> {
+    let local = load_hash_algo()?.to_string();
+    if adv.hash_algo != local {
+        return Err(anyhow!(
+            "Remote uses the {} hash algorithm but this repository uses {}",
+            adv.hash_algo,
+            local
+        ));
+    }
+    Ok(())
+}
+
+/// FETCH_HEAD lists the fetched tips, the remote's HEAD branch first
+fn write_fetch_head(adv: &Advertisement, url: &str) -> Result<()> {
+    let mut refs: Vec<&(String, String)> = adv.refs.iter().collect();
+    refs.sort_by_key(|(name, _)| Some(name) != adv.head.as_ref());
+
+    let lines: String = refs
+        .iter()
+        .map(|(name, hash)| {
+            let branch = name.strip_prefix("refs/heads/").unwrap_or(name);
+            format!("{}\t\tbranch '{}' of {}\n", hash, branch, url)
+        })
+        .collect();
+
+    write(".ink/FETCH_HEAD", lines)?;
+    Ok(())
+}
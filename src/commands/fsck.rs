@@ -0,0 +1,313 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{read_to_string, File};
+use std::io::{copy, Read};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use crate::commands::branch::read_current_branch;
+use crate::utils::index::Index;
+use crate::utils::object::{is_hex, list_objects, object_path, ZERO_HASH};
+use crate::utils::repo::Repo;
+use crate::utils::revision::list_refs;
+use crate::utils::signature::Signature;
+use crate::utils::zip::decompress_reader;
+
+/// An object that parsed cleanly, with the objects it points to
+struct Checked {
+    kind: String,
+    links: Vec<(&'static str, String)>,
+}
+
+/// `ink fsck [--unreachable]`
+pub fn run(unreachable: bool) -> Result<()> {
+    // Load the settings once up front so a bad config fails here, not for every object
+    let repo = Repo::load()?;
+    println!("Checking object store with {}", repo.hash);
+    let names = list_objects()?;
+
+    let results: Vec<(String, Result<Checked>)> = names
+        .into_par_iter()
+        .map(|hash| {
+            let checked = check_object(&hash, &repo);
+            (hash, checked)
+        })
+        .collect();
+
+    let mut errors: Vec<String> = Vec::new();
+    let mut objects: HashMap<String, Checked> = HashMap::new();
+    for (hash, result) in results {
+        match result {
+            Ok(checked) => {
+                objects.insert(hash, checked);
+            }
+            Err(e) => errors.push(format!("error: object {}: {}", hash, e)),
+        }
+    }
+
+    // Links between objects
+    let mut referenced: HashSet<&str> = HashSet::new();
+    for (hash, checked) in &objects {
+        for (kind, target) in &checked.links {
+            referenced.insert(target);
+            match objects.get(target) {
+                None => errors.push(format!("missing {} {} (from {} {})", kind, target, checked.kind, hash)),
+                Some(found) if found.kind != *kind => errors.push(format!(
+                    "error: {} {} points to {} as a {}, but it is a {}",
+                    checked.kind, hash, target, kind, found.kind
+                )),
+                _ => {}
+            }
+        }
+    }
+
+    // Refs and index entries must name existing objects; they are also where reachability starts
+    let mut roots: Vec<String> = Vec::new();
+    for (name, target) in ref_tips()? {
+        match objects.get(&target) {
+            Some(found) if found.kind == "commit" => roots.push(target),
+            Some(found) => errors.push(format!("error: {}: points to a {} ({}), not a commit", name, found.kind, target)),
+            None => errors.push(format!("error: {}: invalid pointer {}", name, target)),
+        }
+    }
+    for (name, index) in indexes(&mut errors)? {
+        let mut paths: Vec<_> = index.entries.values().collect();
+        paths.sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in paths {
+            match objects.get(&entry.hash) {
+                Some(found) if found.kind == "blob" => roots.push(entry.hash.clone()),
+                _ => errors.push(format!(
+                    "error: {}: entry '{}' points to missing blob {}",
+                    name,
+                    entry.path.display(),
+                    entry.hash
+                )),
+            }
+        }
+    }
+    roots.extend(reflog_tips()?.into_iter().filter(|h| objects.contains_key(h)));
+
+    let mut reachable: HashSet<&str> = HashSet::new();
+    let mut stack: Vec<&str> = roots.iter().map(String::as_str).collect();
+    while let Some(hash) = stack.pop() {
+        if !reachable.insert(hash) {
+            continue;
+        }
+        if let Some(checked) = objects.get(hash) {
+            stack.extend(checked.links.iter().map(|(_, target)| target.as_str()));
+        }
+    }
+
+    // Dangling objects are unreachable ones that nothing else points to either
+    let mut loose: Vec<(&String, &Checked)> = objects.iter().filter(|(h, _)| !reachable.contains(h.as_str())).collect();
+    loose.sort_by(|a, b| a.0.cmp(b.0));
+    for (hash, checked) in &loose {
+        if !referenced.contains(hash.as_str()) {
+            println!("dangling {} {}", checked.kind, hash);
+        } else if unreachable {
+            println!("unreachable {} {}", checked.kind, hash);
+        }
+    }
+
+    errors.sort();
+    for error in &errors {
+        println!("{}", error);
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow!("fsck found {} problem(s)", errors.len()));
+    }
+    Ok(())
+}
+
+/// Decompresses, rehashes and parses one object
+fn check_object(hash: &str, repo: &Repo) -> Result<Checked> {
+    let file = File::open(object_path(hash))?;
+    let mut reader = decompress_reader(file).map_err(|e| anyhow!("cannot decompress: {}", e))?;
+    let cannot = |e: std::io::Error| anyhow!("cannot decompress: {}", e);
+
+    // Read the header byte by byte so it is hashed exactly as stored
+    let mut header = Vec::new();
+    let mut byte = [0u8];
+    while reader.read(&mut byte).map_err(cannot)? == 1 && byte[0] != 0 && header.len() <= 64 {
+        header.push(byte[0]);
+    }
+    if byte[0] != 0 {
+        return Err(anyhow!("missing header"));
+    }
+    let mut hasher = repo.hasher();
+    hasher.update(&header);
+    hasher.update(&[0]);
+
+    let header = std::str::from_utf8(&header).map_err(|_| anyhow!("header is not UTF-8"))?;
+    let (kind, len) = header.split_once(' ').ok_or_else(|| anyhow!("malformed header '{}'", header))?;
+
+    // Blobs can be large, so they are hashed as they are read; trees and commits are parsed whole
+    let mut body = Vec::new();
+    let size = if kind == "blob" {
+        copy(&mut reader, &mut hasher).map_err(cannot)?
+    } else {
+        reader.read_to_end(&mut body).map_err(cannot)?;
+        hasher.update(&body);
+        body.len() as u64
+    };
+
+    let actual = hasher.finish();
+    if actual != hash {
+        return Err(anyhow!("hash mismatch, content hashes to {}", actual));
+    }
+    if len.parse::<u64>().ok() != Some(size) {
+        return Err(anyhow!("header says {} bytes, body has {}", len, size));
+    }
+
+    let links = match kind {
+        "blob" => Vec::new(),
+        "tree" => check_tree(&body)?,
+        "commit" => check_commit(&body)?,
+        other => return Err(anyhow!("unknown object type '{}'", other)),
+    };
+    Ok(Checked { kind: kind.to_string(), links })
+}
+
+fn check_tree(body: &[u8]) -> Result<Vec<(&'static str, String)>> {
+    let text = std::str::from_utf8(body).map_err(|_| anyhow!("tree is not UTF-8"))?;
+    let mut links = Vec::new();
+    let mut names: HashSet<&str> = HashSet::new();
+
+    for line in text.lines() {
+        let (meta, name) = line.split_once('\t').ok_or_else(|| anyhow!("malformed tree line '{}'", line))?;
+        let parts: Vec<&str> = meta.split(' ').collect();
+        let [mode, kind, target] = parts[..] else {
+            return Err(anyhow!("malformed tree line '{}'", line));
+        };
+
+        let kind = match (mode, kind) {
+            ("100644" | "100755", "blob") => "blob",
+            ("040000", "tree") => "tree",
+            _ => return Err(anyhow!("bad mode '{}' for {} entry '{}'", mode, kind, name)),
+        };
+        if !is_hash(target) {
+            return Err(anyhow!("bad hash '{}' for entry '{}'", target, name));
+        }
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(anyhow!("bad entry name '{}'", name));
+        }
+        if !names.insert(name) {
+            return Err(anyhow!("duplicate entry '{}'", name));
+        }
+        links.push((kind, target.to_string()));
+    }
+
+    Ok(links)
+}
+
+fn check_commit(body: &[u8]) -> Result<Vec<(&'static str, String)>> {
+    let text = std::str::from_utf8(body).map_err(|_| anyhow!("commit is not UTF-8"))?;
+    let (headers, _) = text.split_once("\n\n").ok_or_else(|| anyhow!("no blank line before the message"))?;
+
+    let mut links = Vec::new();
+    let (mut tree, mut author, mut committer) = (false, false, false);
+    for line in headers.lines() {
+        let (key, value) = line.split_once(' ').ok_or_else(|| anyhow!("malformed header line '{}'", line))?;
+        match key {
+            "tree" if !tree && links.is_empty() => {
+                if !is_hash(value) {
+                    return Err(anyhow!("bad tree hash '{}'", value));
+                }
+                tree = true;
+                links.push(("tree", value.to_string()));
+            }
+            "parent" if tree && !author => {
+                if !is_hash(value) {
+                    return Err(anyhow!("bad parent hash '{}'", value));
+                }
+                // Root commits record the zero hash as their parent
+                if value != ZERO_HASH {
+                    links.push(("commit", value.to_string()));
+                }
+            }
+            "author" if tree && !author => {
+                Signature::parse(value).map_err(|e| anyhow!("bad author: {}", e))?;
+                author = true;
+            }
+            "committer" if author && !committer => {
+                Signature::parse(value).map_err(|e| anyhow!("bad committer: {}", e))?;
+                committer = true;
+            }
+            _ => return Err(anyhow!("unexpected header line '{}'", line)),
+        }
+    }
+
+    if !tree || !author {
+        return Err(anyhow!("missing {} line", if tree { "author" } else { "tree" }));
+    }
+    Ok(links)
+}
+
+/// HEAD plus every branch, remote-tracking branch, tag and autosave ref, and
+/// the commits an interrupted rebase or cherry-pick would return to
+fn ref_tips() -> Result<Vec<(String, String)>> {
+    let mut tips = Vec::new();
+
+    let branch = read_current_branch()?;
+    let head = Path::new(".ink/refs/heads").join(&branch);
+    if head.is_file() {
+        tips.push(("HEAD".to_string(), read_to_string(head)?.trim().to_string()));
+    }
+
+    for prefix in ["refs/heads", "refs/remotes", "refs/tags", "refs/autosave"] {
+        tips.extend(list_refs(prefix)?);
+    }
+
+    for state in [".ink/rebase-merge/orig-head", ".ink/rebase-merge/onto", ".ink/sequencer/head"] {
+        if let Ok(hash) = read_to_string(state) {
+            tips.push((state.trim_start_matches(".ink/").to_string(), hash.trim().to_string()));
+        }
+    }
+
+    Ok(tips)
+}
+
+/// The index and the saved per-branch indexes; unreadable ones are reported in `errors`
+fn indexes(errors: &mut Vec<String>) -> Result<Vec<(String, Index)>> {
+    let mut out = vec![("index".to_string(), Index::load()?)];
+
+    let dir = Path::new(".ink/refs/INDEXES");
+    let mut branches: Vec<String> = Vec::new();
+    if dir.is_dir() {
+        for entry in WalkDir::new(dir).sort_by_file_name() {
+            let entry = entry?;
+            // Freshly created branches have an empty placeholder instead of an index
+            if entry.file_type().is_file() && entry.metadata()?.len() > 0 {
+                branches.push(entry.path().strip_prefix(dir)?.to_string_lossy().into_owned());
+            }
+        }
+    }
+    for branch in branches {
+        match Index::load_for_branch(&branch) {
+            Ok(index) => out.push((format!("refs/INDEXES/{}", branch), index)),
+            Err(e) => errors.push(format!("error: refs/INDEXES/{}: cannot decode index: {}", branch, e)),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Commits recorded in the reflogs, which keep rewritten history from counting as dangling
+fn reflog_tips() -> Result<Vec<String>> {
+    let mut tips = Vec::new();
+    for entry in walkdir::WalkDir::new(".ink/logs").into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        for line in read_to_string(entry.path())?.lines() {
+            tips.extend(line.split(' ').take(2).filter(|h| is_hash(h) && *h != ZERO_HASH).map(String::from));
+        }
+    }
+    Ok(tips)
+}
+
+fn is_hash(s: &str) -> bool {
+    s.len() == ZERO_HASH.len() && is_hex(s)
+}
@@ -0,0 +1,30 @@
+use std::fs::read;
+use std::io::{stdin, Read};
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+
+use crate::utils::object::{create_blob_from_bytes, hash_blob};
+use crate::utils::repo::Repo;
+
+/// `ink hash-object [-w] [--stdin] [<file>...]`: prints the blob hash of each
+/// input, storing it in the object database with `-w`
+pub fn run(files: Vec<PathBuf>, use_stdin: bool, write: bool) -> Result<()> {
+    if files.is_empty() && !use_stdin {
+        return Err(anyhow!("Nothing to hash; pass files or --stdin"));
+    }
+
+    let repo = Repo::load()?;
+    let hash = |content: &[u8]| if write { create_blob_from_bytes(&repo, content) } else { Ok(hash_blob(&repo, content)) };
+
+    if use_stdin {
+        let mut content = Vec::new();
+        stdin().read_to_end(&mut content)?;
+        println!("{}", hash(&content)?);
+    }
+    for file in files {
+        let content = read(&file).map_err(|e| anyhow!("Cannot read '{}': {}", file.display(), e))?;
+        println!("{}", hash(&content)?);
+    }
+
+    Ok(())
+}
@@ -5,6 +5,7 @@ use std::fs::{create_dir, create_dir_all, write};
 use crate::utils::enums::HashAlgo;
 use crate::utils::hash::save_hash_algo;
 use crate::utils::zip::save_is_zip;
+#[cfg(target_os = "windows")]
 use crate::utils::dir::hide_folder_windows;
 
 pub fn run(h: HashAlgo, z: bool) -> Result<()> {
@@ -21,6 +22,7 @@ pub fn run(h: HashAlgo, z: bool) -> Result<()> {
     }
 
     create_dir_all(root.join("objects"))?;
+    create_dir_all(root.join("hooks"))?;
     create_dir_all(root.join("refs").join("heads"))?;
     create_dir_all(root.join("refs").join("INDEXES"))?;
     create_dir_all(root.join("logs").join("refs").join("heads"))?;
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::fs::{metadata, read_dir, read_to_string, remove_file, write};
+use std::path::Path;
+use anyhow::Result;
+
+use crate::commands::remote::default_remote;
+use crate::utils::history::{collect_tree_objects, reachable_commits};
+use crate::utils::index::Index;
+use crate::utils::lfs::{
+    fetch_missing, files_in, list_stored, manifest, pointers_in, read_pointer_file, smudge, store_path, Pointer,
+    PATTERNS_FILE,
+};
+use crate::utils::object::{hash_blob, read_commit_object};
+use crate::utils::repo::Repo;
+use crate::utils::revision::{list_refs, resolve_revision};
+use crate::utils::transport::{connect, remote_url};
+
+/// Subcommands of `ink lfs`
+pub enum LfsAction {
+    Track { patterns: Vec<String> },
+    Untrack { patterns: Vec<String> },
+    Ls { rev: Option<String> },
+    Fetch { remote: Option<String>, all: bool },
+    Prune { dry_run: bool },
+}
+
+pub fn run(action: LfsAction) -> Result<()> {
+    match action {
+        LfsAction::Track { patterns } => track(&patterns),
+        LfsAction::Untrack { patterns } => untrack(&patterns),
+        LfsAction::Ls { rev } => ls(rev.as_deref().unwrap_or("HEAD")),
+        LfsAction::Fetch { remote, all } => fetch(remote, all),
+        LfsAction::Prune { dry_run } => prune(dry_run),
+    }
+}
+
+/// `ink lfs track [<pattern>...]`; without patterns, lists the tracked ones
+fn track(patterns: &[String]) -> Result<()> {
+    let mut lines = read_patterns();
+    if patterns.is_empty() {
+        println!("Listing tracked patterns");
+        for line in lines.iter().filter(|l| !l.trim().is_empty() && !l.starts_with('#')) {
+            println!("    {} ({})", line, PATTERNS_FILE);
+        }
+        return Ok(());
+    }
+
+    for pattern in patterns {
+        if lines.contains(pattern) {
+            println!("'{}' is already tracked", pattern);
+        } else {
+            lines.push(pattern.clone());
+            println!("Tracking '{}'", pattern);
+        }
+    }
+    write_patterns(&lines)
+}
+
+/// `ink lfs untrack <pattern>...`
+fn untrack(patterns: &[String]) -> Result<()> {
+    let mut lines = read_patterns();
+    for pattern in patterns {
+        if lines.contains(pattern) {
+            lines.retain(|l| l != pattern);
+            println!("Untracking '{}'", pattern);
+        } else {
+            println!("'{}' is not tracked", pattern);
+        }
+    }
+    write_patterns(&lines)
+}
+
+fn read_patterns() -> Vec<String> {
+    read_to_string(PATTERNS_FILE)
+        .map(|text| text.lines().map(|l| l.trim_end().to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn write_patterns(lines: &[String]) -> Result<()> {
+    write(PATTERNS_FILE, lines.iter().map(|l| format!("{}\n", l)).collect::<String>())?;
+    Ok(())
+}
+
+/// `ink lfs ls [<rev>]`: the large files in a commit, marked `*` when their
+/// content is stored locally and `-` when it still has to be fetched
+fn ls(rev: &str) -> Result<()> {
+    for (path, pointer) in files_in(&resolve_revision(rev)?)? {
+        let marker = if pointer.is_present() { '*' } else { '-' };
+        println!("{} {} {} ({} bytes)", &pointer.oid[..10], marker, path.display(), pointer.size);
+    }
+    Ok(())
+}
+
+/// `ink lfs fetch [--all] [<remote>]`: downloads the large files the index
+/// refers to (with `--all`, those of every branch tip too) and writes them over
+/// the pointers left in the working tree
+fn fetch(remote: Option<String>, all: bool) -> Result<()> {
+    let remote = match remote {
+        Some(r) => r,
+        None => default_remote()?,
+    };
+
+    let repo = Repo::load()?;
+    let index = Index::load()?;
+    let mut wanted = pointers_in(index.entries.values().map(|e| &e.hash))?;
+    if all {
+        for prefix in ["refs/heads", "refs/remotes", "refs/tags"] {
+            for (_, tip) in list_refs(prefix)? {
+                wanted.extend(files_in(&tip)?.into_iter().map(|(_, pointer)| pointer));
+            }
+        }
+    }
+
+    let wanted: Vec<Pointer> = wanted.into_iter().collect();
+    let missing = wanted.iter().filter(|p| !p.is_present()).count();
+    let received = if missing > 0 {
+        let mut transport = connect(&remote_url(&remote)?)?;
+        fetch_missing(&repo, transport.as_mut(), &wanted)?
+    } else {
+        0
+    };
+    println!("Fetched {} large file(s) from {}", received, remote);
+    if received < missing {
+        println!("warning: {} large file(s) are not available from {}", missing - received, remote);
+    }
+
+    // Only pointer files that still match the index are replaced, so edits are never lost
+    let mut updated = 0;
+    for entry in index.entries.values() {
+        if let Ok(Some(pointer)) = read_pointer_file(&entry.path)
+            && pointer.is_present()
+            && hash_blob(&repo, pointer.to_text().as_bytes()) == entry.hash
+        {
+            smudge(&pointer, &entry.path)?;
+            updated += 1;
+        }
+    }
+    if updated > 0 {
+        println!("Updated {} file(s) in the working tree", updated);
+    }
+    Ok(())
+}
+
+/// `ink lfs prune [--dry-run]`: deletes stored large files that no commit
+/// reachable from a ref, no saved branch index and not the index refer to
+fn prune(dry_run: bool) -> Result<()> {
+    let mut tips = Vec::new();
+    for prefix in ["refs/heads", "refs/remotes", "refs/tags", "refs/autosave"] {
+        tips.extend(list_refs(prefix)?.into_iter().map(|(_, hash)| hash));
+    }
+
+    let mut objects: HashSet<String> = HashSet::new();
+    for commit in reachable_commits(&tips)? {
+        collect_tree_objects(&read_commit_object(&commit)?.tree, &mut objects)?;
+    }
+    objects.extend(Index::load()?.entries.into_values().map(|e| e.hash));
+    let dir = Path::new(".ink/refs/INDEXES");
+    if dir.is_dir() {
+        for entry in read_dir(dir)? {
+            // Freshly created branches have an empty placeholder instead of an index
+            if let Ok(index) = Index::load_for_branch(&entry?.file_name().to_string_lossy()) {
+                objects.extend(index.entries.into_values().map(|e| e.hash));
+            }
+        }
+    }
+    let keep: HashSet<String> = pointers_in(&objects)?.into_iter().map(|p| p.oid).collect();
+
+    let mut pruned = 0;
+    let mut used: HashSet<String> = HashSet::new();
+    for oid in list_stored("objects")? {
+        if keep.contains(&oid) {
+            used.extend(manifest(&oid)?);
+            continue;
+        }
+        pruned += 1;
+        if dry_run {
+            println!("Would prune {}", oid);
+        } else {
+            remove_file(store_path("objects", &oid))?;
+        }
+    }
+
+    let mut freed = 0;
+    for chunk in list_stored("chunks")? {
+        if used.contains(&chunk) {
+            continue;
+        }
+        let path = store_path("chunks", &chunk);
+        freed += metadata(&path)?.len();
+        if !dry_run {
+            remove_file(path)?;
+        }
+    }
+
+    let verb = if dry_run { "Would prune" } else { "Pruned" };
+    println!("{} {} large file(s), {} bytes", verb, pruned, freed);
+    Ok(())
+}
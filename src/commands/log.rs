@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use crate::commands::branch::read_current_branch;
+use crate::commands::commit::read_current_commit;
+use crate::utils::graph::{layout, GraphLine};
+use crate::utils::history::{commit_touches, simplify_parents, sort_commits, walk_commits, Order};
+use crate::utils::object::{Commit, ZERO_HASH};
+use crate::utils::pathspec::Pathspec;
+use crate::utils::revision::{list_refs, ref_decorations, resolve_revision, split_revisions};
+
+/// Options accepted by `ink log`
+#[derive(Debug)]
+pub struct LogOptions {
+    pub max_count: Option<usize>,
+    pub oneline: bool,
+    /// Draw the commit graph next to the log
+    pub graph: bool,
+    /// Show the branches and tags pointing at each commit
+    pub decorate: bool,
+    /// Start from every branch, remote-tracking branch and tag as well as HEAD
+    pub all: bool,
+    pub order: Order,
+}
+
+/// `ink log [--graph] [--decorate] [--all] [<revision>...] [[--] <pathspec>...]`
+pub fn run(args: Vec<String>, paths: Vec<String>, opts: LogOptions) -> Result<()> {
+    let (revs, mut specs) = split_revisions(&args)?;
+    specs.extend(paths);
+    let spec = Pathspec::parse(&specs)?;
+
+    let mut starts = revs.iter().map(|r| resolve_revision(r)).collect::<Result<Vec<_>>>()?;
+    if opts.all {
+        for prefix in ["refs/heads", "refs/remotes", "refs/tags"] {
+            starts.extend(list_refs(prefix)?.into_iter().map(|(_, hash)| hash));
+        }
+    }
+    if revs.is_empty() {
+        let head = read_current_commit()?;
+        if head != ZERO_HASH {
+            starts.push(head);
+        } else if starts.is_empty() {
+            return Err(anyhow!("The current branch does not have any commits yet"));
+        }
+    }
+
+    // A graph only makes sense when children are drawn above their parents
+    let order = if opts.graph && opts.order == Order::Walk { Order::Topo } else { opts.order };
+    let all = sort_commits(walk_commits(&starts)?, order);
+
+    let mut commits = Vec::new();
+    for (hash, commit) in &all {
+        if !spec.is_empty() && !commit_touches(commit, &spec)? {
+            continue;
+        }
+        commits.push((hash.clone(), commit.clone()));
+    }
+    if opts.graph && !spec.is_empty() {
+        simplify_parents(&mut commits, &all);
+    }
+    if let Some(max) = opts.max_count {
+        commits.truncate(max);
+    }
+
+    let decorations = Decorations {
+        names: if opts.decorate { ref_decorations()? } else { HashMap::new() },
+        local: list_refs("refs/heads")?
+            .into_iter()
+            .filter_map(|(name, _)| name.strip_prefix("refs/heads/").map(String::from))
+            .collect(),
+        head: (read_current_branch()?, read_current_commit()?),
+    };
+    let decorate = |hash: &str| format_decorations(hash, &decorations);
+
+    if !opts.graph {
+        for (hash, commit) in &commits {
+            print_entry(hash, commit, opts.oneline, &decorate(hash), "", "");
+        }
+        return Ok(());
+    }
+
+    for line in layout(&commits) {
+        match line {
+            GraphLine::Commit(i, prefix) => {
+                let (hash, commit) = &commits[i];
+                // Body lines continue every lane, and this commit's own lane if it has parents
+                let below = if commit.parents.is_empty() { " " } else { "|" };
+                let continuation = prefix.replace('*', below);
+                print_entry(hash, commit, opts.oneline, &decorate(hash), &prefix, &continuation);
+            }
+            GraphLine::Edge(row) => println!("{}", row.trim_end()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Ref names to print next to commits, plus what is needed to colour them
+struct Decorations {
+    names: HashMap<String, Vec<String>>,
+    local: HashSet<String>,
+    /// Current branch and the commit it points at
+    head: (String, String),
+}
+
+fn print_entry(hash: &str, commit: &Commit, oneline: bool, refs: &str, prefix: &str, continuation: &str) {
+    if oneline {
+        println!("{}{}{} {}", prefix, hash[..7].yellow(), refs, commit.message.lines().next().unwrap_or(""));
+        return;
+    }
+
+    println!("{}{}{}", prefix, format!("commit {}", hash).yellow(), refs);
+    let mut lines = Vec::new();
+    if commit.parents.len() > 1 {
+        let short: Vec<&str> = commit.parents.iter().map(|p| &p[..7]).collect();
+        lines.push(format!("Merge: {}", short.join(" ")));
+    }
+    lines.push(format!("Author: {} <{}>", commit.author.name, commit.author.email));
+    lines.push(format!("Date:   {}", commit.author.datetime().format("%a %b %-d %H:%M:%S %Y %z")));
+    lines.push(String::new());
+    lines.extend(commit.message.lines().map(|line| format!("    {}", line)));
+    lines.push(String::new());
+
+    for line in lines {
+        println!("{}{}", continuation, line);
+    }
+}
+
+/// ` (HEAD -> main, origin/main, tag: v1)`, or nothing when no ref points at `hash`
+fn format_decorations(hash: &str, decorations: &Decorations) -> String {
+    let Some(names) = decorations.names.get(hash) else {
+        return String::new();
+    };
+
+    let mut parts: Vec<String> = Vec::new();
+    let (branch, head_commit) = &decorations.head;
+    if hash == head_commit && names.contains(branch) {
+        parts.push(format!("{} -> {}", "HEAD".cyan().bold(), branch.green().bold()));
+    }
+    for name in names {
+        if name == branch && hash == head_commit {
+            continue;
+        }
+        parts.push(if name.starts_with("tag: ") {
+            name.yellow().bold().to_string()
+        } else if !decorations.local.contains(name) {
+            name.red().bold().to_string()
+        } else {
+            name.green().bold().to_string()
+        });
+    }
+
+    format!(" {}{}{}", "(".yellow(), parts.join(&", ".yellow().to_string()), ")".yellow())
+}
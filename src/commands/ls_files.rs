@@ -0,0 +1,27 @@
+use std::io::{stdout, Write};
+use anyhow::Result;
+
+use crate::utils::index::Index;
+use crate::utils::pathspec::Pathspec;
+
+/// `ink ls-files [-s] [-z] [<pathspec>...]`: lists staged paths in sorted order,
+/// with `-s` also showing the mode and blob hash
+pub fn run(specs: Vec<String>, stage: bool, nul: bool) -> Result<()> {
+    let spec = Pathspec::parse(&specs)?;
+    let index = Index::load()?;
+
+    let mut entries: Vec<_> = index.entries.values().filter(|e| spec.matches(&e.path)).collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let end = if nul { '\0' } else { '\n' };
+    let mut out = stdout().lock();
+    for entry in entries {
+        if stage {
+            write!(out, "100644 {}\t{}{}", entry.hash, entry.path.display(), end)?;
+        } else {
+            write!(out, "{}{}", entry.path.display(), end)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
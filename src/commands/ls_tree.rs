@@ -0,0 +1,61 @@
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+use crate::utils::object::{parse_raw_object, read_commit_object, read_raw_object, read_tree_object};
+use crate::utils::revision::resolve_object;
+
+pub struct LsTreeOptions {
+    pub recursive: bool,
+    /// Show trees themselves when recursing
+    pub trees: bool,
+    pub name_only: bool,
+    /// Terminate entries with NUL instead of a newline
+    pub nul: bool,
+}
+
+/// `ink ls-tree [-r] [-t] [--name-only] [-z] <tree-ish> [<path>...]`
+pub fn run(tree_ish: String, paths: Vec<PathBuf>, opts: LsTreeOptions) -> Result<()> {
+    let hash = resolve_object(&tree_ish)?;
+    let tree = match parse_raw_object(&read_raw_object(&hash)?)?.0 {
+        "tree" => hash,
+        "commit" => read_commit_object(&hash)?.tree,
+        other => return Err(anyhow!("'{}' is a {}, not a tree", tree_ish, other)),
+    };
+
+    let paths: Vec<PathBuf> = paths.iter().map(|p| p.components().collect()).collect();
+    let mut out = stdout().lock();
+    list(&tree, Path::new(""), &paths, &opts, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn list(tree: &str, prefix: &Path, filters: &[PathBuf], opts: &LsTreeOptions, out: &mut impl Write) -> Result<()> {
+    for (name, kind, hash) in read_tree_object(tree)? {
+        let path = prefix.join(&name);
+        let matches = filters.is_empty() || filters.iter().any(|f| path.starts_with(f));
+        // Trees leading to a requested path are entered without being shown
+        let leads_to_filter = filters.iter().any(|f| f.starts_with(&path) && *f != path);
+
+        if kind == "tree" && (leads_to_filter || (matches && opts.recursive)) {
+            if matches && opts.trees {
+                print_entry(out, &kind, &hash, &path, opts)?;
+            }
+            list(&hash, &path, filters, opts, out)?;
+        } else if matches {
+            print_entry(out, &kind, &hash, &path, opts)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_entry(out: &mut impl Write, kind: &str, hash: &str, path: &Path, opts: &LsTreeOptions) -> Result<()> {
+    let end = if opts.nul { '\0' } else { '\n' };
+    if opts.name_only {
+        write!(out, "{}{}", path.display(), end)?;
+    } else {
+        let mode = if kind == "tree" { "040000" } else { "100644" };
+        write!(out, "{} {} {}\t{}{}", mode, kind, hash, path.display(), end)?;
+    }
+    Ok(())
+}
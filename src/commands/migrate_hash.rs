@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, read, read_to_string, remove_dir_all, remove_file, rename, write};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use bincode::config::standard;
+use bincode::serde::{decode_from_slice, encode_to_vec};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::commands::rebase;
+use crate::utils::enums::HashAlgo;
+use crate::utils::hash::{
+    hash_with, load_configured_algo, load_store_algo, save_hash_algo, save_store_algo, StreamHasher,
+};
+use crate::utils::index::Index;
+use crate::utils::object::{list_objects, open_object, parse_raw_object, read_raw_object, write_blob_from, ZERO_HASH};
+use crate::utils::repo::Repo;
+use crate::utils::sequencer;
+use crate::utils::watch;
+
+/// Lines of `<new> <old>`, so hashes quoted in messages or scripts can still be looked up
+pub const HASH_MAP: &str = ".ink/hash-map";
+
+/// The converted store is built here and swapped in once complete
+const NEW_OBJECTS: &str = ".ink/objects-migrate";
+
+/// Where the old store is moved during the swap, until the swap is complete
+const OLD_OBJECTS: &str = ".ink/objects-old";
+
+/// Written once the new store is complete and removed when the migration is done
+const JOURNAL: &str = ".ink/MIGRATE_HASH";
+
+/// `ink migrate-hash <algo>`: rewrites every object under the new algorithm and
+/// updates refs, indexes and reflogs to the new names
+pub fn run(to: HashAlgo) -> Result<()> {
+    if let Some(journal) = Journal::load()? {
+        if journal.to != to.to_string() {
+            return Err(anyhow!(
+                "A migration to {} was interrupted; run 'ink migrate-hash {}' to finish it first",
+                journal.to,
+                journal.to
+            ));
+        }
+        println!("Finishing the interrupted migration to {}...", to);
+        journal.apply()?;
+        println!("Done; old names are listed in {}", HASH_MAP);
+        return Ok(());
+    }
+
+    // Older repositories only record the algorithm in the config
+    let from = load_store_algo()?.map_or_else(load_configured_algo, Ok)?;
+    if from == to {
+        return Err(anyhow!("The repository already uses {}", to));
+    }
+    let repo = Repo::load()?;
+    if rebase::in_progress(&repo)?.is_some() || sequencer::in_progress(&repo)?.is_some() {
+        return Err(anyhow!("A rebase, cherry-pick or revert is in progress; finish or abort it first"));
+    }
+    if watch::running().is_some() {
+        return Err(anyhow!("Stop 'ink watch' before migrating"));
+    }
+
+    println!("Migrating from {} to {}...", from, to);
+    if Path::new(NEW_OBJECTS).exists() {
+        remove_dir_all(NEW_OBJECTS)?;
+    }
+
+    let map = convert_objects(from, to)?;
+
+    // Work out every rewritten file before touching any of them
+    let mut files: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+    for entry in WalkDir::new(".ink/refs").into_iter().filter_entry(|e| e.file_name() != "INDEXES") {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let old = read_to_string(entry.path())?;
+            files.push((entry.into_path(), map_strict(&map, old.trim(), "ref")?.into_bytes()));
+        }
+    }
+    files.push((PathBuf::from(".ink/index"), map_index(&map)?.into_bytes()));
+    if let Ok(text) = read_to_string(".ink/FETCH_HEAD") {
+        files.push((PathBuf::from(".ink/FETCH_HEAD"), map_fields(&map, &text, 1).into_bytes()));
+    }
+    for entry in WalkDir::new(".ink/logs") {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let text = read_to_string(entry.path())?;
+            files.push((entry.into_path(), map_fields(&map, &text, 2).into_bytes()));
+        }
+    }
+    let dir = Path::new(".ink/refs/INDEXES");
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        // Freshly created branches have an empty placeholder instead of an index
+        if !entry.file_type().is_file() || entry.metadata()?.len() == 0 {
+            continue;
+        }
+        let branch = entry.path().strip_prefix(dir)?.to_string_lossy().into_owned();
+        let mut index = Index::load_for_branch(&branch)
+            .map_err(|e| anyhow!("Cannot decode the saved index of branch '{}': {}", branch, e))?;
+        for entry in index.entries.values_mut() {
+            entry.hash = map_strict(&map, &entry.hash, "index entry")?;
+        }
+        files.push((entry.into_path(), encode_to_vec(&index, standard())?));
+    }
+
+    // Keep translations from earlier migrations pointing at the newest names
+    let mut table = String::new();
+    if let Ok(previous) = read_to_string(HASH_MAP) {
+        for line in previous.lines() {
+            if let Some((new, old)) = line.split_once(' ') {
+                let newest = map.get(new).map_or(new, String::as_str);
+                // Migrating back makes the original names current again
+                if newest != old {
+                    table += &format!("{} {}\n", newest, old);
+                }
+            }
+        }
+    }
+    let mut pairs: Vec<(&String, &String)> = map.iter().collect();
+    pairs.sort();
+    for (old, new) in pairs {
+        table += &format!("{} {}\n", new, old);
+    }
+    files.push((PathBuf::from(HASH_MAP), table.into_bytes()));
+
+    // An empty repository has nothing to convert, so the new store may not exist yet
+    create_dir_all(NEW_OBJECTS)?;
+    save_store_algo(Path::new(NEW_OBJECTS), to)?;
+
+    // From here on the journal says how to finish, so an interrupted swap can be completed
+    let journal = Journal { to: to.to_string(), files };
+    let tmp = Path::new(JOURNAL).with_extension("tmp");
+    write(&tmp, encode_to_vec(&journal, standard())?)?;
+    rename(&tmp, JOURNAL)?;
+    journal.apply()?;
+
+    println!("Rewrote {} objects; old names are listed in {}", map.len(), HASH_MAP);
+    Ok(())
+}
+
+/// Everything left to do once the new object store is complete
+#[derive(Serialize, Deserialize)]
+struct Journal {
+    to: String,
+    /// Rewritten refs, indexes, logs and the hash map, with their new contents
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl Journal {
+    fn load() -> Result<Option<Self>> {
+        if !Path::new(JOURNAL).exists() {
+            return Ok(None);
+        }
+        let (journal, _) = decode_from_slice(&read(JOURNAL)?, standard())
+            .map_err(|e| anyhow!("{} is corrupt ({}); the migration cannot be resumed", JOURNAL, e))?;
+        Ok(Some(journal))
+    }
+
+    /// Swaps in the new store and writes every file. Each step can be repeated,
+    /// so this also finishes a run that stopped part way through.
+    fn apply(&self) -> Result<()> {
+        if Path::new(NEW_OBJECTS).exists() {
+            if Path::new(".ink/objects").exists() {
+                rename(".ink/objects", OLD_OBJECTS)?;
+            }
+            rename(NEW_OBJECTS, ".ink/objects")?;
+        }
+        for (path, contents) in &self.files {
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)?;
+            }
+            write(path, contents)?;
+        }
+        save_hash_algo(HashAlgo::from_str(&self.to)?)?;
+        if Path::new(OLD_OBJECTS).exists() {
+            remove_dir_all(OLD_OBJECTS)?;
+        }
+        remove_file(JOURNAL)?;
+        Ok(())
+    }
+}
+
+/// Writes every object to the new store and returns the old-to-new mapping.
+/// Blobs are independent and streamed across in parallel; trees and commits
+/// are converted after everything they point to.
+fn convert_objects(from: HashAlgo, to: HashAlgo) -> Result<HashMap<String, String>> {
+    let names = list_objects()?;
+    let repo = Repo { hash: to, ..Repo::load()? };
+
+    let blobs: Vec<Option<(String, String)>> = names
+        .par_iter()
+        .map(|hash| {
+            let (kind, size, body) = open_object(hash)?;
+            if kind != "blob" {
+                return Ok(None);
+            }
+
+            // The old name is checked as the blob is copied, so it is read only once
+            let mut old = StreamHasher::new(from);
+            old.update(format!("blob {}\0", size).as_bytes());
+            let body = Tee { inner: body, hasher: &mut old };
+            let new = write_blob_from(&repo, Path::new(NEW_OBJECTS), size, body)?;
+            if old.finish() != *hash {
+                return Err(anyhow!("Object {} does not match its {} hash; run 'ink fsck'", hash, from));
+            }
+            Ok(Some((hash.clone(), new)))
+        })
+        .collect::<Result<_>>()?;
+    let mut map: HashMap<String, String> = blobs.into_iter().flatten().collect();
+
+    for name in &names {
+        let mut stack = vec![(name.clone(), false)];
+        while let Some((hash, expanded)) = stack.pop() {
+            if map.contains_key(&hash) {
+                continue;
+            }
+            let full = read_checked(&hash, from)?;
+            let (kind, body) = parse_raw_object(&full)?;
+            let body = std::str::from_utf8(body).map_err(|_| anyhow!("{} {} is not valid UTF-8; run 'ink fsck'", kind, hash))?;
+
+            if !expanded {
+                let pending: Vec<String> = links(kind, body).into_iter().filter(|h| !map.contains_key(h)).collect();
+                if !pending.is_empty() {
+                    stack.push((hash, true));
+                    stack.extend(pending.into_iter().map(|h| (h, false)));
+                    continue;
+                }
+            }
+
+            let body = rewrite(kind, body, &map)?;
+            let new = store(&repo, format!("{} {}\0{}", kind, body.len(), body).into_bytes())?;
+            map.insert(hash, new);
+        }
+    }
+
+    Ok(map)
+}
+
+/// Reads an object, making sure it really is named by the algorithm being migrated from
+fn read_checked(hash: &str, algo: HashAlgo) -> Result<Vec<u8>> {
+    let full = read_raw_object(hash)?;
+    if hash_with(algo, &full) != hash {
+        return Err(anyhow!("Object {} does not match its {} hash; run 'ink fsck'", hash, algo));
+    }
+    Ok(full)
+}
+
+/// Hashes everything read through it
+struct Tee<'a, R> {
+    inner: R,
+    hasher: &'a mut StreamHasher,
+}
+
+impl<R: Read> Read for Tee<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Stores a complete tree or commit in the new store
+fn store(repo: &Repo, full: Vec<u8>) -> Result<String> {
+    let hash = repo.hash(&full);
+    let path = Path::new(NEW_OBJECTS).join(&hash[..2]).join(&hash[2..]);
+    if !path.exists() {
+        create_dir_all(path.parent().unwrap())?;
+        write(path, repo.compress(full)?)?;
+    }
+    Ok(hash)
+}
+
+/// Hashes a tree or commit body refers to
+fn links(kind: &str, body: &str) -> Vec<String> {
+    match kind {
+        "tree" => body
+            .lines()
+            .filter_map(|line| line.split('\t').next()?.split(' ').nth(2))
+            .map(String::from)
+            .collect(),
+        "commit" => body
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.strip_prefix("tree ").or_else(|| line.strip_prefix("parent ")))
+            .filter(|hash| *hash != ZERO_HASH)
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A tree or commit body with every link renamed
+fn rewrite(kind: &str, body: &str, map: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(body.len());
+    let mut in_headers = true;
+
+    for line in body.split_inclusive('\n') {
+        let renamed = match kind {
+            "tree" => line.split_once('\t').and_then(|(meta, name)| {
+                let (rest, hash) = meta.rsplit_once(' ')?;
+                Some(format!("{} {}\t{}", rest, map.get(hash)?, name))
+            }),
+            "commit" if in_headers => ["tree ", "parent "].iter().find_map(|key| {
+                let hash = line.strip_prefix(key)?.trim_end();
+                let new = if hash == ZERO_HASH { ZERO_HASH } else { map.get(hash)? };
+                Some(format!("{}{}{}", key, new, &line[key.len() + hash.len()..]))
+            }),
+            _ => None,
+        };
+
+        if kind == "commit" && line == "\n" {
+            in_headers = false;
+        }
+        match renamed {
+            Some(renamed) => out += &renamed,
+            None if kind == "tree" || (in_headers && (line.starts_with("tree ") || line.starts_with("parent "))) => {
+                return Err(anyhow!("Cannot rewrite {} line '{}'; run 'ink fsck'", kind, line.trim_end()));
+            }
+            None => out += line,
+        }
+    }
+
+    Ok(out)
+}
+
+/// The new name for a hash that must exist; the zero hash stays as it is
+fn map_strict(map: &HashMap<String, String>, hash: &str, what: &str) -> Result<String> {
+    if hash.is_empty() || hash == ZERO_HASH {
+        return Ok(hash.to_string());
+    }
+    map.get(hash).cloned().ok_or_else(|| anyhow!("{} points to missing object {}; run 'ink fsck'", what, hash))
+}
+
+fn map_index(map: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::new();
+    for entry in Index::load()?.entries.values() {
+        let what = format!("Index entry '{}'", entry.path.display());
+        out += &format!("{} {}\n", map_strict(map, &entry.hash, &what)?, entry.path.display());
+    }
+    Ok(out)
+}
+
+/// Renames the first `count` space- or tab-separated fields of each line where
+/// they are known; history may mention objects that no longer exist
+fn map_fields(map: &HashMap<String, String>, text: &str, count: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let mut rest = line;
+        for _ in 0..count {
+            let end = rest.find([' ', '\t']).unwrap_or(rest.len());
+            let field = &rest[..end];
+            out += map.get(field).map_or(field, String::as_str);
+            rest = &rest[end..];
+            if !rest.is_empty() {
+                out += &rest[..1];
+                rest = &rest[1..];
+            }
+        }
+        out += rest;
+    }
+    out
+}
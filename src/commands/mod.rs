@@ -5,3 +5,36 @@ pub mod commit;
 pub mod branch;
 pub mod checkout;
 pub mod restore;
+pub mod config;
+pub mod check_ignore;
+pub mod status;
+pub mod diff;
+pub mod log;
+pub mod blame;
+pub mod remote;
+pub mod clone;
+pub mod fetch;
+pub mod push;
+pub mod pull;
+pub mod upload_pack;
+pub mod receive_pack;
+pub mod serve;
+pub mod bundle;
+pub mod watch;
+pub mod tui;
+pub mod cherry_pick;
+pub mod revert;
+pub mod rebase;
+pub mod fsck;
+pub mod cat_file;
+pub mod hash_object;
+pub mod ls_tree;
+pub mod ls_files;
+pub mod rev_parse;
+pub mod update_ref;
+pub mod write_tree;
+pub mod commit_tree;
+pub mod migrate_hash;
+pub mod recompress;
+pub mod lfs;
+pub mod stats;
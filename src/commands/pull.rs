@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+
+use crate::commands::branch::{read_current_branch, upstream_of};
+use crate::commands::checkout::switch_tree;
+use crate::commands::commit::{read_current_commit, update_current_commit};
+use crate::commands::fetch::{fetch_with, print_updates};
+use crate::commands::remote::is_remote;
+use crate::utils::history::is_ancestor;
+use crate::utils::log::log_action;
+use crate::utils::object::ZERO_HASH;
+use crate::utils::repo::Repo;
+use crate::utils::transport::{connect, remote_url};
+
+/// `ink pull [<remote> [<branch>]]`: fetch, then fast-forward the current branch
+pub fn run(remote: Option<String>, branch: Option<String>) -> Result<()> {
+    // Without arguments, pull the upstream of the current branch (or its namesake on origin)
+    let current = read_current_branch()?;
+    let upstream = upstream_of(&current)?;
+    let remote = match (remote, &upstream) {
+        (Some(r), _) => r,
+        (None, Some((r, _))) => r.clone(),
+        (None, None) => "origin".to_string(),
+    };
+    let branch = match (branch, upstream) {
+        (Some(b), _) => b,
+        (None, Some((r, b))) if r == remote => b,
+        (None, _) => current,
+    };
+
+    let url = remote_url(&remote)?;
+    let tracking = is_remote(&remote)?.then_some(remote.as_str());
+    let repo = Repo::load()?;
+    let fetched = fetch_with(&repo, connect(&url)?.as_mut(), &url, tracking)?;
+    if let Some(remote) = tracking {
+        print_updates(remote, &fetched.updated);
+    }
+    let adv = fetched.adv;
+    let theirs = adv
+        .get(&format!("refs/heads/{}", branch))
+        .cloned()
+        .ok_or_else(|| anyhow!("Remote '{}' has no branch '{}'", remote, branch))?;
+    let ours = read_current_commit()?;
+
+    if ours == theirs || (ours != ZERO_HASH && is_ancestor(&theirs, &ours)?) {
+        println!("Already up to date.");
+        return Ok(());
+    }
+
+    if ours != ZERO_HASH && !is_ancestor(&ours, &theirs)? {
+        return Err(anyhow!(
+            "Not possible to fast-forward: your branch and '{}/{}' have diverged.\nMerging is not supported yet; rebase or reset your branch first.",
+            remote,
+            branch
+        ));
+    }
+
+    switch_tree(&repo, &ours, &theirs)?;
+    update_current_commit(&theirs)?;
+    log_action(ours.clone(), theirs.clone(), "pull", &format!("Fast-forward from {}", url))?;
+
+    println!("Updating {}..{}", &ours[..7], &theirs[..7]);
+    println!("Fast-forward");
+    Ok(())
+}
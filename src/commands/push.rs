@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+
+use crate::commands::branch::{read_current_branch, set_upstream as set_branch_upstream};
+use crate::commands::commit::get_branch_commit;
+use crate::commands::fetch::{check_hash_algo, update_tracking_ref};
+use crate::commands::remote::{default_remote, is_remote};
+use crate::utils::history::{is_ancestor, missing_objects};
+use crate::utils::lfs::pointer_ids;
+use crate::utils::object::{object_exists, ZERO_HASH};
+use crate::utils::remote::{Advertisement, PushRequest, RefUpdate};
+use crate::utils::revision::check_ref_name;
+use crate::utils::transport::{connect, remote_url, Transport};
+
+/// `ink push [--force] [-u] [<remote> [<branch>]]`
+pub fn run(remote: Option<String>, branch: Option<String>, force: bool, set_upstream: bool) -> Result<()> {
+    let remote = match remote {
+        Some(r) => r,
+        None => default_remote()?,
+    };
+    let url = remote_url(&remote)?;
+    let tracking = is_remote(&remote)?;
+    if set_upstream && !tracking {
+        return Err(anyhow!("--set-upstream needs a configured remote, not a URL"));
+    }
+
+    let branch = match branch {
+        Some(b) => b,
+        None => read_current_branch()?,
+    };
+
+    let local = get_branch_commit(&branch)?;
+    if local == ZERO_HASH {
+        return Err(anyhow!("Branch '{}' does not have any commits to push", branch));
+    }
+
+    let mut transport = connect(&url)?;
+    let adv = transport.advertise()?;
+    check_hash_algo(&adv)?;
+
+    let old = adv.get(&format!("refs/heads/{}", branch)).cloned().unwrap_or_else(|| ZERO_HASH.to_string());
+
+    if old == local {
+        println!("Everything up-to-date");
+    } else {
+        send(transport.as_mut(), &url, &branch, &adv, &old, &local, force)?;
+    }
+
+    if tracking {
+        update_tracking_ref(&remote, &branch, &local)?;
+    }
+    if set_upstream {
+        set_branch_upstream(&branch, &remote, &branch)?;
+        println!("Branch '{}' set up to track '{}/{}'.", branch, remote, branch);
+    }
+
+    Ok(())
+}
+
+/// Sends the objects the remote lacks and asks it to move `branch` from `old` to `local`
+fn send(
+    transport: &mut dyn Transport,
+    url: &str,
+    branch: &str,
+    adv: &Advertisement,
+    old: &str,
+    local: &str,
+    force: bool,
+) -> Result<()> {
+    // A remote tip we don't have can't be an ancestor of ours
+    let fast_forward = old == ZERO_HASH || (object_exists(old) && is_ancestor(old, local)?);
+    if !fast_forward && !force {
+        return Err(anyhow!(
+            "! [rejected] {} -> {} (non-fast-forward)\nUpdates were rejected because the remote contains work that you do not have.\nFetch and integrate the remote changes first, or use --force to overwrite them.",
+            branch,
+            branch
+        ));
+    }
+
+    let haves: Vec<String> = adv.refs.iter().map(|(_, hash)| hash.clone()).filter(|h| object_exists(h)).collect();
+    let objects = missing_objects(&[local.to_string()], &haves)?;
+    let update = RefUpdate { name: format!("refs/heads/{}", branch), old: old.to_string(), new: local.to_string() };
+    check_ref_name(&update.name)?;
+    let lfs = pointer_ids(&objects)?;
+    let result = transport.push(&PushRequest { updates: vec![update], force }, &objects, &lfs)?;
+
+    println!("To {}", url);
+    for (_, error) in result.results {
+        if let Some(reason) = error {
+            return Err(anyhow!("! [remote rejected] {} -> {} ({})", branch, branch, reason));
+        }
+
+        if old == ZERO_HASH {
+            println!(" * [new branch]      {} -> {}", branch, branch);
+        } else {
+            let sep = if fast_forward { ".." } else { "..." };
+            let marker = if fast_forward { " " } else { "+" };
+            println!(" {}{}{}{}  {} -> {}", marker, &old[..7], sep, &local[..7], branch, branch);
+        }
+    }
+
+    Ok(())
+}
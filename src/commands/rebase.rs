@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+use crate::commands::branch::{read_current_branch, upstream_of};
+use crate::commands::checkout::{get_tree_entries, reset_tree, switch_tree};
+use crate::commands::commit::{read_current_commit, read_tree_of_commit, update_current_commit};
+use crate::commands::status::collect;
+use crate::utils::editor::{edit_file, strip_comments};
+use crate::utils::history::{is_ancestor, reachable_commits, sort_commits, walk_commits, Order};
+use crate::utils::index::Index;
+use crate::utils::log::log_action;
+use crate::utils::merge::{apply_change, is_resolved};
+use crate::utils::object::{create_commit, create_tree, read_commit_object, Commit, ZERO_HASH};
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
+use crate::utils::revision::resolve_revision;
+use crate::utils::sequencer::{self, Control};
+use crate::utils::signature::Signature;
+
+/// State of a rebase that stopped for a conflict or an `edit` step
+const DIR: &str = ".ink/rebase-merge";
+
+/// What to do with one commit of the todo list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Pick,
+    Reword,
+    Edit,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl Command {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "p" | "pick" => Some(Command::Pick),
+            "r" | "reword" => Some(Command::Reword),
+            "e" | "edit" => Some(Command::Edit),
+            "s" | "squash" => Some(Command::Squash),
+            "f" | "fixup" => Some(Command::Fixup),
+            "d" | "drop" => Some(Command::Drop),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Command::Pick => "pick",
+            Command::Reword => "reword",
+            Command::Edit => "edit",
+            Command::Squash => "squash",
+            Command::Fixup => "fixup",
+            Command::Drop => "drop",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    command: Command,
+    hash: String,
+}
+
+impl Step {
+    fn line(&self) -> Result<String> {
+        let subject = read_commit_object(&self.hash)?.message.lines().next().unwrap_or("").to_string();
+        Ok(format!("{} {} {}", self.command.as_str(), &self.hash[..7], subject))
+    }
+}
+
+/// Everything a rebase needs to resume after stopping.
+///
+/// Stored as one file per field in `.ink/rebase-merge/`; the first entry of
+/// `todo` is the step that stopped when `conflicts` is set.
+struct Rebase {
+    branch: String,
+    orig_head: String,
+    onto: String,
+    todo: Vec<Step>,
+    done: Vec<Step>,
+    /// Paths left in conflict by the current step, if it stopped on one
+    conflicts: Option<Vec<PathBuf>>,
+}
+
+impl Rebase {
+    fn load() -> Result<Option<Self>> {
+        let dir = Path::new(DIR);
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+        let field = |name: &str| -> Result<String> { Ok(read_to_string(dir.join(name))?.trim().to_string()) };
+
+        let conflicts = match read_to_string(dir.join("conflicts")) {
+            Ok(text) => Some(text.lines().map(PathBuf::from).collect()),
+            Err(_) => None,
+        };
+
+        Ok(Some(Rebase {
+            branch: field("head-name")?.trim_start_matches("refs/heads/").to_string(),
+            orig_head: field("orig-head")?,
+            onto: field("onto")?,
+            todo: parse_todo(&read_to_string(dir.join("git-rebase-todo"))?)?,
+            done: parse_todo(&read_to_string(dir.join("done"))?)?,
+            conflicts,
+        }))
+    }
+
+    fn save(&self) -> Result<()> {
+        let dir = Path::new(DIR);
+        create_dir_all(dir)?;
+        write(dir.join("head-name"), format!("refs/heads/{}\n", self.branch))?;
+        write(dir.join("orig-head"), &self.orig_head)?;
+        write(dir.join("onto"), &self.onto)?;
+        write(dir.join("git-rebase-todo"), format_todo(&self.todo)?)?;
+        write(dir.join("done"), format_todo(&self.done)?)?;
+
+        let conflicts = dir.join("conflicts");
+        match &self.conflicts {
+            Some(paths) => write(conflicts, paths.iter().map(|p| format!("{}\n", p.display())).collect::<String>())?,
+            None if conflicts.exists() => std::fs::remove_file(conflicts)?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Marks the current step as done
+    fn advance(&mut self) -> Result<()> {
+        if !self.todo.is_empty() {
+            self.done.push(self.todo.remove(0));
+        }
+        self.conflicts = None;
+        self.save()
+    }
+}
+
+/// Whether a rebase is stopped, with the branch, the commit it is going onto and
+/// the paths still in conflict, for `ink status`
+pub fn in_progress(repo: &Repo) -> Result<Option<(String, String, Vec<PathBuf>)>> {
+    let Some(rebase) = Rebase::load()? else {
+        return Ok(None);
+    };
+
+    let index = Index::load()?;
+    let unresolved = rebase.conflicts.unwrap_or_default().into_iter().filter(|p| !is_resolved(repo, p, &index)).collect();
+    Ok(Some((rebase.branch, rebase.onto, unresolved)))
+}
+
+/// `ink rebase [-i] [--onto <newbase>] [<upstream>]`
+pub fn run(upstream: Option<String>, onto: Option<String>, interactive: bool) -> Result<()> {
+    let repo = Repo::load()?;
+    if Rebase::load()?.is_some() {
+        return Err(anyhow!(
+            "A rebase is already in progress.\n  (use \"ink rebase --continue\", \"--skip\" or \"--abort\")"
+        ));
+    }
+    if sequencer::in_progress(&repo)?.is_some() {
+        return Err(anyhow!("A cherry-pick or revert is in progress; finish or abort it first"));
+    }
+
+    let branch = read_current_branch()?;
+    let head = read_current_commit()?;
+    if head == ZERO_HASH {
+        return Err(anyhow!("The current branch does not have any commits yet"));
+    }
+    let report = collect(&repo, &Pathspec::default())?;
+    if !report.staged.is_empty() || !report.unstaged.is_empty() {
+        return Err(anyhow!("Cannot rebase: you have uncommitted changes; please commit or stash them first."));
+    }
+
+    let upstream = match upstream {
+        Some(rev) => resolve_revision(&rev)?,
+        None => match upstream_of(&branch)? {
+            Some((remote, remote_branch)) => resolve_revision(&format!("remotes/{}/{}", remote, remote_branch))?,
+            None => return Err(anyhow!("There is no upstream for branch '{}'; name the branch to rebase onto", branch)),
+        },
+    };
+    let onto = match onto {
+        Some(rev) => resolve_revision(&rev)?,
+        None => upstream.clone(),
+    };
+
+    // Commits on this branch that the upstream lacks, oldest first; merges are flattened away
+    let excluded = reachable_commits(std::slice::from_ref(&upstream))?;
+    let mut commits: Vec<(String, Commit)> = sort_commits(walk_commits(std::slice::from_ref(&head))?, Order::Topo)
+        .into_iter()
+        .filter(|(hash, commit)| !excluded.contains(hash) && commit.parents.len() < 2)
+        .collect();
+    commits.reverse();
+
+    if commits.is_empty() && !interactive {
+        if head != onto && is_ancestor(&head, &onto)? {
+            switch_tree(&repo, &head, &onto)?;
+            update_current_commit(&onto)?;
+            log_action(head, onto.clone(), "rebase (finish)", &format!("refs/heads/{} onto {}", branch, onto))?;
+            println!("Fast-forwarded {} to {}.", branch, &onto[..7]);
+        } else {
+            println!("Current branch {} is up to date.", branch);
+        }
+        return Ok(());
+    }
+    if !interactive && commits.first().and_then(|(_, c)| c.parents.first()) == Some(&onto) {
+        println!("Current branch {} is up to date.", branch);
+        return Ok(());
+    }
+
+    let mut todo: Vec<Step> = commits.into_iter().map(|(hash, _)| Step { command: Command::Pick, hash }).collect();
+    if interactive {
+        todo = edit_todo(&todo, &onto)?;
+        if todo.is_empty() {
+            println!("Nothing to do");
+            return Ok(());
+        }
+    }
+
+    let mut rebase = Rebase { branch: branch.clone(), orig_head: head.clone(), onto: onto.clone(), todo, done: Vec::new(), conflicts: None };
+    rebase.save()?;
+
+    switch_tree(&repo, &head, &onto)?;
+    update_current_commit(&onto)?;
+    log_action(head, onto.clone(), "rebase (start)", &format!("checkout {}", &onto[..7]))?;
+
+    run_steps(&repo, &mut rebase)
+}
+
+/// `ink rebase --continue | --skip | --abort`
+pub fn resume(control: Control) -> Result<()> {
+    let repo = Repo::load()?;
+    let Some(mut rebase) = Rebase::load()? else {
+        return Err(anyhow!("No rebase in progress"));
+    };
+
+    match control {
+        Control::Abort => {
+            let current = read_current_commit()?;
+            update_current_commit(&rebase.orig_head)?;
+            reset_tree(&repo, &rebase.orig_head)?;
+            log_action(current, rebase.orig_head.clone(), "rebase (abort)", &format!("returning to refs/heads/{}", rebase.branch))?;
+            remove_dir_all(DIR)?;
+            println!("Rebase aborted; {} is back at {}", rebase.branch, &rebase.orig_head[..7]);
+            return Ok(());
+        }
+        Control::Skip => {
+            reset_tree(&repo, &read_current_commit()?)?;
+            if rebase.conflicts.is_some() {
+                rebase.advance()?;
+            }
+        }
+        Control::Continue => match &rebase.conflicts {
+            Some(conflicts) => {
+                let index = Index::load()?;
+                if let Some(path) = conflicts.iter().find(|p| !is_resolved(&repo, p, &index)) {
+                    return Err(anyhow!(
+                        "'{}' still has unresolved changes; fix it and run 'ink add {}' first",
+                        path.display(),
+                        path.display()
+                    ));
+                }
+                let step = rebase.todo.first().cloned().ok_or_else(|| anyhow!("The rebase todo list is empty"))?;
+                commit_step(&repo, &step)?;
+                rebase.advance()?;
+            }
+            // Stopped for `edit`: fold anything staged since into the edited commit
+            None => {
+                let head = read_current_commit()?;
+                let tree = create_tree(&repo)?;
+                if tree != read_tree_of_commit(&head)? {
+                    let commit = read_commit_object(&head)?;
+                    let subject = commit.message.lines().next().unwrap_or("").to_string();
+                    let new = create_commit(&repo, &tree, &commit.parents, &commit.message, &commit.author, &Signature::committer()?)?;
+                    update_current_commit(&new)?;
+                    log_action(head, new, "rebase (amend)", &subject)?;
+                }
+            }
+        },
+    }
+
+    run_steps(&repo, &mut rebase)
+}
+
+/// Replays the remaining steps until the list is done or one stops
+fn run_steps(repo: &Repo, rebase: &mut Rebase) -> Result<()> {
+    while let Some(step) = rebase.todo.first().cloned() {
+        if step.command == Command::Drop {
+            rebase.advance()?;
+            continue;
+        }
+
+        let commit = read_commit_object(&step.hash)?;
+        if commit.parents.len() > 1 {
+            return Err(anyhow!("Commit {} is a merge; rebasing merges is not supported", &step.hash[..7]));
+        }
+        let head = read_current_commit()?;
+        let subject = commit.message.lines().next().unwrap_or("").to_string();
+
+        // A commit already sitting on HEAD is reused as is
+        if matches!(step.command, Command::Pick | Command::Edit) && commit.parents.first() == Some(&head) {
+            switch_tree(repo, &head, &step.hash)?;
+            update_current_commit(&step.hash)?;
+            log_action(head, step.hash.clone(), &format!("rebase ({})", step.command.as_str()), &subject)?;
+        } else {
+            let parent_files = match commit.parents.first() {
+                Some(parent) => get_tree_entries(&read_tree_of_commit(parent)?)?,
+                None => HashMap::new(),
+            };
+            let label = format!("{}... {}", &step.hash[..7], subject);
+            let conflicts = apply_change(repo, &parent_files, &get_tree_entries(&commit.tree)?, ("HEAD", &label))?;
+
+            if !conflicts.is_empty() {
+                for (path, reason) in &conflicts {
+                    println!("CONFLICT ({}): {}", reason, path.display());
+                }
+                rebase.conflicts = Some(conflicts.into_iter().map(|(path, _)| path).collect());
+                rebase.save()?;
+                return Err(anyhow!(
+                    "could not apply {}\nhint: resolve all conflicts, mark them with \"ink add <paths>\", then run \"ink rebase --continue\"\nhint: or use \"ink rebase --skip\" to drop this commit, or \"ink rebase --abort\" to start over",
+                    label
+                ));
+            }
+
+            commit_step(repo, &step)?;
+        }
+
+        rebase.advance()?;
+        if step.command == Command::Edit {
+            println!("Stopped at {}... {}", &step.hash[..7], subject);
+            println!("You can amend the commit now, with\n\n  ink commit --amend\n\nOnce you are satisfied with your changes, run\n\n  ink rebase --continue");
+            return Ok(());
+        }
+    }
+
+    let head = read_current_commit()?;
+    log_action(
+        rebase.orig_head.clone(),
+        head,
+        "rebase (finish)",
+        &format!("refs/heads/{} onto {}", rebase.branch, rebase.onto),
+    )?;
+    remove_dir_all(DIR)?;
+    println!("Successfully rebased and updated refs/heads/{}.", rebase.branch);
+    Ok(())
+}
+
+/// Commits the index for a replayed step, following its command
+fn commit_step(repo: &Repo, step: &Step) -> Result<()> {
+    let original = read_commit_object(&step.hash)?;
+    let head = read_current_commit()?;
+    let tree = create_tree(repo)?;
+    let committer = Signature::committer()?;
+    let reflog = format!("rebase ({})", step.command.as_str());
+
+    let (new, message) = match step.command {
+        Command::Squash | Command::Fixup => {
+            let last = read_commit_object(&head)?;
+            let message = if step.command == Command::Squash {
+                let template = format!(
+                    "# This is a combination of 2 commits.\n# This is the 1st commit message:\n\n{}\n\n# This is the commit message #2:\n\n{}\n",
+                    last.message, original.message
+                );
+                strip_comments(&edit_file(&Path::new(DIR).join("message"), &template)?)
+            } else {
+                last.message.clone()
+            };
+            if message.is_empty() {
+                return Err(anyhow!("Aborting commit due to empty commit message."));
+            }
+            (create_commit(repo, &tree, &last.parents, &message, &last.author, &committer)?, message)
+        }
+        _ => {
+            if tree == read_tree_of_commit(&head)? {
+                println!("Dropping {} -- patch contents already upstream", &step.hash[..7]);
+                return Ok(());
+            }
+            let message = if step.command == Command::Reword {
+                strip_comments(&edit_file(&Path::new(DIR).join("message"), &format!("{}\n", original.message))?)
+            } else {
+                original.message.clone()
+            };
+            if message.is_empty() {
+                return Err(anyhow!("Aborting commit due to empty commit message."));
+            }
+            (create_commit(repo, &tree, std::slice::from_ref(&head), &message, &original.author, &committer)?, message)
+        }
+    };
+
+    update_current_commit(&new)?;
+    log_action(head, new, &reflog, message.lines().next().unwrap_or(""))
+}
+
+/// Opens the todo list in the editor and parses what comes back
+fn edit_todo(todo: &[Step], onto: &str) -> Result<Vec<Step>> {
+    let mut text = format_todo(todo)?;
+    text += &format!(
+        "\n# Rebase {} onto {} ({} commands)\n#\n\
+         # Commands:\n\
+         # p, pick <commit> = use commit\n\
+         # r, reword <commit> = use commit, but edit the commit message\n\
+         # e, edit <commit> = use commit, but stop for amending\n\
+         # s, squash <commit> = use commit, but meld into previous commit\n\
+         # f, fixup <commit> = like \"squash\", but discard this commit's log message\n\
+         # d, drop <commit> = remove commit\n#\n\
+         # These lines can be re-ordered; they are executed from top to bottom.\n\
+         # If you remove a line here THAT COMMIT WILL BE LOST.\n\
+         # However, if you remove everything, the rebase will be aborted.\n",
+        &todo.first().map(|s| s.hash.as_str()).unwrap_or(onto)[..7],
+        &onto[..7],
+        todo.len()
+    );
+
+    create_dir_all(DIR)?;
+    let edited = edit_file(&Path::new(DIR).join("git-rebase-todo"), &text);
+    remove_dir_all(DIR)?;
+
+    let steps = parse_todo(&edited?)?;
+    if let Some(first) = steps.iter().find(|s| s.command != Command::Drop)
+        && matches!(first.command, Command::Squash | Command::Fixup)
+    {
+        return Err(anyhow!("Cannot '{}' without a previous commit", first.command.as_str()));
+    }
+    Ok(steps)
+}
+
+fn parse_todo(text: &str) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let mut words = line.split_whitespace();
+        let word = words.next().unwrap_or("");
+        let command = Command::parse(word).ok_or_else(|| anyhow!("Unknown rebase command '{}' in: {}", word, line))?;
+        let rev = words.next().ok_or_else(|| anyhow!("Missing commit in: {}", line))?;
+        steps.push(Step { command, hash: resolve_revision(rev)? });
+    }
+    Ok(steps)
+}
+
+fn format_todo(steps: &[Step]) -> Result<String> {
+    let mut text = String::new();
+    for step in steps {
+        text += &step.line()?;
+        text.push('\n');
+    }
+    Ok(text)
+}
@@ -0,0 +1,15 @@
+use std::io::{stdin, stdout, Write};
+use anyhow::Result;
+
+use crate::utils::remote::{receive_pack, write_message};
+use crate::utils::repo::Repo;
+
+/// Server side of push: reads a push request and its pack stream on stdin and
+/// writes the per-ref results to stdout
+pub fn run() -> Result<()> {
+    let result = receive_pack(&Repo::load()?, &mut stdin().lock())?;
+    let mut out = stdout().lock();
+    write_message(&mut out, &result)?;
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::fs::{read, rename, write};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+
+use crate::utils::chunk::{chunk_path, is_manifest, list_chunks, CHUNK_THRESHOLD};
+use crate::utils::object::{encode_object, list_objects, object_path, open_object};
+use crate::utils::repo::Repo;
+use crate::utils::zip::{compress_with, decompress, detect};
+
+/// `ink recompress [--all]`: rewrites stored objects and chunks with the
+/// configured `core.compression` and `core.compressionLevel`, and splits or
+/// joins large blobs to match `core.chunking`. Files already stored that way
+/// are left alone unless `--all` is given, e.g. after changing the level.
+pub fn run(all: bool) -> Result<()> {
+    let repo = Repo::load()?;
+    let (encoding, level) = (repo.encoding, repo.level);
+    let names = list_objects()?;
+    let chunk_names = list_chunks()?;
+
+    // Chunks go first, so objects split below find them already converted
+    let chunks: Vec<Option<(String, u64, u64)>> = chunk_names
+        .par_iter()
+        .map(|name| {
+            let path = chunk_path(name);
+            let data = read(&path)?;
+            let found = detect(&data).map_err(|e| anyhow!("Chunk {}: {}; run 'ink fsck'", name, e))?;
+            if found == encoding && !all {
+                return Ok(None);
+            }
+            let (before, after) = replace(&path, data, |full| compress_with(full, encoding, level))?;
+            Ok(Some((found.to_string(), before, after)))
+        })
+        .collect::<Result<_>>()?;
+
+    // (how it was stored, bytes before, bytes after) for every object that was rewritten
+    let objects: Vec<Option<(String, u64, u64)>> = names
+        .par_iter()
+        .map(|hash| {
+            let path = object_path(hash);
+            let data = read(&path)?;
+            let found = if is_manifest(&data) {
+                // Chunk lists only change when chunking has been turned off
+                if repo.chunking {
+                    return Ok(None);
+                }
+                "chunked".to_string()
+            } else {
+                let found = detect(&data).map_err(|e| anyhow!("Object {}: {}; run 'ink fsck'", hash, e))?;
+                let splits = repo.chunking && {
+                    let (kind, size, _) = open_object(hash)?;
+                    kind == "blob" && size > CHUNK_THRESHOLD as u64
+                };
+                if found == encoding && !splits && !all {
+                    return Ok(None);
+                }
+                found.to_string()
+            };
+
+            let (before, after) = replace(&path, data, |full| encode_object(&repo, full))?;
+            Ok(Some((found, before, after)))
+        })
+        .collect::<Result<_>>()?;
+
+    let mut from: BTreeMap<String, usize> = BTreeMap::new();
+    let (mut before, mut after) = (0, 0);
+    for (found, b, a) in chunks.iter().chain(&objects).flatten() {
+        *from.entry(found.clone()).or_default() += 1;
+        before += b;
+        after += a;
+    }
+
+    let rewritten: usize = from.values().sum();
+    let mut target = match level {
+        Some(level) => format!("{} (level {})", encoding, level),
+        None => encoding.to_string(),
+    };
+    if repo.chunking {
+        target += ", large blobs chunked";
+    }
+    let stored = if chunk_names.is_empty() {
+        format!("{} objects", names.len())
+    } else {
+        format!("{} objects and chunks", names.len() + chunk_names.len())
+    };
+    if rewritten == 0 {
+        println!("All {} are already stored as {}", stored, target);
+        return Ok(());
+    }
+
+    let sources: Vec<String> = from.iter().map(|(name, count)| format!("{} {}", count, name)).collect();
+    println!(
+        "Recompressed {} of {} to {} (from {}): {} -> {} bytes",
+        rewritten,
+        stored,
+        target,
+        sources.join(", "),
+        before,
+        after
+    );
+    Ok(())
+}
+
+/// Re-encodes a stored file and returns its size before and after. The new
+/// version is written beside it and renamed, so an interruption never leaves
+/// a truncated file.
+fn replace(path: &Path, data: Vec<u8>, encode: impl Fn(Vec<u8>) -> Result<Vec<u8>>) -> Result<(u64, u64)> {
+    let before = data.len() as u64;
+    let converted = encode(decompress(data)?)?;
+    let after = converted.len() as u64;
+
+    let tmp = path.with_extension("tmp");
+    write(&tmp, converted)?;
+    rename(&tmp, path)?;
+    Ok((before, after))
+}
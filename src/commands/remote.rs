@@ -0,0 +1,77 @@
+use std::fs::remove_dir_all;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+
+use crate::commands::branch::{read_current_branch, unset_upstream, upstream_of};
+use crate::utils::config::{get_config, list_config_file, set_config, unset_config};
+
+/// Subcommands of `ink remote`
+pub enum RemoteAction {
+    List { verbose: bool },
+    Add { name: String, url: String },
+    Remove { name: String },
+}
+
+pub fn run(action: RemoteAction) -> Result<()> {
+    match action {
+        RemoteAction::List { verbose } => {
+            for (name, url) in list_remotes()? {
+                if verbose {
+                    println!("{}\t{} (fetch)", name, url);
+                    println!("{}\t{} (push)", name, url);
+                } else {
+                    println!("{}", name);
+                }
+            }
+        }
+        RemoteAction::Add { name, url } => {
+            let key = format!("remote.{}.url", name);
+            if get_config(&key)?.is_some() {
+                return Err(anyhow!("Remote '{}' already exists", name));
+            }
+            set_config(&key, &url, false)?;
+        }
+        RemoteAction::Remove { name } => {
+            if !unset_config(&format!("remote.{}.url", name), false)? {
+                return Err(anyhow!("No such remote: '{}'", name));
+            }
+
+            // Forget its remote-tracking refs and any branches tracking it
+            let refs = Path::new(".ink").join("refs").join("remotes").join(&name);
+            if refs.exists() {
+                remove_dir_all(refs)?;
+            }
+            for (key, value) in list_config_file(Path::new(".ink/config"))? {
+                if let Some(branch) = key.strip_prefix("branch.").and_then(|k| k.strip_suffix(".remote"))
+                    && value == name
+                {
+                    unset_upstream(branch)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Configured remotes as `(name, url)`
+pub fn list_remotes() -> Result<Vec<(String, String)>> {
+    Ok(list_config_file(Path::new(".ink/config"))?
+        .into_iter()
+        .filter_map(|(key, url)| {
+            let name = key.strip_prefix("remote.")?.strip_suffix(".url")?;
+            Some((name.to_string(), url))
+        })
+        .collect())
+}
+
+pub fn is_remote(name: &str) -> Result<bool> {
+    Ok(list_remotes()?.iter().any(|(n, _)| n == name))
+}
+
+/// Remote of the current branch's upstream, or `origin`
+pub fn default_remote() -> Result<String> {
+    Ok(upstream_of(&read_current_branch()?)?
+        .map(|(remote, _)| remote)
+        .unwrap_or_else(|| "origin".to_string()))
+}
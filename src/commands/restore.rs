@@ -1,54 +1,135 @@
-use std::fs::{create_dir_all, write};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::remove_file;
 use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Result};
 
+use crate::commands::checkout::restore_blob;
 use crate::commands::commit::read_current_commit;
-use crate::utils::object::{read_blob_object, read_tree_object};
+use crate::utils::dir::remove_empty_parents_up_to;
+use crate::utils::history::commit_files;
+use crate::utils::index::{save_index_for_current_branch, Index, IndexEntry};
+use crate::utils::object::{hash_file, read_commit_object, ZERO_HASH};
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
+use crate::utils::revision::resolve_revision;
+
+/// Options accepted by `ink restore`
+#[derive(Debug, Default)]
+pub struct RestoreOptions {
+    /// Revision to restore from; defaults to the index, or HEAD with --staged
+    pub source: Option<String>,
+    pub staged: bool,
+    pub worktree: bool,
+    /// Overwrite files even if they have changes that are not in the index
+    pub force: bool,
+}
 
 /// Entry point for restore command
-pub fn run(target: PathBuf) -> Result<()> {
-    let commit_hash = read_current_commit()?;
-    if commit_hash == "0000000000000000000000000000000000000000000000000000000000000000" {
-        return Err(anyhow!("No commits yet. Nothing to restore."));
+pub fn run(paths: Vec<PathBuf>, opts: RestoreOptions) -> Result<()> {
+    let spec = Pathspec::from_paths(&paths)?;
+    let worktree = opts.worktree || !opts.staged;
+    let mut index = Index::load()?;
+
+    let index_files: HashMap<PathBuf, String> = index
+        .entries
+        .values()
+        .map(|e| (e.path.clone(), e.hash.clone()))
+        .collect();
+
+    let source = match (&opts.source, opts.staged) {
+        (Some(rev), _) => commit_files(&read_commit_object(&resolve_revision(rev)?)?)?,
+        (None, true) => head_files()?,
+        (None, false) => index_files.clone(),
+    };
+
+    // Every path the source or the index knows about that the pathspec selects
+    let candidates: BTreeSet<PathBuf> = source
+        .keys()
+        .chain(index_files.keys())
+        .filter(|p| spec.matches(p))
+        .cloned()
+        .collect();
+
+    if let Some(first) = spec.unmatched(&candidates.iter().cloned().collect::<Vec<_>>()).first() {
+        return Err(anyhow!("pathspec '{}' did not match any file(s) known to ink", first));
+    }
+
+    if worktree {
+        restore_worktree(&Repo::load()?, &candidates, &source, &index_files, opts.force)?;
     }
 
-    let tree_hash = crate::commands::commit::read_tree_of_commit(&commit_hash)?;
-    restore_tree(&tree_hash, Path::new(""), &target)?;
+    if opts.staged {
+        for path in &candidates {
+            match source.get(path) {
+                Some(hash) => index.add(IndexEntry { path: path.clone(), hash: hash.clone() }),
+                None => index.remove(path),
+            }
+        }
+        index.save()?;
+        save_index_for_current_branch()?;
+    }
 
     Ok(())
 }
 
-/// Recursively restores a tree into the working directory
-fn restore_tree(tree_hash: &str, target: &Path, restore_target: &Path) -> Result<()> {
-    let entries = read_tree_object(tree_hash)?;
-
-    for (entry_path, object_type, object_hash) in entries {
-        let full_path = target.join(&entry_path);
-
-        match object_type.as_str() {
-            "blob" => {
-                if restore_target == Path::new(".") || full_path.starts_with(restore_target) {
-                    let content = read_blob_object(&object_hash)?;
-                    if let Some(parent) = full_path.parent() {
-                        create_dir_all(parent)?;
-                    }
-                    write(&full_path, content)?;
-                    println!("Restored: {}", full_path.display());
-                }
-            }
+/// Writes `source` versions of `paths` to disk, deleting tracked files it does not contain
+fn restore_worktree(
+    repo: &Repo,
+    paths: &BTreeSet<PathBuf>,
+    source: &HashMap<PathBuf, String>,
+    index: &HashMap<PathBuf, String>,
+    force: bool,
+) -> Result<()> {
+    let mut actions: Vec<(&PathBuf, Option<&String>)> = Vec::new();
+    let mut clobbered: Vec<&PathBuf> = Vec::new();
 
-            "tree" => {
-                let new_target = if entry_path.as_os_str().is_empty() {
-                    target.to_path_buf()
-                } else {
-                    target.join(&entry_path)
-                };
-                restore_tree(&object_hash, &new_target, restore_target)?;
-            }
+    for path in paths {
+        let wanted = source.get(path);
+        let current = if path.is_file() { Some(hash_file(repo, path)?) } else { None };
 
-            _ => return Err(anyhow!("Unknown object type in tree: {}", object_type)),
+        if current.as_ref() == wanted {
+            continue;
+        }
+
+        // Content that is neither staged nor in the source would be lost
+        if current.is_some() && current.as_ref() != index.get(path) && !force {
+            clobbered.push(path);
+        }
+
+        actions.push((path, wanted));
+    }
+
+    if !clobbered.is_empty() {
+        let list: Vec<String> = clobbered.iter().map(|p| format!("\t{}", p.display())).collect();
+        return Err(anyhow!(
+            "The following files have local modifications that would be lost:\n{}\nCommit or stage them, or use --force to discard them.",
+            list.join("\n")
+        ));
+    }
+
+    for (path, wanted) in actions {
+        match wanted {
+            Some(hash) => {
+                restore_blob(path, hash)?;
+                println!("Restored: {}", path.display());
+            }
+            None => {
+                if path.exists() {
+                    remove_file(path)?;
+                    remove_empty_parents_up_to(path, Path::new("."))?;
+                    println!("Removed: {}", path.display());
+                }
+            }
         }
     }
 
     Ok(())
 }
+
+fn head_files() -> Result<HashMap<PathBuf, String>> {
+    let head = read_current_commit()?;
+    if head == ZERO_HASH {
+        return Ok(HashMap::new());
+    }
+    commit_files(&read_commit_object(&head)?)
+}
@@ -0,0 +1,50 @@
+use anyhow::{anyhow, Result};
+
+use crate::commands::branch::read_current_branch;
+use crate::utils::revision::{resolve_object, resolve_ref};
+
+pub struct RevParseOptions {
+    /// Require exactly one argument naming an existing object
+    pub verify: bool,
+    /// Abbreviate hashes to this many characters
+    pub short: Option<usize>,
+    /// Print the branch name instead of the hash where there is one
+    pub abbrev_ref: bool,
+}
+
+/// `ink rev-parse [--verify] [--short[=<n>]] [--abbrev-ref] <rev>...`
+pub fn run(revs: Vec<String>, opts: RevParseOptions) -> Result<()> {
+    if opts.verify && revs.len() != 1 {
+        return Err(anyhow!("Needed a single revision"));
+    }
+
+    for rev in revs {
+        if opts.abbrev_ref {
+            println!("{}", abbrev_ref(&rev)?);
+            continue;
+        }
+
+        let hash = resolve_object(&rev).map_err(|e| if opts.verify { anyhow!("Needed a single revision") } else { e })?;
+        match opts.short {
+            Some(n) => println!("{}", &hash[..n.clamp(4, hash.len())]),
+            None => println!("{}", hash),
+        }
+    }
+    Ok(())
+}
+
+/// The short ref name for `rev`: the checked-out branch for HEAD, or the name itself
+fn abbrev_ref(rev: &str) -> Result<String> {
+    if rev == "HEAD" || rev == "@" {
+        return read_current_branch();
+    }
+    if resolve_ref(rev)?.is_none() {
+        return Err(anyhow!("'{}' is not a ref", rev));
+    }
+
+    let short = ["refs/heads/", "refs/tags/", "refs/remotes/", "refs/"]
+        .iter()
+        .find_map(|prefix| rev.strip_prefix(prefix))
+        .unwrap_or(rev);
+    Ok(short.to_string())
+}
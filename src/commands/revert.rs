@@ -0,0 +1,12 @@
+use anyhow::{anyhow, Result};
+
+use crate::utils::sequencer::{resume, start, Action, Control};
+
+/// `ink revert <rev>... | --continue | --skip | --abort`
+pub fn run(revs: Vec<String>, control: Option<Control>) -> Result<()> {
+    match control {
+        Some(control) => resume(control),
+        None if revs.is_empty() => Err(anyhow!("Nothing to revert; name at least one commit")),
+        None => start(Action::Revert, &revs),
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::{BufWriter, Cursor, Read, Write};
+use std::net::Shutdown;
+use std::path::Path;
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+use crate::utils::lfs;
+use crate::utils::remote::{advertise, decode, encode, receive_pack, upload_pack, write_pack};
+use crate::utils::repo::Repo;
+
+/// Fetch requests and large file lists larger than this are refused before
+/// any of it is read. Pushes are not limited; they are stored as they arrive.
+const MAX_BODY: u64 = 64 << 20;
+
+/// Limit on the request line and headers together
+const MAX_HEAD: u64 = 16 << 10;
+
+/// `ink serve [--host <addr>] [--port <port>] [--allow-remote]`: serves the
+/// repository in the current directory over HTTP. There is no authentication,
+/// so only loopback addresses are served unless `--allow-remote` is given.
+///
+/// Endpoints (bodies are bincode messages and pack streams from `utils::remote`):
+/// - `GET  /info/refs`     ref advertisement
+/// - `POST /upload-pack`   fetch request -> pack stream
+/// - `POST /receive-pack`  push request and pack stream -> per-ref results
+/// - `POST /lfs-objects`   large file ids -> pack stream of their contents
+pub fn run(host: String, port: u16, allow_remote: bool) -> Result<()> {
+    if !Path::new(".ink").is_dir() {
+        return Err(anyhow!("Not an ink repository"));
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let listener = TcpListener::bind((host.as_str(), port)).await?;
+        let addr = listener.local_addr()?;
+        if !addr.ip().is_loopback() && !allow_remote {
+            return Err(anyhow!(
+                "Refusing to serve on {}: anyone who can reach it could push. Use --allow-remote to serve anyway",
+                addr
+            ));
+        }
+        println!("Serving {} on http://{}", std::env::current_dir()?.display(), addr);
+
+        // Pushes check and then move refs, so only one may run at a time
+        let push_lock = Arc::new(Mutex::new(()));
+
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let push_lock = push_lock.clone();
+
+            tokio::spawn(async move {
+                if let Err(e) = handle(stream, push_lock).await {
+                    eprintln!("{}: {}", peer, e);
+                }
+            });
+        }
+    })
+}
+
+async fn handle(mut stream: TcpStream, push_lock: Arc<Mutex<()>>) -> Result<()> {
+    let mut reader = BufReader::new(&mut stream);
+    let mut head = (&mut reader).take(MAX_HEAD);
+
+    let mut request_line = String::new();
+    head.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("").to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if head.read_line(&mut line).await? == 0 {
+            if head.limit() == 0 {
+                return respond(&mut stream, "431 Request Header Fields Too Large", b"Headers too large").await;
+            }
+            break;
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse()?;
+        }
+    }
+
+    // Repositories may be served under a prefix, so only the tail of the path matters
+    let endpoint = ["info/refs", "upload-pack", "receive-pack", "lfs-objects"]
+        .into_iter()
+        .find(|e| path.trim_end_matches('/').ends_with(e));
+
+    // A push is stored as it arrives rather than read first, so its size is not limited
+    if method == "POST" && endpoint == Some("receive-pack") {
+        let buffered = reader.buffer().to_vec();
+        let _guard = push_lock.lock().await;
+        let socket = stream.into_std()?;
+        socket.set_nonblocking(false)?;
+
+        let (socket, result) = tokio::task::spawn_blocking(move || {
+            let result = {
+                let mut body = std::io::BufReader::new(Read::chain(Cursor::new(buffered), &socket).take(content_length));
+                Repo::load().and_then(|repo| receive_pack(&repo, &mut body)).and_then(|r| encode(&r))
+            };
+            (socket, result)
+        })
+        .await?;
+
+        socket.set_nonblocking(true)?;
+        return finish(&mut TcpStream::from_std(socket)?, &method, &path, result).await;
+    }
+
+    if content_length > MAX_BODY {
+        return respond(&mut stream, "413 Payload Too Large", b"Request too large").await;
+    }
+    // Grows as data arrives, so a client cannot make us allocate a body it never sends
+    let mut body = Vec::new();
+    (&mut reader).take(content_length).read_to_end(&mut body).await?;
+    if body.len() as u64 != content_length {
+        return Err(anyhow!("connection closed after {} of {} body bytes", body.len(), content_length));
+    }
+
+    let result = match (method.as_str(), endpoint) {
+        ("GET", Some("info/refs")) => tokio::task::spawn_blocking(|| encode(&advertise()?)).await?,
+        ("POST", Some("upload-pack")) => match tokio::task::spawn_blocking(move || upload_pack(&decode(&body)?)).await? {
+            Ok(objects) => return respond_pack(stream, &method, &path, objects, Vec::new()).await,
+            Err(e) => Err(e),
+        },
+        ("POST", Some("lfs-objects")) => {
+            let ids = decode::<Vec<String>>(&body).and_then(|ids| {
+                ids.iter().try_for_each(|id| lfs::parse_id(id).map(|_| ()))?;
+                Ok(ids)
+            });
+            match ids {
+                Ok(ids) => return respond_pack(stream, &method, &path, Vec::new(), ids).await,
+                Err(e) => Err(e),
+            }
+        }
+        _ => return respond(&mut stream, "404 Not Found", b"Not found").await,
+    };
+
+    finish(&mut stream, &method, &path, result).await
+}
+
+/// Answers with a message, or with the error that prevented one
+async fn finish(stream: &mut TcpStream, method: &str, path: &str, result: Result<Vec<u8>>) -> Result<()> {
+    let outcome = match &result {
+        Ok(bytes) => respond(stream, "200 OK", bytes).await,
+        Err(e) => respond(stream, "500 Internal Server Error", e.to_string().as_bytes()).await,
+    };
+    println!("{} {} {}", method, path, if result.is_ok() { 200 } else { 500 });
+    outcome
+}
+
+/// Answers with a pack stream written straight to the socket. Its length is
+/// not known up front, so the body ends when the connection closes.
+async fn respond_pack(stream: TcpStream, method: &str, path: &str, objects: Vec<String>, lfs: Vec<String>) -> Result<()> {
+    let socket = stream.into_std()?;
+    socket.set_nonblocking(false)?;
+    println!("{} {} 200", method, path);
+
+    tokio::task::spawn_blocking(move || {
+        let mut out = BufWriter::new(&socket);
+        out.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nConnection: close\r\n\r\n")?;
+        write_pack(&mut out, &objects, &lfs)?;
+        out.flush()?;
+        drop(out);
+        socket.shutdown(Shutdown::Write)?;
+        Ok(())
+    })
+    .await?
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &[u8]) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
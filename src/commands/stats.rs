@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::fs::{metadata, read, File};
+use std::io::Read;
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+
+use crate::utils::chunk::{chunk_path, is_manifest, list_chunks, parse_manifest, MANIFEST_MAGIC};
+use crate::utils::object::{list_objects, object_path};
+
+/// One object file: its size on disk, plus the blob size and chunks when it is a chunk list
+type ObjectInfo = (u64, Option<(u64, Vec<(String, u64)>)>);
+
+/// `ink stats`: what the object store holds, and how much sharing chunks
+/// between blobs saves when `core.chunking` is on
+pub fn run() -> Result<()> {
+    let objects: Vec<ObjectInfo> = list_objects()?.par_iter().map(|hash| inspect(hash)).collect::<Result<_>>()?;
+
+    let mut disk = 0;
+    let mut chunked = 0;
+    let mut content = 0;
+    let mut references = 0;
+    let mut unique: HashMap<&str, u64> = HashMap::new();
+    for (size, chunks) in &objects {
+        disk += size;
+        if let Some((blob_size, list)) = chunks {
+            chunked += 1;
+            content += blob_size;
+            references += list.len();
+            unique.extend(list.iter().map(|(name, len)| (name.as_str(), *len)));
+        }
+    }
+
+    let stored = list_chunks()?;
+    let mut chunk_disk = 0;
+    let mut unreferenced = (0, 0);
+    for name in &stored {
+        let size = metadata(chunk_path(name))?.len();
+        if unique.contains_key(name.as_str()) {
+            chunk_disk += size;
+        } else {
+            unreferenced.0 += 1;
+            unreferenced.1 += size;
+        }
+    }
+
+    println!("Objects:        {}, {} on disk", objects.len(), human(disk));
+    if unreferenced.0 > 0 {
+        println!("Unused chunks:  {}, {} on disk", unreferenced.0, human(unreferenced.1));
+    }
+    if chunked == 0 {
+        println!("Chunked blobs:  none (set core.chunking=true to split blobs over 512 KiB)");
+        return Ok(());
+    }
+
+    let unique_bytes: u64 = unique.values().sum();
+    println!("Chunked blobs:  {} holding {}", chunked, human(content));
+    println!(
+        "Chunks:         {} used {} times, {} of content, {} on disk",
+        unique.len(),
+        references,
+        human(unique_bytes),
+        human(chunk_disk)
+    );
+    let missing = unique.len() - (stored.len() - unreferenced.0);
+    if missing > 0 {
+        println!("warning: {} chunks are missing; run 'ink fsck'", missing);
+    }
+    println!("Dedup ratio:    {:.2}x", content as f64 / unique_bytes.max(1) as f64);
+    Ok(())
+}
+
+/// Reads just enough of an object file to tell whether it is a chunk list
+fn inspect(hash: &str) -> Result<ObjectInfo> {
+    let path = object_path(hash);
+    let size = metadata(&path)?.len();
+
+    let mut start = Vec::new();
+    File::open(&path)?.take(MANIFEST_MAGIC.len() as u64).read_to_end(&mut start)?;
+    if !is_manifest(&start) {
+        return Ok((size, None));
+    }
+
+    let (header, chunks) = parse_manifest(&read(&path)?).map_err(|e| anyhow!("Object {}: {}", hash, e))?;
+    let blob_size = header
+        .split_once(' ')
+        .and_then(|(_, len)| len.parse().ok())
+        .ok_or_else(|| anyhow!("Object {}: malformed header '{}'", hash, header))?;
+    Ok((size, Some((blob_size, chunks))))
+}
+
+/// `1536` -> `1.5 KiB`
+fn human(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
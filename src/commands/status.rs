@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use anyhow::Result;
+use colored::Colorize;
+use rayon::prelude::*;
+
+use crate::commands::branch::{read_current_branch, tracking_status, Tracking};
+use crate::commands::commit::{read_current_commit, staged_changes};
+use crate::commands::rebase;
+use crate::utils::dir::list_worktree_files;
+use crate::utils::index::Index;
+use crate::utils::object::hash_file;
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
+use crate::utils::sequencer::{in_progress, Action};
+use crate::utils::watch::query_dirty;
+
+/// Staged, unstaged and untracked changes in the repository
+#[derive(Debug, Default)]
+pub struct StatusReport {
+    pub branch: String,
+    pub staged: Vec<(&'static str, PathBuf)>,
+    pub unstaged: Vec<(&'static str, PathBuf)>,
+    pub untracked: Vec<PathBuf>,
+}
+
+impl StatusReport {
+    pub fn is_clean(&self) -> bool {
+        self.staged.is_empty() && self.unstaged.is_empty() && self.untracked.is_empty()
+    }
+}
+
+/// Compares HEAD, the index and the working tree for paths matching `spec`
+pub fn collect(repo: &Repo, spec: &Pathspec) -> Result<StatusReport> {
+    let index = Index::load()?;
+
+    let staged = staged_changes(&read_current_commit()?)?
+        .into_iter()
+        .filter(|(_, path)| spec.matches(path))
+        .collect();
+
+    // A running `ink watch` knows which paths changed, which saves hashing every tracked file
+    let dirty: Option<HashSet<PathBuf>> = query_dirty().map(|paths| paths.into_iter().collect());
+
+    let mut unstaged: Vec<(&'static str, PathBuf)> = index
+        .entries
+        .par_iter()
+        .filter(|(path, _)| spec.matches(path) && dirty.as_ref().is_none_or(|d| d.contains(*path)))
+        .filter_map(|(path, entry)| {
+            if !path.is_file() {
+                return Some(("deleted", path.clone()));
+            }
+            let hash = hash_file(repo, path).ok()?;
+            (hash != entry.hash).then(|| ("modified", path.clone()))
+        })
+        .collect();
+    unstaged.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let tracked: HashSet<&PathBuf> = index.entries.keys().collect();
+    let candidates = match &dirty {
+        Some(paths) => paths.iter().filter(|p| p.is_file()).cloned().collect(),
+        None => list_worktree_files(),
+    };
+    let mut untracked: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter(|p| !tracked.contains(p) && spec.matches(p))
+        .collect();
+    untracked.sort();
+
+    Ok(StatusReport {
+        branch: read_current_branch()?,
+        staged,
+        unstaged,
+        untracked,
+    })
+}
+
+pub fn run(paths: Vec<PathBuf>, short: bool, show_branch: bool) -> Result<()> {
+    let spec = Pathspec::from_paths(&paths)?;
+    let repo = Repo::load()?;
+    let report = collect(&repo, &spec)?;
+    let tracking = tracking_status(&report.branch)?;
+
+    if short {
+        if show_branch {
+            match &tracking {
+                Some(t) => println!("## {}...{}", report.branch, describe_short(t)),
+                None => println!("## {}", report.branch),
+            }
+        }
+        print_short(&report);
+        return Ok(());
+    }
+
+    println!("On branch {}", report.branch);
+    if let Some(t) = &tracking {
+        println!("{}", describe_long(t));
+    }
+
+    if let Some((branch, onto, unresolved)) = rebase::in_progress(&repo)? {
+        println!("\nYou are currently rebasing branch '{}' on '{}'.", branch, &onto[..7]);
+        if unresolved.is_empty() {
+            println!("  (all conflicts fixed, or stopped to edit: run \"ink rebase --continue\")");
+        } else {
+            println!("  (fix conflicts and then run \"ink rebase --continue\")");
+        }
+        println!("  (use \"ink rebase --skip\" to skip this patch, \"ink rebase --abort\" to check out the original branch)");
+        print_unmerged(&unresolved);
+    }
+
+    if let Some((action, hash, unresolved)) = in_progress(&repo)? {
+        let doing = match action {
+            Action::Pick => "cherry-picking",
+            Action::Revert => "reverting",
+        };
+        println!("\nYou are currently {} commit {}.", doing, &hash[..7]);
+        if unresolved.is_empty() {
+            println!("  (all conflicts fixed: run \"ink {} --continue\")", action.command());
+        } else {
+            println!("  (fix conflicts and run \"ink {} --continue\")", action.command());
+        }
+        println!("  (use \"ink {0} --skip\" to skip this commit, \"ink {0} --abort\" to cancel)", action.command());
+        print_unmerged(&unresolved);
+    }
+
+    if !report.staged.is_empty() {
+        println!("\nChanges to be committed:");
+        for (status, path) in &report.staged {
+            println!("\t{}", format!("{:<12}{}", format!("{}:", status), path.display()).green());
+        }
+    }
+
+    if !report.unstaged.is_empty() {
+        println!("\nChanges not staged for commit:");
+        for (status, path) in &report.unstaged {
+            println!("\t{}", format!("{:<12}{}", format!("{}:", status), path.display()).red());
+        }
+    }
+
+    if !report.untracked.is_empty() {
+        println!("\nUntracked files:");
+        for path in &report.untracked {
+            println!("\t{}", path.display().to_string().red());
+        }
+    }
+
+    if report.is_clean() {
+        println!("nothing to commit, working tree clean");
+    }
+
+    Ok(())
+}
+
+fn print_unmerged(paths: &[PathBuf]) {
+    if paths.is_empty() {
+        return;
+    }
+    println!("\nUnmerged paths:\n  (use \"ink add <file>...\" to mark resolution)");
+    for path in paths {
+        println!("\t{}", format!("{:<12}{}", "unmerged:", path.display()).red());
+    }
+}
+
+/// `origin/main [ahead 1, behind 2]`
+fn describe_short(t: &Tracking) -> String {
+    match t.describe().split_once(": ") {
+        Some((name, counts)) => format!("{} [{}]", name, counts),
+        None => t.name.clone(),
+    }
+}
+
+fn describe_long(t: &Tracking) -> String {
+    let commits = |n: usize| if n == 1 { "1 commit".to_string() } else { format!("{} commits", n) };
+
+    match (t.gone, t.ahead, t.behind) {
+        (true, _, _) => format!("Your branch is based on '{}', but the upstream is gone.", t.name),
+        (_, 0, 0) => format!("Your branch is up to date with '{}'.", t.name),
+        (_, ahead, 0) => format!(
+            "Your branch is ahead of '{}' by {}.\n  (use \"ink push\" to publish your local commits)",
+            t.name,
+            commits(ahead)
+        ),
+        (_, 0, behind) => format!(
+            "Your branch is behind '{}' by {}, and can be fast-forwarded.\n  (use \"ink pull\" to update your local branch)",
+            t.name,
+            commits(behind)
+        ),
+        (_, ahead, behind) => format!(
+            "Your branch and '{}' have diverged,\nand have {} and {} different commits each, respectively.",
+            t.name, ahead, behind
+        ),
+    }
+}
+
+/// Two-column `XY path` output, stable for scripts
+fn print_short(report: &StatusReport) {
+    let code = |status: &str| match status {
+        "new file" => 'A',
+        "deleted" => 'D',
+        _ => 'M',
+    };
+
+    let mut lines: Vec<(PathBuf, char, char)> = Vec::new();
+    for (status, path) in &report.staged {
+        lines.push((path.clone(), code(status), ' '));
+    }
+    for (status, path) in &report.unstaged {
+        match lines.iter_mut().find(|(p, _, _)| p == path) {
+            Some(line) => line.2 = code(status),
+            None => lines.push((path.clone(), ' ', code(status))),
+        }
+    }
+    lines.sort();
+
+    for (path, x, y) in lines {
+        println!("{}{} {}", x, y, path.display());
+    }
+    for path in &report.untracked {
+        println!("?? {}", path.display());
+    }
+}
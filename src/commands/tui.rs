@@ -0,0 +1,424 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::commands;
+use crate::commands::branch::{read_current_branch, tracking_status};
+use crate::commands::commit::read_current_commit;
+use crate::commands::diff::{changed_paths, Side};
+use crate::commands::restore::RestoreOptions;
+use crate::commands::status::collect;
+use crate::utils::diff::unified_diff;
+use crate::utils::enums::AddMode;
+use crate::utils::graph::{layout, GraphLine};
+use crate::utils::history::{sort_commits, walk_commits, Order};
+use crate::utils::object::ZERO_HASH;
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
+use crate::utils::revision::{list_refs, ref_decorations};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Status,
+    Branches,
+    Log,
+    Diff,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Change {
+    Staged,
+    Unstaged,
+    Untracked,
+}
+
+struct StatusEntry {
+    path: PathBuf,
+    change: Change,
+    /// Two-letter code as in `ink status --short`
+    code: String,
+}
+
+struct LogRow {
+    graph: String,
+    /// Hash, subject and ref names; `None` for rows that only draw edges
+    commit: Option<(String, String, Vec<String>)>,
+}
+
+struct App {
+    repo: Repo,
+    focus: Pane,
+    branch: String,
+    tracking: Option<String>,
+    status: Vec<StatusEntry>,
+    status_at: usize,
+    branches: Vec<String>,
+    branches_at: usize,
+    log: Vec<LogRow>,
+    log_at: usize,
+    diff: Vec<Line<'static>>,
+    diff_scroll: u16,
+    message: String,
+}
+
+/// `ink tui`: status, branches, commit graph and diffs in one screen.
+/// Every action goes through the same command functions as the CLI.
+pub fn run() -> Result<()> {
+    let mut app = App {
+        repo: Repo::load()?,
+        focus: Pane::Status,
+        branch: String::new(),
+        tracking: None,
+        status: Vec::new(),
+        status_at: 0,
+        branches: Vec::new(),
+        branches_at: 0,
+        log: Vec::new(),
+        log_at: 0,
+        diff: Vec::new(),
+        diff_scroll: 0,
+        message: "Tab: switch pane  j/k: move  s: stage  u: unstage  Enter: checkout branch  r: refresh  q: quit".into(),
+    };
+    app.refresh()?;
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(Duration::from_millis(500))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let action: fn(&mut App) -> Result<String> = match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Tab => {
+                    self.cycle_focus();
+                    continue;
+                }
+                KeyCode::Char('j') | KeyCode::Down => {
+                    self.move_by(1);
+                    continue;
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    self.move_by(-1);
+                    continue;
+                }
+                KeyCode::PageDown => {
+                    self.move_by(10);
+                    continue;
+                }
+                KeyCode::PageUp => {
+                    self.move_by(-10);
+                    continue;
+                }
+                KeyCode::Char('r') => |_| Ok("Refreshed".into()),
+                KeyCode::Char('s') | KeyCode::Char(' ') if self.focus == Pane::Status => App::stage,
+                KeyCode::Char('u') if self.focus == Pane::Status => App::unstage,
+                KeyCode::Enter if self.focus == Pane::Branches => App::checkout,
+                _ => continue,
+            };
+
+            self.act(action);
+            // Commands print as they would on the CLI; repaint over their output
+            terminal.clear()?;
+        }
+    }
+
+    /// Runs an action, then reloads everything and reports the outcome on the message line
+    fn act(&mut self, action: fn(&mut App) -> Result<String>) {
+        let outcome = action(self).and_then(|message| self.refresh().map(|_| message));
+        self.message = match outcome {
+            Ok(message) => message,
+            Err(e) => format!("error: {}", e.to_string().lines().next().unwrap_or("")),
+        };
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let report = collect(&self.repo, &Pathspec::default())?;
+        self.branch = report.branch.clone();
+        self.tracking = tracking_status(&self.branch)?.map(|t| t.describe());
+
+        let code = |status: &str| match status {
+            "new file" => 'A',
+            "deleted" => 'D',
+            _ => 'M',
+        };
+        self.status = Vec::new();
+        for (status, path) in report.staged {
+            self.status.push(StatusEntry { path, change: Change::Staged, code: format!("{} ", code(status)) });
+        }
+        for (status, path) in report.unstaged {
+            self.status.push(StatusEntry { path, change: Change::Unstaged, code: format!(" {}", code(status)) });
+        }
+        for path in report.untracked {
+            self.status.push(StatusEntry { path, change: Change::Untracked, code: "??".into() });
+        }
+
+        let tips = list_refs("refs/heads")?;
+        self.branches = tips.iter().map(|(name, _)| name.trim_start_matches("refs/heads/").to_string()).collect();
+        if !self.branches.contains(&self.branch) {
+            self.branches.push(self.branch.clone());
+        }
+
+        let decorations = ref_decorations()?;
+        let mut starts: Vec<String> = tips.into_iter().map(|(_, hash)| hash).collect();
+        let head = read_current_commit()?;
+        if head != ZERO_HASH && !starts.contains(&head) {
+            starts.push(head);
+        }
+        let commits = sort_commits(walk_commits(&starts)?, Order::Topo);
+        self.log = layout(&commits)
+            .into_iter()
+            .map(|line| match line {
+                GraphLine::Commit(i, graph) => {
+                    let (hash, commit) = &commits[i];
+                    let subject = commit.message.lines().next().unwrap_or("").to_string();
+                    let refs = decorations.get(hash).cloned().unwrap_or_default();
+                    LogRow { graph, commit: Some((hash.clone(), subject, refs)) }
+                }
+                GraphLine::Edge(graph) => LogRow { graph, commit: None },
+            })
+            .collect();
+
+        self.status_at = self.status_at.min(self.status.len().saturating_sub(1));
+        self.branches_at = self.branches_at.min(self.branches.len().saturating_sub(1));
+        self.log_at = self.log_at.min(self.log.len().saturating_sub(1));
+        self.load_diff()
+    }
+
+    fn cycle_focus(&mut self) {
+        self.focus = match self.focus {
+            Pane::Status => Pane::Branches,
+            Pane::Branches => Pane::Log,
+            Pane::Log => Pane::Diff,
+            Pane::Diff => Pane::Status,
+        };
+        if matches!(self.focus, Pane::Status | Pane::Log) {
+            let _ = self.load_diff();
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let step = |at: usize, len: usize| (at as isize + delta).clamp(0, len.saturating_sub(1) as isize) as usize;
+        match self.focus {
+            Pane::Status => self.status_at = step(self.status_at, self.status.len()),
+            Pane::Branches => self.branches_at = step(self.branches_at, self.branches.len()),
+            Pane::Log if self.log.is_empty() => {}
+            Pane::Log => {
+                self.log_at = step(self.log_at, self.log.len());
+                // Skip edge-only rows in the direction of travel
+                while self.log[self.log_at].commit.is_none() && self.log_at + 1 < self.log.len() && delta > 0 {
+                    self.log_at += 1;
+                }
+                while self.log[self.log_at].commit.is_none() && self.log_at > 0 {
+                    self.log_at -= 1;
+                }
+            }
+            Pane::Diff => self.diff_scroll = (self.diff_scroll as isize + delta).max(0) as u16,
+        }
+        if matches!(self.focus, Pane::Status | Pane::Log) {
+            let _ = self.load_diff();
+        }
+    }
+
+    /// Shows the diff for the selected status entry, or for the selected commit
+    fn load_diff(&mut self) -> Result<()> {
+        self.diff_scroll = 0;
+        let text = match self.focus {
+            Pane::Log => match self.log.get(self.log_at).and_then(|row| row.commit.as_ref()) {
+                Some((hash, _, _)) => commit_diff(&self.repo, hash)?,
+                None => String::new(),
+            },
+            _ => match self.status.get(self.status_at) {
+                Some(entry) => entry_diff(entry)?,
+                None => String::new(),
+            },
+        };
+        self.diff = text.lines().map(color_diff_line).collect();
+        Ok(())
+    }
+
+    fn selected_path(&self) -> Result<&StatusEntry> {
+        self.status.get(self.status_at).ok_or_else(|| anyhow::anyhow!("Nothing selected"))
+    }
+
+    fn stage(&mut self) -> Result<String> {
+        let path = self.selected_path()?.path.clone();
+        commands::add::run(AddMode::Files(vec![path.clone()]))?;
+        Ok(format!("Staged {}", path.display()))
+    }
+
+    fn unstage(&mut self) -> Result<String> {
+        let entry = self.selected_path()?;
+        if entry.change != Change::Staged {
+            return Ok(format!("{} is not staged", entry.path.display()));
+        }
+        let path = entry.path.clone();
+        commands::restore::run(vec![path.clone()], RestoreOptions { staged: true, ..Default::default() })?;
+        Ok(format!("Unstaged {}", path.display()))
+    }
+
+    fn checkout(&mut self) -> Result<String> {
+        let name = self.branches.get(self.branches_at).cloned().unwrap_or_default();
+        if name == read_current_branch()? {
+            return Ok(format!("Already on '{}'", name));
+        }
+        commands::checkout::run(false, false, false, name.clone())?;
+        Ok(format!("Switched to branch '{}'", name))
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1)])
+            .split(frame.area());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(rows[1]);
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+            .split(columns[0]);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(columns[1]);
+
+        let mut header = vec![Span::styled(format!(" On branch {}", self.branch), Style::new().add_modifier(Modifier::BOLD))];
+        if let Some(tracking) = &self.tracking {
+            header.push(Span::styled(format!("  [{}]", tracking), Style::new().fg(Color::Blue)));
+        }
+        frame.render_widget(Line::from(header), rows[0]);
+        frame.render_widget(Line::from(self.message.clone()).style(Style::new().fg(Color::DarkGray)), rows[2]);
+
+        let status: Vec<ListItem> = self
+            .status
+            .iter()
+            .map(|entry| {
+                let color = if entry.change == Change::Staged { Color::Green } else { Color::Red };
+                ListItem::new(Line::from(vec![
+                    Span::styled(entry.code.clone(), Style::new().fg(color)),
+                    Span::raw(format!(" {}", entry.path.display())),
+                ]))
+            })
+            .collect();
+        self.draw_list(frame, left[0], "Status", Pane::Status, status, self.status_at);
+
+        let branches: Vec<ListItem> = self
+            .branches
+            .iter()
+            .map(|name| {
+                if *name == self.branch {
+                    ListItem::new(Span::styled(format!("* {}", name), Style::new().fg(Color::Green)))
+                } else {
+                    ListItem::new(format!("  {}", name))
+                }
+            })
+            .collect();
+        self.draw_list(frame, left[1], "Branches", Pane::Branches, branches, self.branches_at);
+
+        let log: Vec<ListItem> = self
+            .log
+            .iter()
+            .map(|row| {
+                let mut spans = vec![Span::styled(row.graph.clone(), Style::new().fg(Color::Magenta))];
+                if let Some((hash, subject, refs)) = &row.commit {
+                    spans.push(Span::styled(hash[..7].to_string(), Style::new().fg(Color::Yellow)));
+                    if !refs.is_empty() {
+                        spans.push(Span::styled(format!(" ({})", refs.join(", ")), Style::new().fg(Color::Green)));
+                    }
+                    spans.push(Span::raw(format!(" {}", subject)));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        self.draw_list(frame, right[0], "Log", Pane::Log, log, self.log_at);
+
+        let diff = Paragraph::new(self.diff.clone())
+            .block(self.block("Diff", Pane::Diff))
+            .scroll((self.diff_scroll, 0));
+        frame.render_widget(diff, right[1]);
+    }
+
+    fn draw_list(&self, frame: &mut Frame, area: Rect, title: &str, pane: Pane, items: Vec<ListItem>, at: usize) {
+        let list = List::new(items)
+            .block(self.block(title, pane))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(at));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn block(&self, title: &str, pane: Pane) -> Block<'static> {
+        let style = if self.focus == pane { Style::new().fg(Color::Yellow) } else { Style::new() };
+        Block::bordered().title(format!(" {} ", title)).border_style(style)
+    }
+}
+
+fn entry_diff(entry: &StatusEntry) -> Result<String> {
+    let path = &entry.path;
+    let (old, new) = match entry.change {
+        Change::Staged => (Side::of_commit(&read_current_commit()?)?, Side::of_index()?),
+        Change::Unstaged => (Side::of_index()?, Side::of_worktree()?),
+        Change::Untracked => {
+            let content = std::fs::read(path)?;
+            return Ok(unified_diff(&path.to_string_lossy(), None, Some(&content), false));
+        }
+    };
+
+    let before = old.content(path)?;
+    let after = new.content(path)?;
+    Ok(unified_diff(&path.to_string_lossy(), before.as_deref(), after.as_deref(), false))
+}
+
+fn commit_diff(repo: &Repo, hash: &str) -> Result<String> {
+    let commit = crate::utils::object::read_commit_object(hash)?;
+    let parent = commit.parents.first().cloned().unwrap_or_else(|| ZERO_HASH.to_string());
+    let old = Side::of_commit(&parent)?;
+    let new = Side::of_commit(hash)?;
+
+    let mut text = format!("commit {}\nAuthor: {} <{}>\n\n", hash, commit.author.name, commit.author.email);
+    for line in commit.message.lines() {
+        text.push_str(&format!("    {}\n", line));
+    }
+    text.push('\n');
+
+    for path in changed_paths(repo, &old, &new, &Pathspec::default())? {
+        let before = old.content(&path)?;
+        let after = new.content(&path)?;
+        text.push_str(&unified_diff(&path.to_string_lossy(), before.as_deref(), after.as_deref(), false));
+    }
+    Ok(text)
+}
+
+fn color_diff_line(line: &str) -> Line<'static> {
+    let style = if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff ") {
+        Style::new().add_modifier(Modifier::BOLD)
+    } else if line.starts_with('+') {
+        Style::new().fg(Color::Green)
+    } else if line.starts_with('-') {
+        Style::new().fg(Color::Red)
+    } else if line.starts_with("@@") {
+        Style::new().fg(Color::Cyan)
+    } else {
+        Style::new()
+    };
+    Line::styled(line.to_string(), style)
+}
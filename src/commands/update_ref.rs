@@ -0,0 +1,63 @@
+use std::fs::{create_dir_all, read_to_string, remove_file, write};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+
+use crate::commands::branch::read_current_branch;
+use crate::utils::dir::remove_empty_parents_up_to;
+use crate::utils::log::log_action;
+use crate::utils::object::{parse_raw_object, read_raw_object, ZERO_HASH};
+use crate::utils::revision::{check_ref_name, resolve_object};
+
+/// `ink update-ref <ref> <new> [<old>]` or `ink update-ref -d <ref> [<old>]`.
+///
+/// `new` of `None` deletes the ref. When `old` is given the ref must currently
+/// hold that value (zeros or an empty string meaning "must not exist").
+pub fn run(name: String, new: Option<String>, old: Option<String>, message: Option<String>) -> Result<()> {
+    let current = read_current_branch()?;
+    let full = if name == "HEAD" { format!("refs/heads/{}", current) } else { name.clone() };
+    check_ref_name(&full)?;
+    if !full.starts_with("refs/") {
+        return Err(anyhow!("Refusing to update '{}': refs must live under refs/", name));
+    }
+
+    let path = Path::new(".ink").join(&full);
+    let existing = match read_to_string(&path) {
+        Ok(hash) => hash.trim().to_string(),
+        Err(_) => ZERO_HASH.to_string(),
+    };
+
+    if let Some(old) = old {
+        let expected = if old.chars().all(|c| c == '0') { ZERO_HASH.to_string() } else { resolve_object(&old)? };
+        if expected != existing {
+            return Err(anyhow!("Cannot update '{}': expected {}, but it is {}", full, expected, existing));
+        }
+    }
+
+    let on_current = full == format!("refs/heads/{}", current);
+    let Some(new) = new else {
+        if on_current {
+            return Err(anyhow!("Refusing to delete the checked-out branch '{}'", current));
+        }
+        if path.is_file() {
+            remove_file(&path)?;
+            // Keep the namespace directory itself, e.g. refs/tags
+            let namespace: Vec<&str> = full.split('/').take(2).collect();
+            remove_empty_parents_up_to(&path, &Path::new(".ink").join(namespace.join("/")))?;
+        }
+        return Ok(());
+    };
+
+    let hash = resolve_object(&new)?;
+    let kind = parse_raw_object(&read_raw_object(&hash)?)?.0.to_string();
+    // Everything that walks refs (log, fsck, push) expects them to name commits
+    if kind != "commit" {
+        return Err(anyhow!("Cannot point '{}' at a {} ({})", full, kind, hash));
+    }
+
+    create_dir_all(path.parent().unwrap())?;
+    write(&path, &hash)?;
+    if on_current {
+        log_action(existing, hash, "update-ref", message.as_deref().unwrap_or(""))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use std::io::{stdin, stdout, BufWriter, Read, Write};
+use anyhow::Result;
+
+use crate::utils::remote::{advertise, decode, encode, upload_pack, write_pack};
+
+/// Server side of fetch: reads a request on stdin and streams the answer to stdout.
+/// With `lfs` the request is a list of large file ids rather than commits.
+pub fn run(advertise_only: bool, lfs: bool) -> Result<()> {
+    let mut out = BufWriter::new(stdout().lock());
+    if advertise_only {
+        out.write_all(&encode(&advertise()?)?)?;
+    } else {
+        let mut input = Vec::new();
+        stdin().read_to_end(&mut input)?;
+        if lfs {
+            write_pack(&mut out, &[], &decode::<Vec<String>>(&input)?)?;
+        } else {
+            write_pack(&mut out, &upload_pack(&decode(&input)?)?, &[])?;
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::env::current_dir;
+use std::fs::{create_dir_all, write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
+
+use crate::commands::branch::read_current_branch;
+use crate::commands::commit::{read_current_commit, read_tree_of_commit};
+use crate::utils::index::{save_index_for_current_branch, Index, IndexEntry, IndexLock};
+use crate::utils::object::{create_blob, create_commit, create_tree_from, ZERO_HASH};
+use crate::utils::repo::Repo;
+use crate::utils::revision::resolve_ref;
+use crate::utils::signature::Signature;
+use crate::utils::watch::{cookie_name, is_watched, query_dirty, running, serve, shutdown, stop, Shared, WatchState};
+
+/// Options accepted by `ink watch`
+#[derive(Debug, Default)]
+pub struct WatchOptions {
+    /// Stage tracked files as soon as they are saved
+    pub stage: bool,
+    /// Snapshot the working tree to `refs/autosave/<branch>` this often
+    pub autosave: Option<u64>,
+    /// Stop a running daemon instead of starting one
+    pub stop: bool,
+    /// Print the dirty paths known to a running daemon
+    pub list: bool,
+}
+
+/// `ink watch [--stage] [--autosave <secs>] | --stop | --list`
+pub fn run(opts: WatchOptions) -> Result<()> {
+    if opts.stop {
+        if !stop() {
+            return Err(anyhow!("ink watch is not running"));
+        }
+        println!("Stopped ink watch");
+        return Ok(());
+    }
+
+    if opts.list {
+        let paths = query_dirty().ok_or_else(|| anyhow!("ink watch is not running"))?;
+        for path in paths {
+            println!("{}", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(pid) = running() {
+        return Err(anyhow!("ink watch is already running (pid {})", pid));
+    }
+
+    let repo = Repo::load()?;
+    let shared: Shared = Arc::new((Mutex::new(WatchState::scan(repo)?), Condvar::new()));
+    let root = current_dir()?.canonicalize()?;
+
+    let handler_state = shared.clone();
+    let handler_root = root.clone();
+    let stage = opts.stage;
+    let mut watcher = recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) => handle_event(&repo, &event, &handler_root, &handler_state, stage),
+        Err(e) => eprintln!("watch: {}", e),
+    })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    let addr = serve(shared.clone())?;
+    println!("Watching {} (status queries on {})", root.display(), addr);
+
+    // Clean up the advertisement on Ctrl-C so status stops asking
+    thread::spawn(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build();
+        if let Ok(runtime) = runtime {
+            runtime.block_on(tokio::signal::ctrl_c()).ok();
+        }
+        shutdown();
+    });
+
+    loop {
+        match opts.autosave {
+            Some(secs) => {
+                thread::sleep(Duration::from_secs(secs.max(1)));
+                if let Err(e) = autosave(&repo, &shared) {
+                    eprintln!("watch: autosave failed: {}", e);
+                }
+            }
+            None => thread::park(),
+        }
+    }
+}
+
+fn handle_event(repo: &Repo, event: &Event, root: &Path, shared: &Shared, stage: bool) {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+
+    let (lock, cvar) = &**shared;
+    let mut state = lock.lock().unwrap();
+
+    for path in &event.paths {
+        let Ok(rel) = path.strip_prefix(root) else { continue };
+
+        if let Some(cookie) = cookie_name(rel) {
+            state.add_cookie(cookie);
+            cvar.notify_all();
+            continue;
+        }
+        if !is_watched(rel) {
+            continue;
+        }
+
+        state.mark(rel);
+        if stage && let Err(e) = stage_path(repo, rel) {
+            eprintln!("watch: cannot stage {}: {}", rel.display(), e);
+        }
+    }
+}
+
+/// Stages a saved or deleted tracked file; new files are left untracked
+fn stage_path(repo: &Repo, path: &Path) -> Result<()> {
+    let path = path.to_path_buf();
+    if !Index::load()?.entries.contains_key(&path) {
+        return Ok(());
+    }
+    // Stored before locking, so a large file does not hold up other index writers
+    let hash = if path.is_file() { Some(create_blob(repo, path.clone())?) } else { None };
+
+    // Reloaded under the lock, so changes other commands made meanwhile are kept
+    let lock = IndexLock::acquire()?;
+    let mut index = Index::load()?;
+    if !index.entries.contains_key(&path) {
+        return Ok(());
+    }
+    match hash {
+        Some(hash) => index.add(IndexEntry { path: path.clone(), hash }),
+        None => index.remove(&path),
+    }
+    index.save_locked(&lock)?;
+    drop(lock);
+    save_index_for_current_branch()?;
+
+    println!("Staged {}", path.display());
+    Ok(())
+}
+
+/// Commits the current working tree (tracked files plus untracked, non-ignored
+/// ones) on `refs/autosave/<branch>` without touching the index or the branch
+fn autosave(repo: &Repo, shared: &Shared) -> Result<()> {
+    let dirty = {
+        let mut state = shared.0.lock().unwrap();
+        state.refresh()?;
+        state.dirty()
+    };
+
+    let mut snapshot = Index::load()?;
+    for path in &dirty {
+        if path.is_file() {
+            snapshot.add(IndexEntry { path: path.clone(), hash: create_blob(repo, path.clone())? });
+        } else {
+            snapshot.remove(path);
+        }
+    }
+    if snapshot.entries.is_empty() {
+        return Ok(());
+    }
+    let tree = create_tree_from(repo, &snapshot)?;
+
+    let branch = read_current_branch()?;
+    let name = format!("autosave/{}", branch);
+    let parent = match resolve_ref(&name)? {
+        Some(hash) => hash,
+        None => read_current_commit()?,
+    };
+    if parent != ZERO_HASH && read_tree_of_commit(&parent)? == tree {
+        return Ok(());
+    }
+
+    let parents: Vec<String> = if parent == ZERO_HASH { Vec::new() } else { vec![parent] };
+    let signature = Signature::committer()
+        .unwrap_or_else(|_| Signature::now("ink watch".to_string(), "unknown".to_string()));
+    let message = format!("autosave on {}: {} changed path(s)", branch, dirty.len());
+    let commit = create_commit(repo, &tree, &parents, &message, &signature, &signature)?;
+
+    let path: PathBuf = Path::new(".ink").join("refs").join(&name);
+    create_dir_all(path.parent().unwrap())?;
+    write(path, &commit)?;
+
+    println!("Autosaved {} on refs/{}", &commit[..7], name);
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use anyhow::Result;
+
+use crate::utils::object::create_tree;
+use crate::utils::repo::Repo;
+
+/// `ink write-tree`: stores the index as a tree and prints its hash
+pub fn run() -> Result<()> {
+    println!("{}", create_tree(&Repo::load()?)?);
+    Ok(())
+}
@@ -4,7 +4,19 @@ use std::str::FromStr;
 use clap::{Parser, Subcommand};
 use anyhow::Result;
 
+use crate::commands::bundle::BundleAction;
+use crate::commands::lfs::LfsAction;
+use crate::commands::cat_file::CatMode;
+use crate::commands::commit::CommitOptions;
+use crate::commands::log::LogOptions;
+use crate::commands::ls_tree::LsTreeOptions;
+use crate::commands::remote::RemoteAction;
+use crate::commands::restore::RestoreOptions;
+use crate::commands::rev_parse::RevParseOptions;
+use crate::commands::watch::WatchOptions;
 use crate::utils::enums::{AddMode, HashAlgo};
+use crate::utils::history::Order;
+use crate::utils::sequencer::Control;
 
 mod commands;
 mod utils;
@@ -43,15 +55,62 @@ enum Commands {
 
         #[arg(short)]
         u: bool,
+
+        /// Interactively choose hunks to stage
+        #[arg(short = 'p', long)]
+        patch: bool,
     },
     Commit {
-        message: String,
+        message: Option<String>,
+
+        /// Commit message; repeat to add paragraphs
+        #[arg(short = 'm', long = "message")]
+        paragraphs: Vec<String>,
+
+        /// Read the commit message from a file ("-" for stdin)
+        #[arg(short = 'F', long = "file")]
+        file: Option<PathBuf>,
 
         #[arg(short)]
-        a: bool
+        a: bool,
+
+        /// Allow recording a commit with the same tree as its parent
+        #[arg(long)]
+        allow_empty: bool,
+
+        /// Replace the tip of the current branch
+        #[arg(long)]
+        amend: bool,
+
+        /// Reuse the amended commit's message without opening an editor
+        #[arg(long)]
+        no_edit: bool,
+
+        /// Bypass the pre-commit and commit-msg hooks
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+
+        /// Override the commit author, given as "Name <email>"
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Override the author date
+        #[arg(long)]
+        date: Option<String>,
     },
     Branch {
-        name: Option<String>
+        name: Option<String>,
+
+        /// Show each tip; twice to also show upstream and ahead/behind counts
+        #[arg(short, long, action = clap::ArgAction::Count)]
+        verbose: u8,
+
+        /// Track <remote>/<branch> from the named (or current) branch
+        #[arg(short = 'u', long)]
+        set_upstream_to: Option<String>,
+
+        #[arg(long)]
+        unset_upstream: bool,
     },
     Checkout {
         #[arg(short)]
@@ -60,11 +119,483 @@ enum Commands {
         #[arg(long)]
         force: bool,
 
+        /// Bypass the pre-checkout hook
+        #[arg(long)]
+        no_verify: bool,
+
         name: String,
     },
      Restore {
-        target: PathBuf,
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Restore from this commit instead of the index (or HEAD with --staged)
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Restore the index
+        #[arg(short = 'S', long)]
+        staged: bool,
+
+        /// Restore the working tree (the default unless --staged is given)
+        #[arg(short = 'W', long)]
+        worktree: bool,
+
+        /// Discard local modifications that are not staged
+        #[arg(short, long)]
+        force: bool,
+    },
+    Status {
+        paths: Vec<PathBuf>,
+
+        /// Give the output in the short format
+        #[arg(short, long)]
+        short: bool,
+
+        /// Show the branch and its upstream in the short format
+        #[arg(short, long)]
+        branch: bool,
+    },
+    Diff {
+        /// Revisions to compare, followed by pathspecs
+        args: Vec<String>,
+
+        #[arg(last = true)]
+        paths: Vec<String>,
+
+        /// Compare the index with HEAD (or the given commit)
+        #[arg(long, visible_alias = "cached")]
+        staged: bool,
+
+        /// Only list the names of changed files
+        #[arg(long)]
+        name_only: bool,
+    },
+    Log {
+        /// Revisions to start from, followed by pathspecs
+        args: Vec<String>,
+
+        #[arg(last = true)]
+        paths: Vec<String>,
+
+        /// Limit the number of commits shown
+        #[arg(short = 'n', long)]
+        max_count: Option<usize>,
+
+        #[arg(long)]
+        oneline: bool,
+
+        /// Draw the commit graph
+        #[arg(long)]
+        graph: bool,
+
+        /// Show branch and tag names next to commits
+        #[arg(long)]
+        decorate: bool,
+
+        /// Show commits reachable from any branch or tag
+        #[arg(long)]
+        all: bool,
+
+        /// Show children before parents, keeping lines of history together
+        #[arg(long, conflicts_with = "date_order")]
+        topo_order: bool,
+
+        /// Show children before parents, otherwise by commit date
+        #[arg(long)]
+        date_order: bool,
+    },
+    /// Show the commit that last changed each line of a file
+    Blame {
+        file: PathBuf,
+
+        /// Revision to blame instead of the working tree
+        rev: Option<String>,
+
+        /// Only annotate lines <start>,<end> (or <start>,+<count>)
+        #[arg(short = 'L', value_name = "START,END")]
+        range: Option<String>,
+    },
+    CheckIgnore {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Show the ignore file, line and pattern that matched
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// With -v, also list paths that matched no pattern
+        #[arg(short, long)]
+        non_matching: bool,
+    },
+    Config {
+        key: String,
+
+        value: Option<String>,
+
+        /// Use the user-wide config file instead of the repository's
+        #[arg(long)]
+        global: bool,
+
+        #[arg(long)]
+        unset: bool,
+    },
+    Remote {
+        #[command(subcommand)]
+        action: Option<RemoteCommand>,
+
+        /// Show remote URLs
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    Clone {
+        /// Path or URL of the repository to clone
+        url: String,
+
+        directory: Option<PathBuf>,
+    },
+    Fetch {
+        remote: Option<String>,
+    },
+    Push {
+        remote: Option<String>,
+
+        branch: Option<String>,
+
+        /// Overwrite the remote branch even if it is not an ancestor of ours
+        #[arg(short, long)]
+        force: bool,
+
+        /// Make the pushed branch the upstream of the local one
+        #[arg(short = 'u', long)]
+        set_upstream: bool,
+    },
+    Pull {
+        remote: Option<String>,
+
+        branch: Option<String>,
+    },
+    /// Apply the changes introduced by existing commits
+    CherryPick {
+        /// Commits or <from>..<to> ranges to apply, oldest first
+        #[arg(required_unless_present_any = ["cont", "skip", "abort"])]
+        revs: Vec<String>,
+
+        /// Commit the resolved conflicts and carry on
+        #[arg(long = "continue", conflicts_with_all = ["revs", "skip", "abort"])]
+        cont: bool,
+
+        /// Drop the commit that stopped and carry on
+        #[arg(long, conflicts_with_all = ["revs", "abort"])]
+        skip: bool,
+
+        /// Give up and return to where the sequence started
+        #[arg(long, conflicts_with = "revs")]
+        abort: bool,
+    },
+    /// Record new commits undoing existing ones
+    Revert {
+        /// Commits or <from>..<to> ranges to undo
+        #[arg(required_unless_present_any = ["cont", "skip", "abort"])]
+        revs: Vec<String>,
+
+        /// Commit the resolved conflicts and carry on
+        #[arg(long = "continue", conflicts_with_all = ["revs", "skip", "abort"])]
+        cont: bool,
+
+        /// Drop the commit that stopped and carry on
+        #[arg(long, conflicts_with_all = ["revs", "abort"])]
+        skip: bool,
+
+        /// Give up and return to where the sequence started
+        #[arg(long, conflicts_with = "revs")]
+        abort: bool,
+    },
+    /// Replay the commits of the current branch on top of another base
+    Rebase {
+        /// Branch or commit to rebase onto (defaults to the upstream)
+        upstream: Option<String>,
+
+        /// Edit the list of commits to replay before starting
+        #[arg(short, long, conflicts_with_all = ["cont", "skip", "abort"])]
+        interactive: bool,
+
+        /// Replay onto this commit instead of <upstream>
+        #[arg(long, requires = "upstream")]
+        onto: Option<String>,
+
+        /// Continue after resolving conflicts or editing a commit
+        #[arg(long = "continue", conflicts_with_all = ["upstream", "skip", "abort"])]
+        cont: bool,
+
+        /// Drop the commit that stopped and carry on
+        #[arg(long, conflicts_with_all = ["upstream", "abort"])]
+        skip: bool,
+
+        /// Give up and return the branch to where it was
+        #[arg(long, conflicts_with = "upstream")]
+        abort: bool,
+    },
+    /// Verify the integrity and connectivity of the object store
+    Fsck {
+        /// Also list unreachable objects that other unreachable objects point to
+        #[arg(long)]
+        unreachable: bool,
+    },
+    /// Rewrite the repository to use a different hash algorithm
+    MigrateHash {
+        /// sha256 or blake3
+        algo: HashAlgo,
+    },
+    /// Rewrite stored objects with the configured core.compression, core.compressionLevel and core.chunking
+    Recompress {
+        /// Also rewrite objects already in the configured encoding
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show object store usage and the dedup ratio of chunked blobs
+    Stats,
+    /// Print an object's type, size or contents
+    CatFile {
+        /// Show the object's type
+        #[arg(short = 't', group = "mode")]
+        kind: bool,
+
+        /// Show the object's size
+        #[arg(short = 's', group = "mode")]
+        size: bool,
+
+        /// Print the object's contents
+        #[arg(short = 'p', group = "mode")]
+        pretty: bool,
+
+        /// Exit with zero status if the object exists, without printing anything
+        #[arg(short = 'e', group = "mode")]
+        exists: bool,
+
+        /// Expected type followed by the object, or just the object with a flag
+        #[arg(required = true, num_args = 1..=2, value_name = "[TYPE] OBJECT")]
+        args: Vec<String>,
     },
+    /// Compute blob hashes, optionally storing the blobs
+    HashObject {
+        files: Vec<PathBuf>,
+
+        /// Write the blob into the object database
+        #[arg(short = 'w')]
+        write: bool,
+
+        /// Read the content from standard input
+        #[arg(long)]
+        stdin: bool,
+    },
+    /// List the contents of a tree
+    LsTree {
+        tree_ish: String,
+        paths: Vec<PathBuf>,
+
+        /// Recurse into subtrees
+        #[arg(short = 'r')]
+        recursive: bool,
+
+        /// Show trees while recursing
+        #[arg(short = 't')]
+        trees: bool,
+
+        #[arg(long)]
+        name_only: bool,
+
+        /// Terminate entries with NUL
+        #[arg(short = 'z')]
+        z: bool,
+    },
+    /// List the paths in the index
+    LsFiles {
+        pathspec: Vec<String>,
+
+        /// Show the mode and blob hash of each entry
+        #[arg(short = 's', long)]
+        stage: bool,
+
+        /// Terminate entries with NUL
+        #[arg(short = 'z')]
+        z: bool,
+    },
+    /// Resolve revisions and object names to hashes
+    RevParse {
+        revs: Vec<String>,
+
+        /// Fail unless exactly one valid object is named
+        #[arg(long)]
+        verify: bool,
+
+        /// Abbreviate hashes (to 7 characters by default)
+        #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "7")]
+        short: Option<usize>,
+
+        /// Print short ref names instead of hashes
+        #[arg(long)]
+        abbrev_ref: bool,
+    },
+    /// Update or delete a ref, optionally checking its current value
+    UpdateRef {
+        name: String,
+
+        /// New value (or the expected old value with -d), then the expected old value
+        #[arg(num_args = 0..=2, value_name = "VALUE")]
+        values: Vec<String>,
+
+        /// Delete the ref
+        #[arg(short = 'd')]
+        delete: bool,
+
+        /// Reflog message
+        #[arg(short = 'm')]
+        message: Option<String>,
+    },
+    /// Store the index as a tree object
+    WriteTree,
+    /// Create a commit object from a tree
+    CommitTree {
+        tree: String,
+
+        /// Parent commit; repeat for merges
+        #[arg(short = 'p')]
+        parents: Vec<String>,
+
+        /// Commit message; repeat to add paragraphs
+        #[arg(short = 'm')]
+        message: Vec<String>,
+
+        /// Read the message from a file ("-" for stdin)
+        #[arg(short = 'F')]
+        file: Option<PathBuf>,
+    },
+    /// Create, verify or inspect bundle files for offline transfer
+    Bundle {
+        #[command(subcommand)]
+        action: BundleCommand,
+    },
+    /// Keep large files out of the object store, behind pointer blobs
+    Lfs {
+        #[command(subcommand)]
+        action: LfsCommand,
+    },
+    /// Watch the working tree so status does not have to scan it
+    Watch {
+        /// Stage tracked files whenever they are saved
+        #[arg(long)]
+        stage: bool,
+
+        /// Commit a snapshot to refs/autosave/<branch> every SECS seconds
+        #[arg(long, value_name = "SECS")]
+        autosave: Option<u64>,
+
+        /// Stop the running watcher
+        #[arg(long, conflicts_with_all = ["stage", "autosave", "list"])]
+        stop: bool,
+
+        /// List the paths the running watcher considers changed
+        #[arg(long, conflicts_with_all = ["stage", "autosave"])]
+        list: bool,
+    },
+    /// Interactive terminal UI for status, branches, history and diffs
+    Tui,
+    /// Serve this repository over HTTP for fetch and push
+    Serve {
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        #[arg(short, long, default_value_t = 7418)]
+        port: u16,
+
+        /// Serve on addresses other than loopback, although pushes are not authenticated
+        #[arg(long)]
+        allow_remote: bool,
+    },
+    #[command(hide = true)]
+    UploadPack {
+        #[arg(long)]
+        advertise: bool,
+
+        #[arg(long)]
+        lfs: bool,
+    },
+    #[command(hide = true)]
+    ReceivePack,
+}
+
+#[derive(Subcommand)]
+enum BundleCommand {
+    Create {
+        file: PathBuf,
+
+        /// Branches to include, `^<rev>` or `<rev>..<branch>` to leave out history
+        revs: Vec<String>,
+
+        /// Include every branch
+        #[arg(long)]
+        all: bool,
+    },
+    Verify {
+        file: PathBuf,
+    },
+    ListHeads {
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum LfsCommand {
+    /// Store paths matching these patterns as large files; lists the patterns without arguments
+    Track {
+        patterns: Vec<String>,
+    },
+    /// Stop storing paths matching these patterns as large files
+    Untrack {
+        #[arg(required = true)]
+        patterns: Vec<String>,
+    },
+    /// List the large files in a commit (default HEAD)
+    #[command(visible_alias = "ls-files")]
+    Ls {
+        rev: Option<String>,
+    },
+    /// Download large files the index refers to and check them out
+    Fetch {
+        remote: Option<String>,
+
+        /// Also fetch the large files of every branch and tag
+        #[arg(long)]
+        all: bool,
+    },
+    /// Delete stored large files nothing refers to any more
+    Prune {
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RemoteCommand {
+    Add {
+        name: String,
+        url: String,
+    },
+    #[command(visible_alias = "rm")]
+    Remove {
+        name: String,
+    },
+}
+
+/// Maps the `--continue`/`--skip`/`--abort` flags of cherry-pick and revert
+fn control(cont: bool, skip: bool, abort: bool) -> Option<Control> {
+    match (cont, skip, abort) {
+        (true, _, _) => Some(Control::Continue),
+        (_, true, _) => Some(Control::Skip),
+        (_, _, true) => Some(Control::Abort),
+        _ => None,
+    }
 }
 
 fn main() -> Result<()> {
@@ -100,8 +631,10 @@ fn main() -> Result<()> {
             commands::init::run(HashAlgo::default(), z)?
         },
         Commands::Uninit => commands::uninit::run()?,
-        Commands::Add { files, u } => {
-            if u {
+        Commands::Add { files, u, patch } => {
+            if patch {
+                commands::add::run(AddMode::Patch(files))?
+            } else if u {
                 commands::add::run(AddMode::Update)?
             } else if files == vec![PathBuf::from(".")] {
                 commands::add::run(AddMode::All)?
@@ -109,10 +642,132 @@ fn main() -> Result<()> {
                 commands::add::run(AddMode::Files(files))?
             }
         },
-        Commands::Commit { message, a } => commands::commit::run(message, a)?,
-        Commands::Branch { name } => commands::branch::run(name)?,
-        Commands::Checkout { b, force, name } => commands::checkout::run(b, force, name)?,
-        Commands::Restore { target } => commands::restore::run(target)?,
+        Commands::Commit { message, paragraphs, file, a, allow_empty, amend, no_edit, no_verify, author, date } => {
+            commands::commit::run(CommitOptions {
+                message,
+                paragraphs,
+                file,
+                all: a,
+                author,
+                date,
+                allow_empty,
+                amend,
+                no_edit,
+                no_verify,
+            })?
+        },
+        Commands::Branch { name, verbose, set_upstream_to, unset_upstream } => {
+            if let Some(upstream) = set_upstream_to {
+                commands::branch::set_upstream_to(&upstream, name)?
+            } else if unset_upstream {
+                let branch = match name {
+                    Some(n) => n,
+                    None => commands::branch::read_current_branch()?,
+                };
+                commands::branch::unset_upstream(&branch)?;
+            } else if name.is_none() {
+                commands::branch::list(verbose)?
+            } else {
+                commands::branch::run(name)?
+            }
+        },
+        Commands::Checkout { b, force, no_verify, name } => commands::checkout::run(b, force, no_verify, name)?,
+        Commands::Restore { paths, source, staged, worktree, force } => {
+            commands::restore::run(paths, RestoreOptions { source, staged, worktree, force })?
+        },
+        Commands::Status { paths, short, branch } => commands::status::run(paths, short, branch)?,
+        Commands::Diff { args, paths, staged, name_only } => commands::diff::run(args, paths, staged, name_only)?,
+        Commands::Log { args, paths, max_count, oneline, graph, decorate, all, topo_order, date_order } => {
+            let order = if topo_order {
+                Order::Topo
+            } else if date_order {
+                Order::Date
+            } else {
+                Order::Walk
+            };
+            commands::log::run(args, paths, LogOptions { max_count, oneline, graph, decorate, all, order })?
+        },
+        Commands::Blame { file, rev, range } => commands::blame::run(file, rev, range)?,
+        Commands::CheckIgnore { paths, verbose, non_matching } => commands::check_ignore::run(paths, verbose, non_matching)?,
+        Commands::Config { key, value, global, unset } => commands::config::run(key, value, global, unset)?,
+        Commands::Remote { action, verbose } => {
+            commands::remote::run(match action {
+                None => RemoteAction::List { verbose },
+                Some(RemoteCommand::Add { name, url }) => RemoteAction::Add { name, url },
+                Some(RemoteCommand::Remove { name }) => RemoteAction::Remove { name },
+            })?
+        },
+        Commands::Clone { url, directory } => commands::clone::run(url, directory)?,
+        Commands::Fetch { remote } => commands::fetch::run(remote)?,
+        Commands::Push { remote, branch, force, set_upstream } => commands::push::run(remote, branch, force, set_upstream)?,
+        Commands::Pull { remote, branch } => commands::pull::run(remote, branch)?,
+        Commands::CherryPick { revs, cont, skip, abort } => commands::cherry_pick::run(revs, control(cont, skip, abort))?,
+        Commands::Revert { revs, cont, skip, abort } => commands::revert::run(revs, control(cont, skip, abort))?,
+        Commands::Rebase { upstream, interactive, onto, cont, skip, abort } => match control(cont, skip, abort) {
+            Some(control) => commands::rebase::resume(control)?,
+            None => commands::rebase::run(upstream, onto, interactive)?,
+        },
+        Commands::Fsck { unreachable } => commands::fsck::run(unreachable)?,
+        Commands::MigrateHash { algo } => commands::migrate_hash::run(algo)?,
+        Commands::Recompress { all } => commands::recompress::run(all)?,
+        Commands::Stats => commands::stats::run()?,
+        Commands::CatFile { kind, size, pretty, exists, mut args } => {
+            let object = args.pop().unwrap();
+            let mode = match (args.pop(), kind, size, pretty, exists) {
+                (Some(expected), false, false, false, false) => CatMode::Expect(expected),
+                (Some(_), ..) => return Err(anyhow::anyhow!("Give either a type or a flag, not both")),
+                (None, true, ..) => CatMode::Type,
+                (None, _, true, ..) => CatMode::Size,
+                (None, _, _, true, _) => CatMode::Pretty,
+                (None, _, _, _, true) => CatMode::Exists,
+                (None, ..) => return Err(anyhow::anyhow!("Give a type or one of -t, -s, -p, -e")),
+            };
+            commands::cat_file::run(mode, object)?
+        },
+        Commands::HashObject { files, write, stdin } => commands::hash_object::run(files, stdin, write)?,
+        Commands::LsTree { tree_ish, paths, recursive, trees, name_only, z } => {
+            commands::ls_tree::run(tree_ish, paths, LsTreeOptions { recursive, trees, name_only, nul: z })?
+        },
+        Commands::LsFiles { pathspec, stage, z } => commands::ls_files::run(pathspec, stage, z)?,
+        Commands::RevParse { revs, verify, short, abbrev_ref } => {
+            commands::rev_parse::run(revs, RevParseOptions { verify, short, abbrev_ref })?
+        },
+        Commands::UpdateRef { name, values, delete, message } => {
+            let mut values = values.into_iter();
+            let (new, old) = if delete { (None, values.next()) } else { (values.next(), values.next()) };
+            if !delete && new.is_none() {
+                return Err(anyhow::anyhow!("update-ref needs a new value, or -d to delete"));
+            }
+            if values.next().is_some() {
+                return Err(anyhow::anyhow!("Too many values for update-ref"));
+            }
+            commands::update_ref::run(name, new, old, message)?
+        },
+        Commands::WriteTree => commands::write_tree::run()?,
+        Commands::CommitTree { tree, parents, message, file } => commands::commit_tree::run(tree, parents, message, file)?,
+        Commands::Bundle { action } => {
+            commands::bundle::run(match action {
+                BundleCommand::Create { file, revs, all } => BundleAction::Create { file, revs, all },
+                BundleCommand::Verify { file } => BundleAction::Verify { file },
+                BundleCommand::ListHeads { file } => BundleAction::ListHeads { file },
+            })?
+        },
+        Commands::Lfs { action } => {
+            commands::lfs::run(match action {
+                LfsCommand::Track { patterns } => LfsAction::Track { patterns },
+                LfsCommand::Untrack { patterns } => LfsAction::Untrack { patterns },
+                LfsCommand::Ls { rev } => LfsAction::Ls { rev },
+                LfsCommand::Fetch { remote, all } => LfsAction::Fetch { remote, all },
+                LfsCommand::Prune { dry_run } => LfsAction::Prune { dry_run },
+            })?
+        },
+        Commands::Watch { stage, autosave, stop, list } => {
+            commands::watch::run(WatchOptions { stage, autosave, stop, list })?
+        },
+        Commands::Tui => commands::tui::run()?,
+        Commands::Serve { host, port, allow_remote } => commands::serve::run(host, port, allow_remote)?,
+        Commands::UploadPack { advertise, lfs } => commands::upload_pack::run(advertise, lfs)?,
+        Commands::ReceivePack => commands::receive_pack::run()?,
     }
 
     Ok(())
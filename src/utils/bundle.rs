@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+use crate::utils::object::{object_exists, open_object, read_commit_object};
+use crate::utils::remote::{
+    check_pack, read_pack, write_pack, Advertisement, FetchRequest, PushRequest, PushResult, Received,
+};
+use crate::utils::repo::Repo;
+use crate::utils::revision::check_ref_name;
+use crate::utils::transport::Transport;
+
+const SIGNATURE: &str = "# ink bundle v1";
+
+/// A self-contained file holding ref tips, the objects needed to reach them
+/// and the contents of any large files among those objects.
+///
+/// The file starts with a text header, then a blank line and a pack stream
+/// (see `remote::write_pack`):
+///
+/// ```text
+/// # ink bundle v1
+/// hash blake3
+/// -<commit>            (prerequisite the receiver must already have)
+/// <commit> refs/heads/main
+/// ```
+pub struct Bundle {
+    pub hash_algo: String,
+    pub prerequisites: Vec<String>,
+    pub refs: Vec<(String, String)>,
+    /// File the bundle was read from and the offset of its pack stream
+    source: Option<(PathBuf, u64)>,
+}
+
+impl Bundle {
+    /// Describes a bundle of `objects`, deriving the prerequisites from commits
+    /// whose parents are not among them
+    pub fn new(hash_algo: String, refs: Vec<(String, String)>, objects: &[String]) -> Result<Self> {
+        let included: HashSet<&String> = objects.iter().collect();
+
+        let mut prerequisites = Vec::new();
+        for hash in objects {
+            if open_object(hash)?.0 != "commit" {
+                continue;
+            }
+            for parent in read_commit_object(hash)?.parents {
+                if !included.contains(&parent) && !prerequisites.contains(&parent) {
+                    prerequisites.push(parent);
+                }
+            }
+        }
+
+        Ok(Bundle { hash_algo, prerequisites, refs, source: None })
+    }
+
+    pub fn is_bundle(path: &Path) -> bool {
+        let mut start = [0u8; SIGNATURE.len()];
+        File::open(path).and_then(|mut f| f.read_exact(&mut start)).is_ok() && start == SIGNATURE.as_bytes()
+    }
+
+    /// Writes the header followed by `objects` and the large file contents `lfs`
+    pub fn write(&self, path: &Path, objects: &[String], lfs: &[String]) -> Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}\nhash {}", SIGNATURE, self.hash_algo)?;
+        for hash in &self.prerequisites {
+            writeln!(out, "-{}", hash)?;
+        }
+        for (name, hash) in &self.refs {
+            writeln!(out, "{} {}", hash, name)?;
+        }
+        writeln!(out)?;
+
+        write_pack(&mut out, objects, lfs)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Reads a bundle's header; its contents are read by `unpack` and `check`
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut offset = 0;
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            let n = reader.read_line(&mut line)?;
+            if n == 0 {
+                return Err(anyhow!("'{}' is not a valid bundle", path.display()));
+            }
+            offset += n as u64;
+            if line == "\n" {
+                break;
+            }
+            lines.push(line.trim_end_matches('\n').to_string());
+        }
+
+        let mut lines = lines.into_iter();
+        if lines.next().as_deref() != Some(SIGNATURE) {
+            return Err(anyhow!("'{}' is not an ink bundle", path.display()));
+        }
+
+        let mut bundle = Bundle {
+            hash_algo: String::new(),
+            prerequisites: Vec::new(),
+            refs: Vec::new(),
+            source: Some((path.to_path_buf(), offset)),
+        };
+
+        for line in lines {
+            if let Some(algo) = line.strip_prefix("hash ") {
+                bundle.hash_algo = algo.to_string();
+            } else if let Some(hash) = line.strip_prefix('-') {
+                bundle.prerequisites.push(hash.to_string());
+            } else if let Some((hash, name)) = line.split_once(' ') {
+                check_ref_name(name)?;
+                bundle.refs.push((name.to_string(), hash.to_string()));
+            } else {
+                return Err(anyhow!("Malformed bundle header line: {}", line));
+            }
+        }
+
+        Ok(bundle)
+    }
+
+    /// Stores the bundle's contents in the current repository
+    pub fn unpack(&self, repo: &Repo) -> Result<Received> {
+        read_pack(repo, &mut self.open_pack()?)
+    }
+
+    /// Checks every object and large file in the bundle against its hash
+    pub fn check(&self, repo: &Repo) -> Result<Received> {
+        check_pack(repo, &mut self.open_pack()?)
+    }
+
+    fn open_pack(&self) -> Result<BufReader<File>> {
+        let (path, offset) = self.source.as_ref().ok_or_else(|| anyhow!("The bundle has not been written yet"))?;
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(*offset))?;
+        Ok(BufReader::new(file))
+    }
+
+    /// Prerequisite commits missing from the current repository
+    pub fn missing_prerequisites(&self) -> Vec<&String> {
+        self.prerequisites.iter().filter(|h| !object_exists(h)).collect()
+    }
+}
+
+/// Reads refs and objects from a bundle file as if it were a remote
+pub struct BundleTransport {
+    bundle: Bundle,
+}
+
+impl BundleTransport {
+    pub fn new(path: &Path) -> Result<Self> {
+        Ok(BundleTransport { bundle: Bundle::read(path)? })
+    }
+}
+
+impl Transport for BundleTransport {
+    fn advertise(&mut self) -> Result<Advertisement> {
+        let refs = self.bundle.refs.clone();
+        let head = refs
+            .iter()
+            .map(|(name, _)| name.clone())
+            .find(|name| name == "refs/heads/main")
+            .or_else(|| refs.first().map(|(name, _)| name.clone()));
+
+        Ok(Advertisement { hash_algo: self.bundle.hash_algo.clone(), head, refs })
+    }
+
+    fn fetch(&mut self, repo: &Repo, _req: &FetchRequest) -> Result<Received> {
+        if let Some(missing) = self.bundle.missing_prerequisites().first() {
+            return Err(anyhow!("The bundle requires commit {}, which this repository does not have", missing));
+        }
+        self.bundle.unpack(repo)
+    }
+
+    fn push(&mut self, _req: &PushRequest, _objects: &[String], _lfs: &[String]) -> Result<PushResult> {
+        Err(anyhow!("Cannot push to a bundle; use 'ink bundle create' instead"))
+    }
+
+    /// Large files are stored with the objects by `fetch`; this picks up any
+    /// that were missing then, e.g. when fetching into an existing repository
+    fn fetch_lfs(&mut self, repo: &Repo, _ids: &[String]) -> Result<Received> {
+        self.bundle.unpack(repo)
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::VecDeque;
+use std::fs::{create_dir_all, read, remove_file, rename, write};
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::{anyhow, Result};
+
+use crate::utils::config::get_config;
+use crate::utils::enums::HashAlgo;
+use crate::utils::hash::hash_with;
+use crate::utils::object::{is_hex, ZERO_HASH};
+use crate::utils::repo::Repo;
+use crate::utils::zip::{decompress, string_to_bool};
+
+/// A chunked object's file starts with this, followed by its header:
+///
+/// ```text
+/// chunked blob <size>
+/// <blake3 of chunk> <length>
+/// ...
+/// ```
+pub const MANIFEST_MAGIC: &[u8] = b"chunked ";
+
+/// Chunks are shared by every object, so they live beside the object store
+const CHUNKS: &str = ".ink/chunks";
+
+/// Blobs smaller than this are stored whole even with chunking enabled
+pub const CHUNK_THRESHOLD: usize = 512 * 1024;
+
+const MIN_CHUNK: usize = 16 * 1024;
+const MAX_CHUNK: usize = 256 * 1024;
+
+/// A boundary is cut where the top 16 bits of the rolling hash are clear,
+/// about once every 64 KiB past the minimum
+const MASK: u64 = 0xffff << 48;
+
+/// Bytes a hash value depends on: each step shifts older bytes one bit further out
+const WINDOW: usize = 64;
+
+/// Random values mixed in per byte. Changing them moves every boundary, which
+/// stays correct but stops new chunks matching the ones already stored.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        // splitmix64
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// `core.chunking`: whether large blobs are split into content-defined chunks
+pub fn load_chunking() -> Result<bool> {
+    match get_config("core.chunking")? {
+        Some(value) => string_to_bool(&value),
+        None => Ok(false),
+    }
+}
+
+/// Splits a stream into chunks whose boundaries depend only on the bytes around
+/// them, so an insertion early in a file leaves the later chunks unchanged
+pub struct Chunker<R> {
+    reader: R,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> Chunker<R> {
+    pub fn new(reader: R) -> Self {
+        Chunker { reader, buf: Vec::with_capacity(MAX_CHUNK), eof: false }
+    }
+}
+
+impl<R: Read> Iterator for Chunker<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.eof && self.buf.len() < MAX_CHUNK {
+            let start = self.buf.len();
+            self.buf.resize(MAX_CHUNK, 0);
+            match self.reader.read(&mut self.buf[start..]) {
+                Ok(0) => {
+                    self.buf.truncate(start);
+                    self.eof = true;
+                }
+                Ok(n) => self.buf.truncate(start + n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => self.buf.truncate(start),
+                Err(e) => {
+                    self.buf.truncate(start);
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        if self.buf.is_empty() {
+            return None;
+        }
+        let cut = cut_point(&self.buf);
+        Some(Ok(self.buf.drain(..cut).collect()))
+    }
+}
+
+/// Length of the first chunk of `data`, using a gear hash over the last `WINDOW` bytes
+fn cut_point(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK {
+        return data.len();
+    }
+
+    let end = data.len().min(MAX_CHUNK);
+    let mut hash: u64 = 0;
+    for (i, byte) in data.iter().enumerate().take(end).skip(MIN_CHUNK - WINDOW) {
+        hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+        if i >= MIN_CHUNK && hash & MASK == 0 {
+            return i + 1;
+        }
+    }
+    end
+}
+
+/// Whether a stored object file is a chunk list rather than the object itself
+pub fn is_manifest(data: &[u8]) -> bool {
+    data.starts_with(MANIFEST_MAGIC)
+}
+
+/// Splits an object body into chunks, stores them and returns the object's
+/// hash, the manifest to store in its place and the number of body bytes read
+pub fn store_chunks(repo: &Repo, kind: &str, size: u64, body: impl Read) -> Result<(String, Vec<u8>, u64)> {
+    let header = format!("{} {}", kind, size);
+    let mut hasher = repo.hasher();
+    hasher.update(header.as_bytes());
+    hasher.update(&[0]);
+
+    let mut manifest = format!("chunked {}\n", header);
+    let mut total = 0;
+    for chunk in Chunker::new(body) {
+        let chunk = chunk?;
+        hasher.update(&chunk);
+        manifest += &format!("{} {}\n", write_chunk(repo, &chunk)?, chunk.len());
+        total += chunk.len() as u64;
+    }
+
+    Ok((hasher.finish(), manifest.into_bytes(), total))
+}
+
+/// The object header (`blob <size>`) and chunk list of a manifest
+pub fn parse_manifest(data: &[u8]) -> Result<(String, Vec<(String, u64)>)> {
+    let text = std::str::from_utf8(data).map_err(|_| anyhow!("chunk list is not UTF-8"))?;
+    let mut lines = text.lines();
+    let header = lines
+        .next()
+        .and_then(|line| line.strip_prefix("chunked "))
+        .ok_or_else(|| anyhow!("malformed chunk list"))?;
+
+    let mut chunks = Vec::new();
+    for line in lines {
+        let (name, len) = line.split_once(' ').ok_or_else(|| anyhow!("malformed chunk list line '{}'", line))?;
+        let len = len.parse().map_err(|_| anyhow!("malformed chunk list line '{}'", line))?;
+        if name.len() != ZERO_HASH.len() || !is_hex(name) {
+            return Err(anyhow!("malformed chunk list line '{}'", line));
+        }
+        chunks.push((name.to_string(), len));
+    }
+    Ok((header.to_string(), chunks))
+}
+
+/// The complete object (`<header>\0<body>`) a manifest stands for
+pub fn reassemble(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    reader(data)?.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Reads the complete object a manifest stands for, loading one chunk at a time
+pub fn reader(data: &[u8]) -> Result<Box<dyn Read>> {
+    let (header, chunks) = parse_manifest(data)?;
+    Ok(Box::new(ChunkedReader {
+        current: Cursor::new(format!("{}\0", header).into_bytes()),
+        pending: chunks.into(),
+    }))
+}
+
+struct ChunkedReader {
+    current: Cursor<Vec<u8>>,
+    pending: VecDeque<(String, u64)>,
+}
+
+impl Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.current.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            let Some((name, len)) = self.pending.pop_front() else {
+                return Ok(0);
+            };
+            self.current = Cursor::new(read_chunk(&name, len).map_err(io::Error::other)?);
+        }
+    }
+}
+
+/// A chunk's content, checked against its name and the length the manifest expects
+pub fn read_chunk(name: &str, len: u64) -> Result<Vec<u8>> {
+    let data = read(chunk_path(name)).map_err(|e| anyhow!("cannot read chunk {}: {}", name, e))?;
+    let full = decompress(data).map_err(|e| anyhow!("chunk {} is corrupt: {}", name, e))?;
+
+    let nul = full.iter().position(|b| *b == 0).ok_or_else(|| anyhow!("chunk {} has no header", name))?;
+    let body = full[nul + 1..].to_vec();
+    if body.len() as u64 != len || hash_with(HashAlgo::Blake3, &body) != name {
+        return Err(anyhow!("chunk {} is corrupt (hash or length mismatch)", name));
+    }
+    Ok(body)
+}
+
+/// Location of a chunk, named by the BLAKE3 hash of its content
+pub fn chunk_path(name: &str) -> PathBuf {
+    Path::new(CHUNKS).join(&name[..2]).join(&name[2..])
+}
+
+/// Names of every stored chunk
+pub fn list_chunks() -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    if !Path::new(CHUNKS).is_dir() {
+        return Ok(names);
+    }
+
+    for entry in walkdir::WalkDir::new(CHUNKS).min_depth(2).max_depth(2) {
+        let entry = entry?;
+        let prefix = entry.path().parent().and_then(Path::file_name).unwrap_or_default().to_string_lossy();
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.file_type().is_file() && name.len() == ZERO_HASH.len() && is_hex(&name) {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Stores a chunk (as `chunk <len>\0<data>`, compressed like objects) unless it is already there
+fn write_chunk(repo: &Repo, data: &[u8]) -> Result<String> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = hash_with(HashAlgo::Blake3, data);
+    let path = chunk_path(&name);
+    if path.exists() {
+        return Ok(name);
+    }
+
+    let full = [format!("chunk {}\0", data.len()).as_bytes(), data].concat();
+    let dir = path.parent().unwrap();
+    create_dir_all(dir)?;
+
+    // Several threads may store the same chunk; each writes its own file and renames it into place
+    let tmp = dir.join(format!("tmp-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    write(&tmp, repo.compress(full)?)?;
+    if let Err(e) = rename(&tmp, &path) {
+        remove_file(&tmp).ok();
+        return Err(e.into());
+    }
+    Ok(name)
+}
@@ -0,0 +1,124 @@
+use std::env;
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+
+/// Path of the user-wide config file (`$INK_CONFIG_GLOBAL`, or `~/.inkconfig`)
+pub fn global_config_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("INK_CONFIG_GLOBAL") {
+        return Some(PathBuf::from(path));
+    }
+
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".inkconfig"))
+}
+
+/// Reads a single `key=value` entry from a config file
+pub fn read_config_file(path: &Path, key: &str) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    for line in read_to_string(path)?.lines() {
+        if let Some((k, v)) = line.split_once('=')
+            && k.trim() == key
+        {
+            return Ok(Some(v.trim().to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Writes (or replaces) a single `key=value` entry in a config file
+pub fn write_config_file(path: &Path, key: &str, value: &str) -> Result<()> {
+    let mut lines = if path.exists() {
+        read_to_string(path)?
+            .lines()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+
+    let mut found = false;
+    for line in lines.iter_mut() {
+        if let Some((k, _)) = line.split_once('=')
+            && k.trim() == key
+        {
+            *line = format!("{}={}", key, value);
+            found = true;
+            break;
+        }
+    }
+
+    if !found {
+        lines.push(format!("{}={}", key, value));
+    }
+
+    write(path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Removes a `key=value` entry from a config file, returning whether it existed
+pub fn unset_config_file(path: &Path, key: &str) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let contents = read_to_string(path)?;
+    let kept: Vec<&str> = contents
+        .lines()
+        .filter(|line| !matches!(line.split_once('='), Some((k, _)) if k.trim() == key))
+        .collect();
+
+    let removed = kept.len() != contents.lines().count();
+    if removed {
+        write(path, kept.join("\n") + "\n")?;
+    }
+
+    Ok(removed)
+}
+
+/// Looks a key up in the repository config first, then the global config
+pub fn get_config(key: &str) -> Result<Option<String>> {
+    if let Some(value) = read_config_file(Path::new(".ink/config"), key)? {
+        return Ok(Some(value));
+    }
+
+    match global_config_path() {
+        Some(path) => read_config_file(&path, key),
+        None => Ok(None),
+    }
+}
+
+pub fn set_config(key: &str, value: &str, global: bool) -> Result<()> {
+    write_config_file(&config_path(global)?, key, value)
+}
+
+pub fn unset_config(key: &str, global: bool) -> Result<bool> {
+    unset_config_file(&config_path(global)?, key)
+}
+
+fn config_path(global: bool) -> Result<PathBuf> {
+    if global {
+        global_config_path().ok_or_else(|| anyhow::anyhow!("Cannot locate the global config file"))
+    } else {
+        Ok(PathBuf::from(".ink/config"))
+    }
+}
+
+/// All `key=value` entries of a config file, in file order
+pub fn list_config_file(path: &Path) -> Result<Vec<(String, String)>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(read_to_string(path)?
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect())
+}
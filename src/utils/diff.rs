@@ -0,0 +1,429 @@
+use colored::Colorize;
+
+/// A single step of an edit script, with indices into the old and new sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Computes a shortest edit script between `a` and `b` (Myers' O(ND) algorithm)
+pub fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    // Common prefix and suffix never take part in the search
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Equal(i, i)).collect();
+    edits.extend(myers(a_mid, b_mid).into_iter().map(|e| match e {
+        Edit::Equal(i, j) => Edit::Equal(i + prefix, j + prefix),
+        Edit::Delete(i) => Edit::Delete(i + prefix),
+        Edit::Insert(j) => Edit::Insert(j + prefix),
+    }));
+    edits.extend((0..suffix).map(|i| Edit::Equal(a.len() - suffix + i, b.len() - suffix + i)));
+    edits
+}
+
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    if max == 0 {
+        return Vec::new();
+    }
+
+    let offset = max + 1;
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    // trace[d] holds the slice v[-d-1..=d+1] as it was before round d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+
+        let mut k = -d;
+        while k <= d {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for (d, saved) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| saved[(k + d + 1) as usize];
+        let k = x - y;
+
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal((x - 1) as usize, (y - 1) as usize));
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert((y - 1) as usize));
+            } else {
+                edits.push(Edit::Delete((x - 1) as usize));
+            }
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    edits.reverse();
+    edits
+}
+
+/// Kind of a line inside a hunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Removed,
+    Added,
+}
+
+/// A contiguous group of changes with surrounding context
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// 1-based first line in the old text (0 when the hunk starts an empty file)
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    /// Lines including their trailing newline, if any
+    pub lines: Vec<(LineKind, String)>,
+}
+
+impl Hunk {
+    pub fn header(&self) -> String {
+        format!("@@ -{} +{} @@", range(self.old_start, self.old_len), range(self.new_start, self.new_len))
+    }
+}
+
+fn range(start: usize, len: usize) -> String {
+    if len == 1 { start.to_string() } else { format!("{},{}", start, len) }
+}
+
+/// Splits text into lines, keeping each line's newline so content round-trips exactly
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Groups the differences between two texts into hunks with `context` lines around them
+pub fn make_hunks(old: &str, new: &str, context: usize) -> Vec<Hunk> {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let edits = diff(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+
+    // Merge changes whose context would overlap into one edit range
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(edits.len());
+        match ranges.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let (mut old_pos, mut new_pos) = position_before(&edits, start);
+            let old_start = old_pos + 1;
+            let new_start = new_pos + 1;
+            let mut lines = Vec::new();
+
+            for edit in &edits[start..end] {
+                match *edit {
+                    Edit::Equal(i, _) => {
+                        lines.push((LineKind::Context, old_lines[i].to_string()));
+                        old_pos += 1;
+                        new_pos += 1;
+                    }
+                    Edit::Delete(i) => {
+                        lines.push((LineKind::Removed, old_lines[i].to_string()));
+                        old_pos += 1;
+                    }
+                    Edit::Insert(j) => {
+                        lines.push((LineKind::Added, new_lines[j].to_string()));
+                        new_pos += 1;
+                    }
+                }
+            }
+
+            let old_len = old_pos + 1 - old_start;
+            let new_len = new_pos + 1 - new_start;
+            Hunk {
+                old_start: if old_len == 0 { old_start - 1 } else { old_start },
+                old_len,
+                new_start: if new_len == 0 { new_start - 1 } else { new_start },
+                new_len,
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Number of old and new lines consumed before edit `idx`
+fn position_before(edits: &[Edit], idx: usize) -> (usize, usize) {
+    edits[..idx].iter().fold((0, 0), |(o, n), e| match e {
+        Edit::Equal(..) => (o + 1, n + 1),
+        Edit::Delete(_) => (o + 1, n),
+        Edit::Insert(_) => (o, n + 1),
+    })
+}
+
+pub fn is_binary(data: &[u8]) -> bool {
+    data.iter().take(8000).any(|b| *b == 0)
+}
+
+/// Formats a unified diff between two versions of `path`
+pub fn unified_diff(path: &str, old: Option<&[u8]>, new: Option<&[u8]>, color: bool) -> String {
+    let mut out = String::new();
+    let paint = |s: String, f: fn(&str) -> colored::ColoredString| {
+        if color { f(&s).to_string() } else { s }
+    };
+
+    out += &paint(format!("diff --ink a/{} b/{}\n", path, path), |s| s.bold());
+    match (old, new) {
+        (None, Some(_)) => out += &paint("new file\n".to_string(), |s| s.bold()),
+        (Some(_), None) => out += &paint("deleted file\n".to_string(), |s| s.bold()),
+        _ => {}
+    }
+
+    let old_name = if old.is_some() { format!("a/{}", path) } else { "/dev/null".to_string() };
+    let new_name = if new.is_some() { format!("b/{}", path) } else { "/dev/null".to_string() };
+
+    let (old, new) = (old.unwrap_or_default(), new.unwrap_or_default());
+    if is_binary(old) || is_binary(new) {
+        out += &format!("Binary files {} and {} differ\n", old_name, new_name);
+        return out;
+    }
+
+    out += &paint(format!("--- {}\n", old_name), |s| s.bold());
+    out += &paint(format!("+++ {}\n", new_name), |s| s.bold());
+
+    let hunks = make_hunks(&String::from_utf8_lossy(old), &String::from_utf8_lossy(new), 3);
+    for hunk in &hunks {
+        out += &format_hunk(hunk, color);
+    }
+
+    out
+}
+
+/// Formats one hunk with its header, marking lines without a trailing newline
+pub fn format_hunk(hunk: &Hunk, color: bool) -> String {
+    let mut out = if color {
+        format!("{}\n", hunk.header().cyan())
+    } else {
+        format!("{}\n", hunk.header())
+    };
+
+    for (kind, text) in &hunk.lines {
+        let (marker, body) = (
+            match kind {
+                LineKind::Context => ' ',
+                LineKind::Removed => '-',
+                LineKind::Added => '+',
+            },
+            text.strip_suffix('\n').unwrap_or(text),
+        );
+
+        let line = format!("{}{}", marker, body);
+        out += &match (color, kind) {
+            (true, LineKind::Removed) => line.red().to_string(),
+            (true, LineKind::Added) => line.green().to_string(),
+            _ => line,
+        };
+        out.push('\n');
+
+        if !text.ends_with('\n') {
+            out += "\\ No newline at end of file\n";
+        }
+    }
+
+    out
+}
+
+impl Hunk {
+    /// Splits a hunk at the context runs between its change groups.
+    /// Returns `None` when there is only one group.
+    pub fn split(&self) -> Option<Vec<Hunk>> {
+        // Line ranges of each run of changes
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        let mut i = 0;
+        while i < self.lines.len() {
+            if self.lines[i].0 == LineKind::Context {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < self.lines.len() && self.lines[i].0 != LineKind::Context {
+                i += 1;
+            }
+            groups.push((start, i));
+        }
+
+        if groups.len() < 2 {
+            return None;
+        }
+
+        let mut hunks = Vec::new();
+        for g in 0..groups.len() {
+            // Context shared with the neighbouring groups is shown on both sides
+            let from = if g == 0 { 0 } else { groups[g - 1].1 };
+            let to = groups.get(g + 1).map(|n| n.0).unwrap_or(self.lines.len());
+
+            let (old_before, new_before) = self.lines[..from].iter().fold((0, 0), |(o, n), (k, _)| match k {
+                LineKind::Context => (o + 1, n + 1),
+                LineKind::Removed => (o + 1, n),
+                LineKind::Added => (o, n + 1),
+            });
+
+            let lines = self.lines[from..to].to_vec();
+            let old_len = lines.iter().filter(|(k, _)| *k != LineKind::Added).count();
+            let new_len = lines.iter().filter(|(k, _)| *k != LineKind::Removed).count();
+
+            hunks.push(Hunk {
+                old_start: self.first_old_line() + old_before,
+                old_len,
+                new_start: self.first_new_line() + new_before,
+                new_len,
+                lines,
+            });
+        }
+
+        Some(hunks)
+    }
+
+    /// 1-based number of the first old line covered by this hunk
+    fn first_old_line(&self) -> usize {
+        if self.old_len == 0 { self.old_start + 1 } else { self.old_start }
+    }
+
+    fn first_new_line(&self) -> usize {
+        if self.new_len == 0 { self.new_start + 1 } else { self.new_start }
+    }
+
+    /// The old side of the hunk (context and removed lines)
+    pub fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter(|(k, _)| *k != LineKind::Added)
+            .map(|(_, t)| t.as_str())
+            .collect()
+    }
+
+    /// Rebuilds a hunk from an edited `+`/`-`/` ` listing, keeping this hunk's position.
+    /// Fails if the old side no longer matches, since the result could not be applied.
+    pub fn with_edit(&self, text: &str) -> Result<Hunk, String> {
+        let original_old = self.old_lines();
+        let mut lines = Vec::new();
+        let mut old_idx = 0;
+
+        for raw in text.lines() {
+            if raw.starts_with('#') {
+                continue;
+            }
+            let (kind, body) = match raw.chars().next() {
+                Some('+') => (LineKind::Added, &raw[1..]),
+                Some('-') => (LineKind::Removed, &raw[1..]),
+                Some(' ') => (LineKind::Context, &raw[1..]),
+                None => (LineKind::Context, ""),
+                Some(_) => return Err(format!("unexpected line in edited hunk: {}", raw)),
+            };
+
+            let text = if kind == LineKind::Added {
+                format!("{}\n", body)
+            } else {
+                // Reuse the original line so a missing final newline is preserved
+                let orig = original_old.get(old_idx).ok_or("edited hunk has too many old lines")?;
+                if orig.strip_suffix('\n').unwrap_or(orig) != body {
+                    return Err(format!("edited hunk does not apply at: {}", raw));
+                }
+                old_idx += 1;
+                orig.to_string()
+            };
+            lines.push((kind, text));
+        }
+
+        if old_idx != original_old.len() {
+            return Err("edited hunk does not apply: old lines were removed".to_string());
+        }
+
+        Ok(Hunk {
+            old_start: self.old_start,
+            old_len: self.old_len,
+            new_start: self.new_start,
+            new_len: lines.iter().filter(|(k, _)| *k != LineKind::Removed).count(),
+            lines,
+        })
+    }
+}
+
+/// Applies non-overlapping hunks (sorted by position) to `old`, leaving the rest untouched
+pub fn apply_hunks(old: &str, hunks: &[&Hunk]) -> String {
+    let old_lines = split_lines(old);
+    let mut out = String::with_capacity(old.len());
+    let mut cursor = 0;
+
+    for hunk in hunks {
+        let mut pos = hunk.first_old_line() - 1;
+
+        for (kind, text) in &hunk.lines {
+            match kind {
+                LineKind::Context => pos += 1,
+                LineKind::Removed => {
+                    out.extend(old_lines[cursor..pos].iter().copied());
+                    cursor = pos + 1;
+                    pos += 1;
+                }
+                LineKind::Added => {
+                    out.extend(old_lines[cursor..pos].iter().copied());
+                    cursor = pos;
+                    out.push_str(text);
+                }
+            }
+        }
+    }
+
+    out.extend(old_lines[cursor..].iter().copied());
+    out
+}
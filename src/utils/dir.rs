@@ -1,16 +1,58 @@
 use std::fs::{read_dir, remove_dir};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use anyhow::Result;
+use ignore::WalkBuilder;
+
+use crate::utils::ignore::matcher;
 
 pub fn is_in_ink(path: &Path) -> bool {
     path.components().any(|c| c.as_os_str() == ".ink")
 }
 
+/// Lists every non-ignored file in the working tree, relative to the repository root
+pub fn list_worktree_files() -> Vec<PathBuf> {
+    list_files_under(Path::new("."))
+}
+
+/// Lists every non-ignored file below `dir`, relative to the repository root
+pub fn list_files_under(dir: &Path) -> Vec<PathBuf> {
+    let files = Arc::new(Mutex::new(Vec::new()));
+
+    WalkBuilder::new(dir)
+        .standard_filters(false)
+        .hidden(false)
+        .filter_entry(|entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !matcher().is_ignored(entry.path(), is_dir)
+        })
+        .build_parallel()
+        .run(|| {
+            let files = Arc::clone(&files);
+            Box::new(move |res| {
+                if let Ok(entry) = res {
+                    let path = entry.path();
+
+                    if path.is_file() && !is_in_ink(path) {
+                        let rel = path.strip_prefix(".").unwrap_or(path);
+                        files.lock().unwrap().push(rel.to_path_buf());
+                    }
+                }
+                ignore::WalkState::Continue
+            })
+        });
+
+    Arc::try_unwrap(files)
+        .map(|mutex| mutex.into_inner().unwrap())
+        .unwrap_or_else(|arc| (*arc.lock().unwrap()).clone())
+}
+
 pub fn remove_empty_parents_up_to(path: &Path, stop_at: &Path) -> Result<()> {
     let mut current = path.parent();
 
     while let Some(dir) = current {
-        if dir == stop_at {
+        // Relative paths run out at "" rather than reaching "."
+        if dir == stop_at || dir.as_os_str().is_empty() {
             break;
         }
 
@@ -0,0 +1,71 @@
+use std::env;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::process::Command;
+use anyhow::{anyhow, Result};
+
+/// Picks the editor from `$INK_EDITOR`, `core.editor` config, `$VISUAL` or `$EDITOR`
+pub fn editor_command() -> Result<String> {
+    if let Ok(editor) = env::var("INK_EDITOR") {
+        return Ok(editor);
+    }
+
+    if let Some(editor) = crate::utils::config::get_config("core.editor")? {
+        return Ok(editor);
+    }
+
+    Ok(env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad".into() } else { "vi".into() }))
+}
+
+/// Writes `initial` to `path`, opens it in the user's editor and returns the edited text
+pub fn edit_file(path: &Path, initial: &str) -> Result<String> {
+    write(path, initial)?;
+
+    let editor = editor_command()?;
+    let status = if cfg!(windows) {
+        Command::new("cmd").arg("/C").arg(format!("{} \"{}\"", editor, path.display())).status()?
+    } else {
+        // Run through the shell so editors configured with arguments ("code --wait") work
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg(&editor)
+            .arg(path)
+            .status()?
+    };
+
+    if !status.success() {
+        return Err(anyhow!("Editor '{}' exited with {}", editor, status));
+    }
+
+    Ok(read_to_string(path)?)
+}
+
+/// Drops `#` comment lines, trailing whitespace and surrounding blank lines
+pub fn strip_comments(text: &str) -> String {
+    let mut lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .map(|l| l.trim_end())
+        .collect();
+
+    while lines.first().is_some_and(|l| l.is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+
+    // Collapse runs of blank lines into one
+    let mut out: Vec<&str> = Vec::with_capacity(lines.len());
+    for line in lines {
+        if line.is_empty() && out.last().is_some_and(|l| l.is_empty()) {
+            continue;
+        }
+        out.push(line);
+    }
+
+    out.join("\n")
+}
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::path::PathBuf;
 use std::str::FromStr;
 use anyhow::{ anyhow, Error, Result };
@@ -7,25 +8,22 @@ pub enum AddMode {
     All,
     Update,
     Files(Vec<PathBuf>),
+    /// Interactively pick hunks to stage from the given pathspecs
+    Patch(Vec<PathBuf>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum HashAlgo {
     Sha256,
+    #[default]
     Blake3,
 }
 
-impl Default for HashAlgo {
-    fn default() -> Self {
-        HashAlgo::Blake3
-    }
-}
-
-impl ToString for HashAlgo {
-    fn to_string(&self) -> String {
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            HashAlgo::Sha256 => "sha256".to_string(),
-            HashAlgo::Blake3 => "blake3".to_string(),
+            HashAlgo::Sha256 => write!(f, "sha256"),
+            HashAlgo::Blake3 => write!(f, "blake3"),
         }
     }
 }
@@ -41,3 +39,34 @@ impl FromStr for HashAlgo {
         }
     }
 }
+
+/// How an object file is stored on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    None,
+    Zlib,
+    Zstd,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::None => write!(f, "none"),
+            Encoding::Zlib => write!(f, "zlib"),
+            Encoding::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Encoding> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Encoding::None),
+            "zlib" => Ok(Encoding::Zlib),
+            "zstd" => Ok(Encoding::Zstd),
+            _ => Err(anyhow!("Unsupported compression: {} (use zlib, zstd or none)", s)),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::utils::object::Commit;
+
+/// One row of a drawn commit graph
+pub enum GraphLine {
+    /// Graph prefix for the commit at this index of the input
+    Commit(usize, String),
+    /// Row of connecting edges between commits
+    Edge(String),
+}
+
+/// Lays out `commits` (children before parents) as an ASCII graph, one lane per
+/// line of history, in the style of `git log --graph`:
+///
+/// ```text
+/// *   merge
+/// |\
+/// | * side
+/// * | main
+/// |/
+/// * base
+/// ```
+pub fn layout(commits: &[(String, Commit)]) -> Vec<GraphLine> {
+    let mut lanes: Vec<Option<String>> = Vec::new();
+    let mut out = Vec::new();
+
+    for (i, (hash, commit)) in commits.iter().enumerate() {
+        let col = match lanes.iter().position(|l| l.as_deref() == Some(hash.as_str())) {
+            Some(col) => col,
+            None => open_lane(&mut lanes, hash.clone(), 0),
+        };
+
+        // Other children of this commit had lanes of their own; fold them in
+        let folded: Vec<usize> = (0..lanes.len())
+            .filter(|&j| j != col && lanes[j].as_deref() == Some(hash.as_str()))
+            .collect();
+        if !folded.is_empty() {
+            out.push(GraphLine::Edge(render_edges(&lanes, &folded, '/')));
+            for j in folded {
+                lanes[j] = None;
+            }
+            trim(&mut lanes);
+        }
+
+        out.push(GraphLine::Commit(i, render(&lanes, |j| if j == col { '*' } else { '|' })));
+
+        lanes[col] = commit.parents.first().cloned();
+        let mut opened = Vec::new();
+        for parent in commit.parents.iter().skip(1) {
+            if !lanes.iter().any(|l| l.as_ref() == Some(parent)) {
+                opened.push(open_lane(&mut lanes, parent.clone(), col + 1));
+            }
+        }
+        if !opened.is_empty() {
+            out.push(GraphLine::Edge(render_edges(&lanes, &opened, '\\')));
+        }
+        trim(&mut lanes);
+    }
+
+    out
+}
+
+/// Puts `hash` in the first free lane at or after `from`, returning its column
+fn open_lane(lanes: &mut Vec<Option<String>>, hash: String, from: usize) -> usize {
+    match (from..lanes.len()).find(|&j| lanes[j].is_none()) {
+        Some(j) => {
+            lanes[j] = Some(hash);
+            j
+        }
+        None => {
+            lanes.push(Some(hash));
+            lanes.len() - 1
+        }
+    }
+}
+
+/// Drops free lanes from the right edge
+fn trim(lanes: &mut Vec<Option<String>>) {
+    while lanes.last().is_some_and(|l| l.is_none()) {
+        lanes.pop();
+    }
+}
+
+/// Draws `glyph` in the gap left of each lane in `moving`, the way `|/` and `|\`
+/// join a lane to its neighbour, and `|` for the other occupied lanes
+fn render_edges(lanes: &[Option<String>], moving: &[usize], glyph: char) -> String {
+    let mut row = vec![' '; lanes.len() * 2];
+    for (j, lane) in lanes.iter().enumerate() {
+        if lane.is_none() {
+            continue;
+        }
+        if moving.contains(&j) && j > 0 {
+            row[j * 2 - 1] = glyph;
+        } else {
+            row[j * 2] = '|';
+        }
+    }
+    row.into_iter().collect::<String>().trim_end().to_string()
+}
+
+/// Draws occupied lanes with `glyph(column)` and free ones as blanks
+fn render(lanes: &[Option<String>], glyph: impl Fn(usize) -> char) -> String {
+    let mut row = String::new();
+    for (j, lane) in lanes.iter().enumerate() {
+        row.push(if lane.is_some() { glyph(j) } else { ' ' });
+        row.push(' ');
+    }
+    row
+}
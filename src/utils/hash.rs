@@ -1,72 +1,114 @@
-use std::fs::{read_to_string, write};
+use std::fs::read_to_string;
+use std::io::Write;
 use std::str::FromStr;
 use std::path::Path;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use sha2::{Digest, Sha256};
 use blake3::Hasher;
 
+use crate::utils::config::write_config_file;
 use crate::utils::enums::HashAlgo;
 
-pub fn hash_object(data: &[u8]) -> Result<String> {
-    let algo = load_hash_algo()?;
-    
-    let hash = match algo {
-        HashAlgo::Sha256 => {
-            let mut hasher = Sha256::new();
-            hasher.update(data);
-            format!("{:x}", hasher.finalize())
+/// Records which algorithm named the objects in the store, independently of
+/// the config, so a hand-edited `hash=` cannot silently mix algorithms
+const STORE_ALGO_FILE: &str = ".ink/objects/hash";
+
+/// Hashes `data` with a specific algorithm, regardless of the repository's setting
+pub fn hash_with(algo: HashAlgo, data: &[u8]) -> String {
+    let mut hasher = StreamHasher::new(algo);
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// Incremental hashing, for content that is read in pieces rather than held in memory
+pub enum StreamHasher {
+    Sha256(Sha256),
+    Blake3(Box<Hasher>),
+}
+
+impl StreamHasher {
+    pub fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha256 => StreamHasher::Sha256(Sha256::new()),
+            HashAlgo::Blake3 => StreamHasher::Blake3(Box::new(Hasher::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            StreamHasher::Sha256(hasher) => hasher.update(data),
+            StreamHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
         }
-        HashAlgo::Blake3 => {
-            let mut hasher = Hasher::new();
-            hasher.update(data);
-            hasher.finalize().to_hex().to_string()
+    }
+
+    pub fn finish(self) -> String {
+        match self {
+            StreamHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            StreamHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
         }
-    };
-    
-    Ok(hash)
+    }
 }
 
+impl Write for StreamHasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The configured algorithm, checked against the one the object store records
 pub fn load_hash_algo() -> Result<HashAlgo> {
-    let path = Path::new(".ink/config");
-    let contents = read_to_string(path)?;
+    let configured = load_configured_algo()?;
 
-    for line in contents.lines() {
-        if let Some((key, value)) = line.split_once('=') {
-            if key.trim() == "hash" {
-                return Ok(HashAlgo::from_str(value.trim())?);
-            }
-        }
+    if let Some(stored) = load_store_algo()?
+        && stored != configured
+    {
+        return Err(anyhow!(
+            "The object store is hashed with {} but .ink/config says hash={}.\nSet hash={} again, or convert the repository with 'ink migrate-hash {}'.",
+            stored, configured, stored, configured
+        ));
     }
-    Ok(HashAlgo::default())
+    Ok(configured)
 }
 
-pub fn save_hash_algo(algo: HashAlgo) -> Result<()> {
+/// The algorithm named by `hash=` in the config, without checking the object store
+pub fn load_configured_algo() -> Result<HashAlgo> {
     let path = Path::new(".ink/config");
+    let contents = read_to_string(path)?;
 
-    let mut lines = if path.exists() {
-        read_to_string(path)?
-            .lines()
-            .map(|l| l.to_string())
-            .collect::<Vec<_>>()
-    } else {
-        Vec::new()
-    };
-
-    let mut found = false;
-    for line in lines.iter_mut() {
-        if line.trim_start().starts_with("hash=") {
-            *line = format!("hash={}", algo.to_string());
-            found = true;
-            break;
+    for line in contents.lines() {
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim() == "hash"
+        {
+            return HashAlgo::from_str(value.trim());
         }
     }
+    Ok(HashAlgo::default())
+}
 
-    if !found {
-        lines.push(format!("hash={}", algo.to_string()));
+/// The algorithm recorded by the object store; repositories created before it
+/// was recorded have none
+pub fn load_store_algo() -> Result<Option<HashAlgo>> {
+    match read_to_string(STORE_ALGO_FILE) {
+        Ok(name) => Ok(Some(HashAlgo::from_str(name.trim())?)),
+        Err(_) => Ok(None),
     }
+}
 
-    let output = lines.join("\n") + "\n";
-    write(path, output)?; // .as_bytes()
+/// Sets the algorithm in both the config and the object store
+pub fn save_hash_algo(algo: HashAlgo) -> Result<()> {
+    save_store_algo(Path::new(".ink/objects"), algo)?;
+    write_config_file(Path::new(".ink/config"), "hash", &algo.to_string())
+}
 
+/// Records `algo` for the object store rooted at `objects`
+pub fn save_store_algo(objects: &Path, algo: HashAlgo) -> Result<()> {
+    std::fs::write(objects.join("hash"), format!("{}\n", algo))?;
     Ok(())
 }
@@ -0,0 +1,246 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::PathBuf;
+use anyhow::Result;
+
+use crate::commands::checkout::get_tree_entries;
+use crate::utils::object::{object_exists, read_commit_object, read_tree_object, Commit};
+use crate::utils::pathspec::Pathspec;
+
+/// Walks every commit reachable from `starts`, newest committer date first
+pub fn walk_commits(starts: &[String]) -> Result<Vec<(String, Commit)>> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut queue: BinaryHeap<(i64, String)> = BinaryHeap::new();
+    let mut out = Vec::new();
+
+    for start in starts {
+        if seen.insert(start.clone()) {
+            let commit = read_commit_object(start)?;
+            queue.push((commit.committer.timestamp, start.clone()));
+        }
+    }
+
+    while let Some((_, hash)) = queue.pop() {
+        let commit = read_commit_object(&hash)?;
+
+        for parent in &commit.parents {
+            if seen.insert(parent.clone()) {
+                let parent_commit = read_commit_object(parent)?;
+                queue.push((parent_commit.committer.timestamp, parent.clone()));
+            }
+        }
+
+        out.push((hash, commit));
+    }
+
+    Ok(out)
+}
+
+/// Flattened `path -> blob hash` map of a commit's tree
+pub fn commit_files(commit: &Commit) -> Result<HashMap<PathBuf, String>> {
+    get_tree_entries(&commit.tree)
+}
+
+/// Whether `commit` changed any path selected by `spec` relative to its first parent
+pub fn commit_touches(commit: &Commit, spec: &Pathspec) -> Result<bool> {
+    let files = commit_files(commit)?;
+    let parent_files = match commit.parents.first() {
+        Some(parent) => commit_files(&read_commit_object(parent)?)?,
+        None => HashMap::new(),
+    };
+
+    let changed = files
+        .iter()
+        .filter(|(path, hash)| parent_files.get(*path) != Some(*hash))
+        .map(|(path, _)| path)
+        .chain(parent_files.keys().filter(|path| !files.contains_key(*path)));
+
+    Ok(changed.into_iter().any(|path| spec.matches(path)))
+}
+
+/// Whether `ancestor` is reachable from `descendant` (a commit is its own ancestor)
+pub fn is_ancestor(ancestor: &str, descendant: &str) -> Result<bool> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut stack = vec![descendant.to_string()];
+
+    while let Some(hash) = stack.pop() {
+        if hash == ancestor {
+            return Ok(true);
+        }
+        if !seen.insert(hash.clone()) || !object_exists(&hash) {
+            continue;
+        }
+        stack.extend(read_commit_object(&hash)?.parents);
+    }
+
+    Ok(false)
+}
+
+/// Every commit reachable from `tips`, skipping tips missing from the store
+pub fn reachable_commits(tips: &[String]) -> Result<HashSet<String>> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut stack: Vec<String> = tips.iter().filter(|t| object_exists(t)).cloned().collect();
+
+    while let Some(hash) = stack.pop() {
+        if seen.insert(hash.clone()) {
+            stack.extend(read_commit_object(&hash)?.parents.into_iter().filter(|p| !seen.contains(p)));
+        }
+    }
+
+    Ok(seen)
+}
+
+/// Adds a tree and everything below it to `out`, skipping subtrees already present
+pub fn collect_tree_objects(tree: &str, out: &mut HashSet<String>) -> Result<()> {
+    if !out.insert(tree.to_string()) {
+        return Ok(());
+    }
+
+    for (_, kind, hash) in read_tree_object(tree)? {
+        if kind == "tree" {
+            collect_tree_objects(&hash, out)?;
+        } else {
+            out.insert(hash);
+        }
+    }
+
+    Ok(())
+}
+
+/// Objects (commits, trees, blobs) needed to go from `haves` to `wants`.
+///
+/// Commits reachable from `haves` are excluded, as are the trees and blobs of
+/// the boundary commits, so unchanged content is not transferred again.
+pub fn missing_objects(wants: &[String], haves: &[String]) -> Result<Vec<String>> {
+    let have_commits = reachable_commits(haves)?;
+
+    let mut commits: Vec<String> = Vec::new();
+    let mut boundary: HashSet<String> = HashSet::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut stack: Vec<String> = wants.iter().filter(|w| !have_commits.contains(*w)).cloned().collect();
+
+    while let Some(hash) = stack.pop() {
+        if !seen.insert(hash.clone()) {
+            continue;
+        }
+        let commit = read_commit_object(&hash)?;
+        for parent in commit.parents {
+            if have_commits.contains(&parent) {
+                boundary.insert(parent);
+            } else {
+                stack.push(parent);
+            }
+        }
+        commits.push(hash);
+    }
+
+    let mut excluded: HashSet<String> = HashSet::new();
+    for hash in &boundary {
+        collect_tree_objects(&read_commit_object(hash)?.tree, &mut excluded)?;
+    }
+
+    let mut objects: HashSet<String> = excluded.clone();
+    for hash in &commits {
+        collect_tree_objects(&read_commit_object(hash)?.tree, &mut objects)?;
+    }
+
+    let mut out: Vec<String> = commits;
+    out.extend(objects.into_iter().filter(|o| !excluded.contains(o)));
+    Ok(out)
+}
+
+/// Number of commits reachable only from `local`, and only from `upstream`
+pub fn ahead_behind(local: &str, upstream: &str) -> Result<(usize, usize)> {
+    let ours = reachable_commits(&[local.to_string()])?;
+    let theirs = reachable_commits(&[upstream.to_string()])?;
+    Ok((ours.difference(&theirs).count(), theirs.difference(&ours).count()))
+}
+
+/// How `ink log` orders the commits it shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Newest committer date first, as walked
+    Walk,
+    /// No parent before all of its children; otherwise newest first
+    Date,
+    /// No parent before all of its children, keeping each line of history together
+    Topo,
+}
+
+/// Reorders walked commits so every commit comes before its parents
+pub fn sort_commits(commits: Vec<(String, Commit)>, order: Order) -> Vec<(String, Commit)> {
+    if order == Order::Walk {
+        return commits;
+    }
+
+    let position: HashMap<String, usize> = commits.iter().enumerate().map(|(i, (h, _))| (h.clone(), i)).collect();
+    let mut children = vec![0usize; commits.len()];
+    for (_, commit) in &commits {
+        for parent in &commit.parents {
+            if let Some(&p) = position.get(parent) {
+                children[p] += 1;
+            }
+        }
+    }
+
+    // Walk order is newest first, so lower positions are newer
+    let tips: Vec<usize> = (0..commits.len()).filter(|&i| children[i] == 0).collect();
+    let mut ready: BinaryHeap<Reverse<usize>> = BinaryHeap::new();
+    let mut stack: Vec<usize> = Vec::new();
+    match order {
+        Order::Date => ready.extend(tips.into_iter().map(Reverse)),
+        _ => stack.extend(tips.into_iter().rev()),
+    }
+
+    let mut sorted: Vec<usize> = Vec::with_capacity(commits.len());
+    loop {
+        let next = match order {
+            Order::Date => ready.pop().map(|Reverse(i)| i),
+            _ => stack.pop(),
+        };
+        let Some(i) = next else { break };
+        sorted.push(i);
+
+        for parent in &commits[i].1.parents {
+            let Some(&p) = position.get(parent) else { continue };
+            children[p] -= 1;
+            if children[p] == 0 {
+                match order {
+                    Order::Date => ready.push(Reverse(p)),
+                    _ => stack.push(p),
+                }
+            }
+        }
+    }
+
+    let mut slots: Vec<Option<(String, Commit)>> = commits.into_iter().map(Some).collect();
+    sorted.into_iter().filter_map(|i| slots[i].take()).collect()
+}
+
+/// Rewrites parents to the nearest ancestors that are still in `commits`, for
+/// drawing a graph of a history filtered by path
+pub fn simplify_parents(commits: &mut [(String, Commit)], all: &[(String, Commit)]) {
+    let kept: HashSet<String> = commits.iter().map(|(h, _)| h.clone()).collect();
+    let parents: HashMap<&String, &Vec<String>> = all.iter().map(|(h, c)| (h, &c.parents)).collect();
+
+    for (_, commit) in commits.iter_mut() {
+        let mut rewritten: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = commit.parents.iter().rev().cloned().collect();
+
+        while let Some(hash) = stack.pop() {
+            if !seen.insert(hash.clone()) {
+                continue;
+            }
+            if kept.contains(&hash) {
+                if !rewritten.contains(&hash) {
+                    rewritten.push(hash);
+                }
+            } else if let Some(grand) = parents.get(&hash) {
+                stack.extend(grand.iter().rev().cloned());
+            }
+        }
+
+        commit.parents = rewritten;
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+use std::process::Command;
+use anyhow::{anyhow, Result};
+
+use crate::utils::config::get_config;
+
+/// Directory hooks are loaded from (`core.hooksPath`, defaulting to `.ink/hooks`)
+pub fn hooks_dir() -> Result<PathBuf> {
+    Ok(get_config("core.hooksPath")?
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(".ink/hooks")))
+}
+
+/// Locates an executable hook by name, if one is installed
+pub fn find_hook(name: &str) -> Result<Option<PathBuf>> {
+    let path = hooks_dir()?.join(name);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if path.metadata()?.permissions().mode() & 0o111 == 0 {
+            eprintln!("hint: the '{}' hook was ignored because it is not executable", name);
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(path))
+}
+
+/// Runs a hook if installed. Returns `Ok(None)` when there is no hook,
+/// otherwise whether it exited successfully.
+pub fn run_hook(name: &str, args: &[&str], env: &[(&str, &str)]) -> Result<Option<bool>> {
+    let Some(path) = find_hook(name)? else {
+        return Ok(None);
+    };
+
+    let mut cmd = if cfg!(windows) {
+        // Hooks are usually shell scripts; let sh interpret them when available
+        let mut c = Command::new("sh");
+        c.arg(&path);
+        c
+    } else {
+        Command::new(&path)
+    };
+
+    let status = cmd
+        .args(args)
+        .envs(env.iter().copied())
+        .env("INK_DIR", ".ink")
+        .env("INK_HOOK", name)
+        .status()
+        .map_err(|e| anyhow!("Failed to run '{}' hook: {}", name, e))?;
+
+    Ok(Some(status.success()))
+}
+
+/// Runs a hook that can veto the operation, failing if it exits non-zero
+pub fn run_pre_hook(name: &str, args: &[&str], env: &[(&str, &str)]) -> Result<()> {
+    match run_hook(name, args, env)? {
+        Some(false) => Err(anyhow!(
+            "The '{}' hook rejected the operation (use --no-verify to bypass).",
+            name
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Runs a notification hook; its exit status cannot change the outcome
+pub fn run_post_hook(name: &str, args: &[&str], env: &[(&str, &str)]) -> Result<()> {
+    if let Some(false) = run_hook(name, args, env)? {
+        eprintln!("warning: the '{}' hook exited with an error", name);
+    }
+    Ok(())
+}
@@ -1,21 +1,193 @@
-use std::fs;
-use std::path::{Path, PathBuf};
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use dashmap::DashMap;
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 
-pub fn is_ignored(path: &Path) -> bool {
-    let ignore_path = PathBuf::from(".inkignore");
-    if !ignore_path.exists() {
-        return false;
+use crate::utils::config::get_config;
+
+/// A compiled ignore file plus the line numbers of its patterns
+struct IgnoreFile {
+    matcher: Gitignore,
+    source: PathBuf,
+    lines: Vec<(usize, String)>,
+}
+
+/// Describes the pattern that decided whether a path is ignored
+#[derive(Debug, Clone)]
+pub struct IgnoreMatch {
+    pub source: PathBuf,
+    pub line: usize,
+    pub pattern: String,
+    /// The pattern was a `!negation`, i.e. the path is explicitly not ignored
+    pub negated: bool,
+}
+
+/// Gitignore-compatible matcher over every `.inkignore` in the tree,
+/// `.ink/info/exclude` and the global excludes file.
+///
+/// Precedence (highest first): the `.inkignore` closest to the path, its
+/// parents up to the root, `.ink/info/exclude`, then the global file.
+pub struct IgnoreMatcher {
+    /// Per-directory `.inkignore` files, loaded lazily and keyed by directory
+    dirs: DashMap<PathBuf, Option<Arc<IgnoreFile>>>,
+    exclude: Option<IgnoreFile>,
+    global: Option<IgnoreFile>,
+}
+
+impl IgnoreMatcher {
+    pub fn load() -> Self {
+        IgnoreMatcher {
+            dirs: DashMap::new(),
+            exclude: IgnoreFile::load(Path::new(""), Path::new(".ink/info/exclude")),
+            global: global_excludes_path().and_then(|p| IgnoreFile::load(Path::new(""), &p)),
+        }
     }
 
-    if let Ok(content) = fs::read_to_string(ignore_path) {
-        for pattern in content.lines().filter(|l| !l.trim().is_empty()) {
-            if let Ok(glob) = glob::Pattern::new(pattern) {
-                if glob.matches_path(path) {
-                    return true;
+    /// Finds the pattern deciding `path`, honouring excluded parent directories
+    pub fn check(&self, path: &Path, is_dir: bool) -> Option<IgnoreMatch> {
+        let path = normalize(path);
+
+        // A file inside an excluded directory cannot be re-included
+        let mut ancestor = PathBuf::new();
+        let components: Vec<_> = path.components().collect();
+        for comp in components.iter().take(components.len().saturating_sub(1)) {
+            ancestor.push(comp);
+            if let Some(m) = self.check_one(&ancestor, true)
+                && !m.negated
+            {
+                return Some(m);
+            }
+        }
+
+        self.check_one(&path, is_dir)
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = normalize(path);
+        if path.components().next().is_some_and(|c| c.as_os_str() == ".ink") {
+            return true;
+        }
+        self.check(&path, is_dir).is_some_and(|m| !m.negated)
+    }
+
+    /// Matches a single path against the ignore files without looking at its parents
+    fn check_one(&self, path: &Path, is_dir: bool) -> Option<IgnoreMatch> {
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if let Some(file) = self.dir_file(d) {
+                let rel = path.strip_prefix(d).unwrap_or(path);
+                if let Some(m) = file.matched(rel, is_dir) {
+                    return Some(m);
                 }
             }
+            dir = d.parent();
+        }
+
+        [&self.exclude, &self.global]
+            .into_iter()
+            .flatten()
+            .find_map(|file| file.matched(path, is_dir))
+    }
+
+    fn dir_file(&self, dir: &Path) -> Option<Arc<IgnoreFile>> {
+        if let Some(entry) = self.dirs.get(dir) {
+            return entry.clone();
         }
+
+        let file = IgnoreFile::load(dir, &dir.join(".inkignore")).map(Arc::new);
+        self.dirs.insert(dir.to_path_buf(), file.clone());
+        file
     }
+}
+
+impl IgnoreFile {
+    fn load(root: &Path, source: &Path) -> Option<Self> {
+        let content = read_to_string(source).ok()?;
+        let mut builder = GitignoreBuilder::new(root);
+        let mut lines = Vec::new();
 
-    false
+        for (i, line) in content.lines().enumerate() {
+            match builder.add_line(Some(source.to_path_buf()), line) {
+                Ok(_) => lines.push((i + 1, line.trim_end().to_string())),
+                Err(e) => eprintln!("warning: {}:{}: {}", source.display(), i + 1, e),
+            }
+        }
+
+        match builder.build() {
+            Ok(matcher) => Some(IgnoreFile { matcher, source: source.to_path_buf(), lines }),
+            Err(e) => {
+                eprintln!("warning: ignoring {}: {}", source.display(), e);
+                None
+            }
+        }
+    }
+
+    fn matched(&self, path: &Path, is_dir: bool) -> Option<IgnoreMatch> {
+        let (glob, negated) = match self.matcher.matched(path, is_dir) {
+            Match::None => return None,
+            Match::Ignore(glob) => (glob, false),
+            Match::Whitelist(glob) => (glob, true),
+        };
+
+        // Later patterns win, so report the last line with this text
+        let line = self
+            .lines
+            .iter()
+            .rev()
+            .find(|(_, l)| l == glob.original())
+            .map(|(n, _)| *n)
+            .unwrap_or(0);
+
+        Some(IgnoreMatch {
+            source: self.source.clone(),
+            line,
+            pattern: glob.original().to_string(),
+            negated,
+        })
+    }
+}
+
+/// `core.excludesFile`, falling back to `$XDG_CONFIG_HOME/ink/ignore`
+fn global_excludes_path() -> Option<PathBuf> {
+    if let Ok(Some(path)) = get_config("core.excludesFile") {
+        if let Some(rest) = path.strip_prefix("~/") {
+            return env::var_os("HOME").map(|home| PathBuf::from(home).join(rest));
+        }
+        return Some(PathBuf::from(path));
+    }
+
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("ink").join("ignore"))
+}
+
+/// Strips `./` prefixes and makes absolute paths relative to the repository root
+fn normalize(path: &Path) -> PathBuf {
+    let path = if path.is_absolute() {
+        env::current_dir()
+            .ok()
+            .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+            .unwrap_or_else(|| path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    };
+
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+static MATCHER: OnceLock<IgnoreMatcher> = OnceLock::new();
+
+/// The process-wide matcher, compiled on first use
+pub fn matcher() -> &'static IgnoreMatcher {
+    MATCHER.get_or_init(IgnoreMatcher::load)
+}
+
+pub fn is_ignored(path: &Path) -> bool {
+    matcher().is_ignored(path, path.is_dir())
 }
@@ -1,14 +1,55 @@
 use std::collections::HashMap;
-use std::fs::{create_dir_all, read_to_string, write, File, read};
-use std::io::{BufWriter, Write};
+use std::fs::{create_dir_all, read_to_string, remove_file, rename, write, File, OpenOptions, read};
+use std::io::{BufWriter, ErrorKind, Write};
 use std::path::{Path, PathBuf};
-use anyhow::Result;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
 use bincode::config::standard;
+use rayon::prelude::*;
 use bincode::serde::{decode_from_slice, encode_to_vec};
 use serde::{Serialize, Deserialize};
 
 use crate::commands::branch::read_current_branch;
 use crate::utils::object::create_blob;
+use crate::utils::repo::Repo;
+
+const LOCK_PATH: &str = ".ink/index.lock";
+
+/// How long to wait for another process to finish writing the index
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The right to rewrite `.ink/index`, held as `.ink/index.lock` until dropped.
+/// Writers that read, change and write back the index hold it throughout, so
+/// concurrent writers such as `ink watch --stage` cannot lose each other's entries.
+pub struct IndexLock(());
+
+impl IndexLock {
+    pub fn acquire() -> Result<Self> {
+        let start = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(LOCK_PATH) {
+                Ok(_) => return Ok(IndexLock(())),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && start.elapsed() < LOCK_TIMEOUT => {
+                    sleep(Duration::from_millis(20));
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    return Err(anyhow!(
+                        "Cannot lock the index: '{}' exists. If no other ink process is running, remove it",
+                        LOCK_PATH
+                    ));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        remove_file(LOCK_PATH).ok();
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct IndexEntry {
@@ -24,7 +65,7 @@ pub struct Index {
 impl Index {
     pub fn save_for_branch(&self, branch: &str) -> Result<()> {
         let dir = Path::new(".ink/refs/INDEXES");
-        create_dir_all(&dir)?;
+        create_dir_all(dir)?;
         let path = dir.join(branch);
 
         let encoded = encode_to_vec(self, standard())?;
@@ -64,13 +105,23 @@ impl Index {
     }
 
     pub fn save(&self) -> Result<()> {
-        let file = File::create(".ink/index")?;
-        let mut writer = BufWriter::new(file);
+        let lock = IndexLock::acquire()?;
+        self.save_locked(&lock)
+    }
+
+    /// Writes the index under a lock the caller already holds
+    pub fn save_locked(&self, _lock: &IndexLock) -> Result<()> {
+        // Written aside and renamed, so readers never see a partly written index
+        let tmp = ".ink/index.new";
+        let mut writer = BufWriter::new(File::create(tmp)?);
 
         for entry in self.entries.values() {
             writeln!(writer, "{} {}", entry.hash, entry.path.display())?;
         }
+        writer.flush()?;
+        drop(writer);
 
+        rename(tmp, ".ink/index")?;
         Ok(())
     }
 
@@ -92,20 +143,23 @@ impl Index {
     }
 }
 
-pub fn add_files_to_index(files: &[PathBuf]) -> Result<()> {
+pub fn add_files_to_index(repo: &Repo, files: &[PathBuf]) -> Result<()> {
+    // Blobs are independent, so write them in parallel and only update the index in order
+    let entries: Vec<IndexEntry> = files
+        .par_iter()
+        .map(|path| {
+            let hash = create_blob(repo, path.clone())?;
+            let rel_path = path.strip_prefix(".").unwrap_or(path);
+            Ok(IndexEntry { path: rel_path.to_path_buf(), hash })
+        })
+        .collect::<Result<_>>()?;
+
+    let lock = IndexLock::acquire()?;
     let mut index = Index::load()?;
-
-    for path in files {
-        let hash = create_blob(path.clone())?;
-        let rel_path = path.strip_prefix(".").unwrap_or(path);
-
-        index.add(IndexEntry {
-            path: rel_path.to_path_buf(),
-            hash,
-        });
+    for entry in entries {
+        index.add(entry);
     }
-
-    index.save()
+    index.save_locked(&lock)
 }
 
 pub fn save_index_for_current_branch() -> Result<()> {
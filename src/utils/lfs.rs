@@ -0,0 +1,364 @@
+use std::collections::HashSet;
+use std::fs::{create_dir_all, metadata, read, read_to_string, remove_file, rename, write, File};
+use std::io::{copy, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::{anyhow, Result};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use rayon::prelude::*;
+
+use crate::commands::checkout::get_tree_entries;
+use crate::utils::enums::HashAlgo;
+use crate::utils::hash::{hash_with, StreamHasher};
+use crate::utils::object::{is_hex, open_object, read_commit_object, ZERO_HASH};
+use crate::utils::repo::Repo;
+use crate::utils::transport::Transport;
+
+/// Gitignore-style patterns, one per line, naming the paths kept in the large file store
+pub const PATTERNS_FILE: &str = ".inklfs";
+
+/// Manifests live in `objects/`, the pieces they list in `chunks/`
+const STORE: &str = ".ink/lfs";
+
+/// Pointer blobs are a few lines of text; anything larger is ordinary content
+pub const POINTER_MAX: u64 = 512;
+
+const VERSION: &str = "version ink-lfs/1";
+
+/// Large files are always named by BLAKE3, whatever the object store uses, so a
+/// pointer stays the same across `ink migrate-hash`
+const OID_ALGO: HashAlgo = HashAlgo::Blake3;
+
+/// Size of the pieces contents are stored and transferred in
+const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// What a tree records in place of a large file:
+///
+/// ```text
+/// version ink-lfs/1
+/// oid blake3:<hash of the content>
+/// size <bytes>
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pointer {
+    pub algo: HashAlgo,
+    pub oid: String,
+    pub size: u64,
+}
+
+impl Pointer {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() as u64 > POINTER_MAX {
+            return None;
+        }
+        let mut lines = std::str::from_utf8(data).ok()?.lines();
+        if lines.next()? != VERSION {
+            return None;
+        }
+        let (algo, oid) = lines.next()?.strip_prefix("oid ")?.split_once(':')?;
+        let size = lines.next()?.strip_prefix("size ")?.parse().ok()?;
+        if lines.next().is_some() || oid.len() != ZERO_HASH.len() || !is_hex(oid) {
+            return None;
+        }
+
+        Some(Pointer { algo: HashAlgo::from_str(algo).ok()?, oid: oid.to_string(), size })
+    }
+
+    /// `<algo>:<oid>`, the name used when asking a remote for the content
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.algo, self.oid)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("{}\noid {}\nsize {}\n", VERSION, self.id(), self.size)
+    }
+
+    /// Whether the content is in the local store
+    pub fn is_present(&self) -> bool {
+        store_path("objects", &self.oid).is_file()
+    }
+}
+
+/// Whether `path` matches a pattern in `.inklfs`
+pub fn is_tracked(path: &Path) -> bool {
+    static PATTERNS: OnceLock<Option<Gitignore>> = OnceLock::new();
+    let Some(matcher) = PATTERNS.get_or_init(load_patterns) else {
+        return false;
+    };
+
+    let path: PathBuf = path.components().filter(|c| !matches!(c, Component::CurDir)).collect();
+    matches!(matcher.matched_path_or_any_parents(&path, false), Match::Ignore(_))
+}
+
+fn load_patterns() -> Option<Gitignore> {
+    let content = read_to_string(PATTERNS_FILE).ok()?;
+    let mut builder = GitignoreBuilder::new("");
+    for (i, line) in content.lines().enumerate() {
+        if let Err(e) = builder.add_line(None, line) {
+            eprintln!("warning: {}:{}: {}", PATTERNS_FILE, i + 1, e);
+        }
+    }
+
+    match builder.build() {
+        Ok(matcher) => Some(matcher),
+        Err(e) => {
+            eprintln!("warning: ignoring {}: {}", PATTERNS_FILE, e);
+            None
+        }
+    }
+}
+
+/// Moves a file's content into the store and returns the pointer standing in for it.
+/// A file that is itself a pointer (its content was never fetched) is left as it is.
+pub fn clean(path: &Path) -> Result<Pointer> {
+    if let Some(pointer) = read_pointer_file(path)? {
+        return Ok(pointer);
+    }
+    store_content(File::open(path)?)
+}
+
+/// Like `clean`, for content that exists only in memory, e.g. partially staged hunks
+pub fn clean_bytes(content: &[u8]) -> Result<Pointer> {
+    if let Some(pointer) = Pointer::parse(content) {
+        return Ok(pointer);
+    }
+    store_content(content)
+}
+
+fn store_content(mut data: impl Read) -> Result<Pointer> {
+    let algo = OID_ALGO;
+    let mut hasher = StreamHasher::new(algo);
+    let mut manifest = String::new();
+    let mut size = 0;
+
+    let mut chunk = Vec::new();
+    loop {
+        chunk.clear();
+        (&mut data).take(CHUNK_SIZE).read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            break;
+        }
+        hasher.update(&chunk);
+        manifest += &write_chunk(algo, &chunk)?;
+        manifest.push('\n');
+        size += chunk.len() as u64;
+    }
+
+    let pointer = Pointer { algo, oid: hasher.finish(), size };
+    write_once(&store_path("objects", &pointer.oid), manifest.as_bytes())?;
+    Ok(pointer)
+}
+
+/// The pointer a file would be cleaned to, without storing anything
+pub fn pointer_of(path: &Path) -> Result<Pointer> {
+    if let Some(pointer) = read_pointer_file(path)? {
+        return Ok(pointer);
+    }
+
+    let algo = OID_ALGO;
+    let mut hasher = StreamHasher::new(algo);
+    let size = copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(Pointer { algo, oid: hasher.finish(), size })
+}
+
+/// The pointer a working tree file holds, if it holds one
+pub fn read_pointer_file(path: &Path) -> Result<Option<Pointer>> {
+    if metadata(path)?.len() > POINTER_MAX {
+        return Ok(None);
+    }
+    Ok(Pointer::parse(&read(path)?))
+}
+
+/// The pointer stored in a blob, if it is one
+pub fn read_pointer_blob(hash: &str) -> Result<Option<Pointer>> {
+    let (kind, size, mut reader) = open_object(hash)?;
+    if kind != "blob" || size > POINTER_MAX {
+        return Ok(None);
+    }
+
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body)?;
+    Ok(Pointer::parse(&body))
+}
+
+/// Writes the content a pointer stands for to `path`. When the content has not
+/// been fetched the pointer itself is written instead and `false` is returned.
+pub fn smudge(pointer: &Pointer, path: &Path) -> Result<bool> {
+    if !pointer.is_present() {
+        write(path, pointer.to_text())?;
+        return Ok(false);
+    }
+    copy_content(pointer, &mut File::create(path)?)?;
+    Ok(true)
+}
+
+/// The content a pointer stands for, which must be in the local store
+pub fn read_content(pointer: &Pointer) -> Result<Vec<u8>> {
+    let mut content = Vec::new();
+    copy_content(pointer, &mut content)?;
+    Ok(content)
+}
+
+fn copy_content(pointer: &Pointer, out: &mut impl Write) -> Result<()> {
+    let manifest = read_to_string(store_path("objects", &pointer.oid))?;
+    let mut written = 0;
+    for chunk in manifest.lines() {
+        let data = read(store_path("chunks", chunk))
+            .map_err(|_| anyhow!("Large file {} is missing chunk {}; run 'ink lfs fetch'", pointer.oid, chunk))?;
+        out.write_all(&data)?;
+        written += data.len() as u64;
+    }
+
+    if written != pointer.size {
+        return Err(anyhow!("Large file {} is {} bytes, expected {}", pointer.oid, written, pointer.size));
+    }
+    Ok(())
+}
+
+/// Chunk hashes of a stored file, in order
+pub fn manifest(oid: &str) -> Result<Vec<String>> {
+    Ok(read_to_string(store_path("objects", oid))?.lines().map(String::from).collect())
+}
+
+/// Names of every manifest (`objects`) or chunk (`chunks`) in the local store
+pub fn list_stored(kind: &str) -> Result<Vec<String>> {
+    let dir = Path::new(STORE).join(kind);
+    let mut names = Vec::new();
+    if !dir.is_dir() {
+        return Ok(names);
+    }
+
+    for entry in walkdir::WalkDir::new(&dir).min_depth(2).max_depth(2) {
+        let entry = entry?;
+        let prefix = entry.path().parent().and_then(Path::file_name).unwrap_or_default().to_string_lossy();
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.file_type().is_file() && is_hex(&name) {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Location of a manifest (`objects`) or chunk (`chunks`) in the store
+pub fn store_path(kind: &str, hash: &str) -> PathBuf {
+    Path::new(STORE).join(kind).join(&hash[..2]).join(&hash[2..])
+}
+
+/// Splits a large file id (`<algo>:<oid>`) into its parts
+pub fn parse_id(id: &str) -> Result<(HashAlgo, &str)> {
+    let malformed = || anyhow!("Malformed large file id '{}'", id);
+    let (algo, oid) = id.split_once(':').ok_or_else(malformed)?;
+    if oid.len() != ZERO_HASH.len() || !is_hex(oid) {
+        return Err(malformed());
+    }
+    Ok((HashAlgo::from_str(algo).map_err(|_| malformed())?, oid))
+}
+
+/// Ids of the large files behind the pointer blobs among `hashes`
+pub fn pointer_ids(hashes: &[String]) -> Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for pointer in pointers_in(hashes)? {
+        if !pointer.is_present() {
+            eprintln!("warning: large file {} was never fetched, so it is not sent", pointer.oid);
+        }
+        ids.push(pointer.id());
+    }
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// Stores `len` bytes of received content a chunk at a time, checking it
+/// against its id. Returns `false` if the store already had it.
+pub fn store_from(id: &str, len: u64, mut data: impl Read) -> Result<bool> {
+    let (algo, oid) = parse_id(id)?;
+    if store_path("objects", oid).is_file() {
+        copy(&mut data, &mut std::io::sink())?;
+        return Ok(false);
+    }
+
+    let mut hasher = StreamHasher::new(algo);
+    let mut manifest = String::new();
+    let mut size = 0;
+
+    let mut chunk = Vec::new();
+    while size < len {
+        chunk.clear();
+        (&mut data).take(CHUNK_SIZE.min(len - size)).read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            return Err(anyhow!("Large file {} ended after {} of {} bytes", oid, size, len));
+        }
+        hasher.update(&chunk);
+        manifest += &write_chunk(algo, &chunk)?;
+        manifest.push('\n');
+        size += chunk.len() as u64;
+    }
+
+    if hasher.finish() != oid {
+        return Err(anyhow!("Large file {} is corrupt (hash mismatch)", oid));
+    }
+    write_once(&store_path("objects", oid), manifest.as_bytes())?;
+    Ok(true)
+}
+
+/// Downloads the contents of `pointers` that are not stored yet, returning how many arrived
+pub fn fetch_missing(repo: &Repo, transport: &mut dyn Transport, pointers: &[Pointer]) -> Result<usize> {
+    let mut ids: Vec<String> = pointers.iter().filter(|p| !p.is_present()).map(Pointer::id).collect();
+    ids.sort();
+    ids.dedup();
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    Ok(transport.fetch_lfs(repo, &ids)?.lfs)
+}
+
+/// The pointers among a set of blob hashes
+pub fn pointers_in<'a>(hashes: impl IntoIterator<Item = &'a String>) -> Result<HashSet<Pointer>> {
+    let mut pointers = HashSet::new();
+    for hash in hashes {
+        if let Some(pointer) = read_pointer_blob(hash)? {
+            pointers.insert(pointer);
+        }
+    }
+    Ok(pointers)
+}
+
+/// The large files in a commit's tree, sorted by path
+pub fn files_in(commit: &str) -> Result<Vec<(PathBuf, Pointer)>> {
+    let mut files: Vec<(PathBuf, Pointer)> = get_tree_entries(&read_commit_object(commit)?.tree)?
+        .into_par_iter()
+        .filter_map(|(path, hash)| read_pointer_blob(&hash).transpose().map(|p| p.map(|p| (path, p))))
+        .collect::<Result<_>>()?;
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+fn write_chunk(algo: HashAlgo, data: &[u8]) -> Result<String> {
+    let hash = hash_with(algo, data);
+    write_once(&store_path("chunks", &hash), data)?;
+    Ok(hash)
+}
+
+/// Writes a content-addressed file unless it already exists, via a temporary
+/// file so a reader never sees it half written
+fn write_once(path: &Path, data: &[u8]) -> Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    if path.exists() {
+        return Ok(());
+    }
+
+    let dir = path.parent().unwrap();
+    create_dir_all(dir)?;
+    let tmp = dir.join(format!("tmp-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    write(&tmp, data)?;
+    if let Err(e) = rename(&tmp, path) {
+        remove_file(&tmp).ok();
+        return Err(e.into());
+    }
+    Ok(())
+}
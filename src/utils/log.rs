@@ -1,46 +1,22 @@
-use std::fs::{read_to_string, OpenOptions};
+use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::Path;
 use anyhow::Result;
-use chrono::{DateTime, Local, Offset};
 
 use crate::commands::branch::read_current_branch;
 use crate::commands::commit::read_current_commit;
-
-fn read_author() -> Result<(String, String)> {
-    let config_path = Path::new(".ink/.inkconfig");
-    let mut name = "Nilotpal Gupta".to_string();
-    let mut email = "nilotpalgupta0701@gmail.com".to_string();
-
-    if config_path.exists() {
-        for line in read_to_string(config_path)?.lines() {
-            if let Some(v) = line.strip_prefix("author=") {
-                name = v.trim().to_string();
-            } else if let Some(v) = line.strip_prefix("email=") {
-                email = v.trim().to_string();
-            }
-        }
-    }
-
-    Ok((name, email))
-}
+use crate::utils::signature::Signature;
 
 pub fn log_action(parent_hash: String, current_hash: String, log_type: &str, message: &str) -> Result<()> {
-    let (author_name, author_email) = read_author()?;
-
-    let now: DateTime<Local> = Local::now();
-    let timestamp = now.timestamp();
-    let offset = now.offset().fix().local_minus_utc();
-    let offset_str = format!("{:+03}{:02}", offset / 3600, (offset.abs() % 3600) / 60);
+    // Reflog entries should never block an operation, so fall back to an unnamed identity
+    let committer = Signature::committer()
+        .unwrap_or_else(|_| Signature::now("unknown".to_string(), "unknown".to_string()));
 
     let log_line = format!(
-        "{} {} {} <{}> {} {}	{}: {}\n",
+        "{} {} {}\t{}: {}\n",
         parent_hash,
         current_hash,
-        author_name,
-        author_email,
-        timestamp,
-        offset_str,
+        committer,
         log_type,
         message
     );
@@ -55,7 +31,8 @@ pub fn log_action(parent_hash: String, current_hash: String, log_type: &str, mes
     let mut head_file = OpenOptions::new().create(true).append(true).open(head_path)?;
     head_file.write_all(log_line.as_bytes())?;
 
-    if log_type == "commit" || log_type == "branch" {
+    // Everything except checkout moves (or creates) the branch itself
+    if !log_type.starts_with("checkout") {
         let mut branch_file = OpenOptions::new().create(true).append(true).open(branch_path)?;
         branch_file.write_all(log_line.as_bytes())?;
     }
@@ -0,0 +1,217 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{create_dir_all, remove_file, write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+use crate::commands::checkout::{get_tree_entries, restore_blob};
+use crate::commands::commit::{read_current_commit, read_tree_of_commit};
+use crate::utils::diff::{diff, is_binary, split_lines, Edit};
+use crate::utils::dir::remove_empty_parents_up_to;
+use crate::utils::index::{save_index_for_current_branch, Index, IndexEntry};
+use crate::utils::object::{create_blob_from_bytes, hash_file, read_blob_object, ZERO_HASH};
+use crate::utils::repo::Repo;
+
+/// Outcome of merging one path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Merged {
+    /// The path resolved to this blob
+    Clean(String),
+    /// The path resolved to being deleted
+    Deleted,
+    /// Both sides changed the path; the working tree gets `content` (with
+    /// conflict markers for text) and the index entry is dropped until a
+    /// resolution is staged
+    Conflict { content: Vec<u8>, reason: &'static str },
+}
+
+/// Three-way merge of `ours` and `theirs` against `base`, as `path -> blob` maps.
+/// Only paths that differ from `ours` appear in the result.
+pub fn merge_trees(
+    repo: &Repo,
+    base: &HashMap<PathBuf, String>,
+    ours: &HashMap<PathBuf, String>,
+    theirs: &HashMap<PathBuf, String>,
+    labels: (&str, &str),
+) -> Result<HashMap<PathBuf, Merged>> {
+    let paths: BTreeSet<&PathBuf> = base.keys().chain(ours.keys()).chain(theirs.keys()).collect();
+    let mut out = HashMap::new();
+
+    for path in paths {
+        let (b, o, t) = (base.get(path), ours.get(path), theirs.get(path));
+        if o == t || b == t {
+            continue;
+        }
+        if b == o {
+            out.insert(path.clone(), t.map_or(Merged::Deleted, |t| Merged::Clean(t.clone())));
+            continue;
+        }
+
+        let merged = match (o, t) {
+            (Some(o), Some(t)) => {
+                let base_data = match b {
+                    Some(b) => read_blob_object(b)?,
+                    None => Vec::new(),
+                };
+                let (ours_data, theirs_data) = (read_blob_object(o)?, read_blob_object(t)?);
+
+                if [&base_data, &ours_data, &theirs_data].iter().any(|d| is_binary(d)) {
+                    Merged::Conflict { content: ours_data, reason: "binary" }
+                } else {
+                    let (text, clean) = merge_text(
+                        &String::from_utf8_lossy(&base_data),
+                        &String::from_utf8_lossy(&ours_data),
+                        &String::from_utf8_lossy(&theirs_data),
+                        labels,
+                    );
+                    match (clean, b) {
+                        (true, _) => Merged::Clean(create_blob_from_bytes(repo, text.as_bytes())?),
+                        (false, Some(_)) => Merged::Conflict { content: text.into_bytes(), reason: "content" },
+                        (false, None) => Merged::Conflict { content: text.into_bytes(), reason: "add/add" },
+                    }
+                }
+            }
+            // One side deleted what the other modified: keep the modified file for review
+            (Some(o), None) => Merged::Conflict { content: read_blob_object(o)?, reason: "modify/delete" },
+            (None, Some(t)) => Merged::Conflict { content: read_blob_object(t)?, reason: "modify/delete" },
+            // Equal sides were handled above
+            (None, None) => continue,
+        };
+        out.insert(path.clone(), merged);
+    }
+
+    Ok(out)
+}
+
+/// Line-based three-way merge (diff3). Returns the merged text and whether it
+/// merged without conflicts; conflicting regions are wrapped in markers
+/// labelled with `labels`.
+pub fn merge_text(base: &str, ours: &str, theirs: &str, labels: (&str, &str)) -> (String, bool) {
+    let base_lines = split_lines(base);
+    let ours_lines = split_lines(ours);
+    let theirs_lines = split_lines(theirs);
+
+    let to_ours = matches(&base_lines, &ours_lines);
+    let to_theirs = matches(&base_lines, &theirs_lines);
+
+    let mut out = String::new();
+    let mut clean = true;
+    let (mut i, mut a, mut b) = (0, 0, 0);
+
+    loop {
+        // Next base line that both sides kept, after the current position
+        let stable = (i..base_lines.len()).find_map(|k| match (to_ours[k], to_theirs[k]) {
+            (Some(x), Some(y)) if x >= a && y >= b => Some((k, x, y)),
+            _ => None,
+        });
+        let (k, x, y) = stable.unwrap_or((base_lines.len(), ours_lines.len(), theirs_lines.len()));
+
+        if (k, x, y) != (i, a, b) {
+            let (o, t, bs) = (&ours_lines[a..x], &theirs_lines[b..y], &base_lines[i..k]);
+            if o == bs || o == t {
+                out.extend(t.iter().copied());
+            } else if t == bs {
+                out.extend(o.iter().copied());
+            } else {
+                clean = false;
+                out.push_str(&format!("<<<<<<< {}\n", labels.0));
+                push_block(&mut out, o);
+                out.push_str("=======\n");
+                push_block(&mut out, t);
+                out.push_str(&format!(">>>>>>> {}\n", labels.1));
+            }
+        }
+
+        if stable.is_none() {
+            break;
+        }
+        out.push_str(base_lines[k]);
+        (i, a, b) = (k + 1, x + 1, y + 1);
+    }
+
+    (out, clean)
+}
+
+/// For every line of `base`, its index in `other` if the diff kept it
+fn matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut out = vec![None; base.len()];
+    for edit in diff(base, other) {
+        if let Edit::Equal(i, j) = edit {
+            out[i] = Some(j);
+        }
+    }
+    out
+}
+
+/// Appends lines, making sure the block ends with a newline before the next marker
+fn push_block(out: &mut String, lines: &[&str]) {
+    for line in lines {
+        out.push_str(line);
+    }
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Applies the change from `base` to `theirs` on top of HEAD, updating the
+/// working tree and index. Returns the paths left in conflict with the reason.
+pub fn apply_change(
+    repo: &Repo,
+    base: &HashMap<PathBuf, String>,
+    theirs: &HashMap<PathBuf, String>,
+    labels: (&str, &str),
+) -> Result<Vec<(PathBuf, &'static str)>> {
+    let head = read_current_commit()?;
+    let ours = if head == ZERO_HASH { HashMap::new() } else { get_tree_entries(&read_tree_of_commit(&head)?)? };
+
+    let mut merged: Vec<(PathBuf, Merged)> = merge_trees(repo, base, &ours, theirs, labels)?.into_iter().collect();
+    merged.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (path, result) in &merged {
+        if !ours.contains_key(path) && *result != Merged::Deleted && path.exists() {
+            return Err(anyhow!(
+                "Untracked file '{}' would be overwritten; please move or remove it first.",
+                path.display()
+            ));
+        }
+    }
+
+    let mut index = Index::load()?;
+    let mut conflicts = Vec::new();
+    for (path, result) in merged {
+        match result {
+            Merged::Clean(hash) => {
+                restore_blob(&path, &hash)?;
+                index.add(IndexEntry { path, hash });
+            }
+            Merged::Deleted => {
+                if path.exists() {
+                    remove_file(&path)?;
+                    remove_empty_parents_up_to(&path, Path::new("."))?;
+                }
+                index.remove(&path);
+            }
+            Merged::Conflict { content, reason } => {
+                if let Some(parent) = path.parent() {
+                    create_dir_all(parent)?;
+                }
+                write(&path, content)?;
+                // Otherwise a binary or modify/delete conflict, whose working tree copy is
+                // our version unchanged, would already count as resolved
+                index.remove(&path);
+                conflicts.push((path, reason));
+            }
+        }
+    }
+
+    index.save()?;
+    save_index_for_current_branch()?;
+    Ok(conflicts)
+}
+
+/// A conflicted path is resolved once the working tree copy is staged (or it is
+/// gone from both)
+pub fn is_resolved(repo: &Repo, path: &Path, index: &Index) -> bool {
+    let staged = index.entries.get(path).map(|e| &e.hash);
+    let worktree = hash_file(repo, path).ok();
+    staged == worktree.as_ref()
+}
@@ -6,3 +6,21 @@ pub mod index;
 pub mod ignore;
 pub mod object;
 pub mod log;
+pub mod config;
+pub mod signature;
+pub mod editor;
+pub mod hooks;
+pub mod pathspec;
+pub mod diff;
+pub mod revision;
+pub mod history;
+pub mod remote;
+pub mod transport;
+pub mod bundle;
+pub mod watch;
+pub mod graph;
+pub mod merge;
+pub mod sequencer;
+pub mod repo;
+pub mod lfs;
+pub mod chunk;
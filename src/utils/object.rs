@@ -1,33 +1,250 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fs::{create_dir_all, read, write};
+use std::fs::{create_dir_all, metadata, read, read_dir, remove_file, rename, write, File};
+use std::io::{copy, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::{ anyhow, Result };
 use rayon::prelude::*;
 
-use crate::utils::hash::hash_object;
+use crate::utils::chunk::{store_chunks, CHUNK_THRESHOLD};
 use crate::utils::index::Index;
-use crate::utils::zip::{compress, decompress};
+use crate::utils::lfs;
+use crate::utils::signature::Signature;
+use crate::utils::repo::Repo;
+use crate::utils::zip::{decompress, decompress_reader, ObjectWriter};
 
-pub fn create_blob(path: PathBuf) -> Result<String> {
-    let content = read(path)?;
+/// Placeholder used for "no commit", e.g. the parent of a root commit
+pub const ZERO_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Files larger than this are hashed and stored in pieces instead of being read into memory
+const STREAM_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+/// Size of the pieces large files are read in
+const CHUNK_SIZE: usize = 256 * 1024;
+
+pub fn create_blob(repo: &Repo, path: PathBuf) -> Result<String> {
+    if lfs::is_tracked(&path) {
+        return create_blob_from_bytes(repo, lfs::clean(&path)?.to_text().as_bytes());
+    }
+    if metadata(&path)?.len() <= STREAM_THRESHOLD {
+        return create_blob_from_bytes(repo, &read(path)?);
+    }
+
+    // Hash first, so re-adding an unchanged large file costs a read but no writes
+    let hash = hash_file(repo, &path)?;
+    if object_exists(&hash) {
+        return Ok(hash);
+    }
+
+    let file = File::open(&path)?;
+    let size = file.metadata()?.len();
+    write_blob_from(repo, Path::new(".ink/objects"), size, file)
+        .map_err(|e| anyhow!("Cannot store '{}': {}", path.display(), e))
+}
+
+/// The blob hash of a file's current content, without storing it
+pub fn hash_file(repo: &Repo, path: &Path) -> Result<String> {
+    if lfs::is_tracked(path) {
+        return Ok(hash_blob(repo, lfs::pointer_of(path)?.to_text().as_bytes()));
+    }
+    let size = metadata(path)?.len();
+    if size <= STREAM_THRESHOLD {
+        return Ok(hash_blob(repo, &read(path)?));
+    }
+
+    let mut hasher = repo.hasher();
+    hasher.update(format!("blob {}\0", size).as_bytes());
+    let copied = copy(&mut File::open(path)?, &mut hasher)?;
+    if copied != size {
+        return Err(anyhow!("'{}' changed while it was being read", path.display()));
+    }
+    Ok(hasher.finish())
+}
+
+/// Stores a blob of `size` bytes read from `body` in the object store at
+/// `objects`, a piece at a time, and returns its hash. Large blobs are split
+/// into chunks when `core.chunking` is on; others are compressed into a
+/// temporary file while being hashed and then moved into place.
+pub fn write_blob_from(repo: &Repo, objects: &Path, size: u64, mut body: impl Read) -> Result<String> {
+    let changed = |total: u64| anyhow!("content changed while it was being stored ({} of {} bytes)", total, size);
+
+    if repo.chunking && size > CHUNK_THRESHOLD as u64 {
+        let (hash, manifest, total) = store_chunks(repo, "blob", size, body)?;
+        if total != size {
+            return Err(changed(total));
+        }
+        let obj_path = objects.join(&hash[..2]).join(&hash[2..]);
+        create_dir_all(obj_path.parent().unwrap())?;
+        write(obj_path, manifest)?;
+        return Ok(hash);
+    }
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    create_dir_all(objects)?;
+    let tmp = objects.join(format!("tmp-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+
+    let written = (|| -> Result<String> {
+        let mut writer = ObjectWriter::new(File::create(&tmp)?, repo.encoding, repo.level)?;
+        let mut hasher = repo.hasher();
+
+        let header = format!("blob {}\0", size);
+        hasher.update(header.as_bytes());
+        writer.write_all(header.as_bytes())?;
+
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut total = 0;
+        loop {
+            let n = body.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            writer.write_all(&buf[..n])?;
+            total += n as u64;
+        }
+        writer.finish()?;
+
+        if total != size {
+            return Err(changed(total));
+        }
+        Ok(hasher.finish())
+    })();
+
+    let hash = match written {
+        Ok(hash) => hash,
+        Err(e) => {
+            remove_file(&tmp).ok();
+            return Err(e);
+        }
+    };
+
+    let obj_path = objects.join(&hash[..2]).join(&hash[2..]);
+    if obj_path.exists() {
+        remove_file(&tmp)?;
+    } else {
+        create_dir_all(obj_path.parent().unwrap())?;
+        rename(&tmp, &obj_path)?;
+    }
+    Ok(hash)
+}
+
+/// Stores in-memory content for `path` the way `create_blob` stores the file,
+/// going through the large file store when `path` is tracked there
+pub fn create_blob_for(repo: &Repo, path: &Path, content: &[u8]) -> Result<String> {
+    if lfs::is_tracked(path) {
+        return create_blob_from_bytes(repo, lfs::clean_bytes(content)?.to_text().as_bytes());
+    }
+    create_blob_from_bytes(repo, content)
+}
+
+/// Stores `content` as a blob, e.g. for partially staged files that exist only in memory
+pub fn create_blob_from_bytes(repo: &Repo, content: &[u8]) -> Result<String> {
     let header = format!("blob {}\0", content.len());
-    let full = [header.as_bytes(), &content].concat();
+    let full = [header.as_bytes(), content].concat();
 
-    let hash = hash_object(&full)?;
-    let obj_path = PathBuf::from(format!(".ink/objects/{}/{}", &hash[..2], &hash[2..]));
+    write_raw_object(repo, full)
+}
+
+/// Location of an object in the store
+pub fn object_path(hash: &str) -> PathBuf {
+    Path::new(".ink/objects").join(&hash[..2]).join(&hash[2..])
+}
+
+pub fn object_exists(hash: &str) -> bool {
+    hash.len() > 2 && object_path(hash).exists()
+}
+
+/// Names of every object file, from the `xx/yyyy` layout
+pub fn list_objects() -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for dir in read_dir(".ink/objects")? {
+        let dir = dir?;
+        let prefix = dir.file_name().to_string_lossy().into_owned();
+        if !dir.file_type()?.is_dir() || prefix.len() != 2 || !is_hex(&prefix) {
+            continue;
+        }
+        for file in read_dir(dir.path())? {
+            let rest = file?.file_name().to_string_lossy().into_owned();
+            if is_hex(&rest) {
+                names.push(format!("{}{}", prefix, rest));
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+pub fn is_hex(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Stores a complete object (`<type> <len>\0<body>`) and returns its hash
+pub fn write_raw_object(repo: &Repo, full: Vec<u8>) -> Result<String> {
+    let hash = repo.hash(&full);
+    let obj_path = object_path(&hash);
 
     if obj_path.exists() {
         return Ok(hash)
     }
 
-    let compressed = compress(full)?;
+    let encoded = encode_object(repo, full)?;
     create_dir_all(obj_path.parent().unwrap())?;
-    write(obj_path, compressed)?;
+    write(obj_path, encoded)?;
 
     Ok(hash)
 }
 
+/// What is stored for a complete object: a chunk list for blobs large enough
+/// to chunk when `core.chunking` is on, otherwise the compressed object
+pub fn encode_object(repo: &Repo, full: Vec<u8>) -> Result<Vec<u8>> {
+    if repo.chunking && full.len() > CHUNK_THRESHOLD {
+        let (kind, body) = parse_raw_object(&full)?;
+        if kind == "blob" && body.len() > CHUNK_THRESHOLD {
+            return Ok(store_chunks(repo, kind, body.len() as u64, body)?.1);
+        }
+    }
+    repo.compress(full)
+}
+
+/// Reads an object and returns its decompressed bytes, header included
+pub fn read_raw_object(hash: &str) -> Result<Vec<u8>> {
+    let data = read(object_path(hash)).map_err(|e| anyhow!("Cannot read object {}: {}", hash, e))?;
+    decompress(data).map_err(|e| anyhow!("Object {} is corrupt ({}); run 'ink fsck' for details", hash, e))
+}
+
+/// Opens an object for streaming, returning its type, body size and a reader
+/// positioned at the start of the body
+pub fn open_object(hash: &str) -> Result<(String, u64, Box<dyn Read>)> {
+    let file = File::open(object_path(hash)).map_err(|e| anyhow!("Cannot read object {}: {}", hash, e))?;
+    let mut reader = decompress_reader(file).map_err(|e| anyhow!("Object {} is corrupt ({}); run 'ink fsck' for details", hash, e))?;
+
+    let mut header = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0] == 0 {
+            break;
+        }
+        header.push(byte[0]);
+        if header.len() > 64 {
+            return Err(anyhow!("Object {} has no valid header; run 'ink fsck'", hash));
+        }
+    }
+
+    let header = String::from_utf8(header)?;
+    let (kind, size) = header.split_once(' ').ok_or_else(|| anyhow!("Invalid object header: {}", header))?;
+    let size = size.parse().map_err(|_| anyhow!("Invalid object header: {}", header))?;
+    Ok((kind.to_string(), size, reader))
+}
+
+/// Splits a raw object into its type and body
+pub fn parse_raw_object(full: &[u8]) -> Result<(&str, &[u8])> {
+    let nul = full.iter().position(|b| *b == 0).ok_or_else(|| anyhow!("Invalid object: missing header"))?;
+    let header = std::str::from_utf8(&full[..nul])?;
+    let (kind, _) = header.split_once(' ').ok_or_else(|| anyhow!("Invalid object header: {}", header))?;
+    Ok((kind, &full[nul + 1..]))
+}
+
 /// Represents a single tree entry (mode, type, hash, filename)
 #[derive(Debug, Clone)]
 struct TreeEntry {
@@ -37,8 +254,13 @@ struct TreeEntry {
     name: String,
 }
 
-pub fn create_tree() -> Result<String> {
-    let index = Index::load()?;
+pub fn create_tree(repo: &Repo) -> Result<String> {
+    create_tree_from(repo, &Index::load()?)
+}
+
+/// Writes the trees for an arbitrary set of index entries, e.g. a snapshot
+/// that should not touch the real index
+pub fn create_tree_from(repo: &Repo, index: &Index) -> Result<String> {
     let mut dir_entries: HashMap<PathBuf, Vec<TreeEntry>> = HashMap::new();
     let mut all_dirs = HashSet::new();
 
@@ -121,14 +343,7 @@ pub fn create_tree() -> Result<String> {
 
                 let header = format!("tree {}\0", tree_bytes.len());
                 let full = [header.as_bytes(), &tree_bytes].concat();
-                let hash = hash_object(&full).expect("hash failed");
-
-                let obj_path = PathBuf::from(format!(".ink/objects/{}/{}", &hash[..2], &hash[2..]));
-                if !obj_path.exists() {
-                    let compressed = compress(full).expect("compress failed");
-                    create_dir_all(obj_path.parent().unwrap()).expect("mkdir failed");
-                    write(obj_path, compressed).expect("write failed");
-                }
+                let hash = write_raw_object(repo, full).expect("writing tree failed");
 
                 (dir.clone(), hash)
             })
@@ -145,38 +360,44 @@ pub fn create_tree() -> Result<String> {
         .ok_or_else(|| anyhow!("No root tree created"))
 }
 
-/// Creates a commit object from a tree hash and returns the commit hash.
-/// If parent is Some(hash), sets it as the commit's parent.
-pub fn create_commit(tree: &str, parent_hash: &str, message: &str, author: &str) -> Result<String> {
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+/// A parsed commit object
+#[derive(Debug, Clone)]
+pub struct Commit {
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub author: Signature,
+    pub committer: Signature,
+    pub message: String,
+}
 
+/// Creates a commit object from a tree hash and returns the commit hash.
+pub fn create_commit(
+    repo: &Repo,
+    tree: &str,
+    parents: &[String],
+    message: &str,
+    author: &Signature,
+    committer: &Signature,
+) -> Result<String> {
     let mut content = format!("tree {}\n", tree);
-    content += &format!("parent {}\n", parent_hash);
-    content += &format!(
-        "author {} {} +0000\ncommitter {} {} +0000\n\n{}\n",
-        author, timestamp, author, timestamp, message
-    );
+    if parents.is_empty() {
+        content += &format!("parent {}\n", ZERO_HASH);
+    }
+    for parent in parents {
+        content += &format!("parent {}\n", parent);
+    }
+    content += &format!("author {}\ncommitter {}\n\n{}\n", author, committer, message);
 
     let header = format!("commit {}\0", content.len());
     let full = [header.as_bytes(), content.as_bytes()].concat();
-    let hash = hash_object(&full)?;
-
-    let obj_path = PathBuf::from(format!(".ink/objects/{}/{}", &hash[..2], &hash[2..]));
-    if !obj_path.exists() {
-        let compressed = compress(full)?;
-        create_dir_all(obj_path.parent().unwrap())?;
-        write(&obj_path, compressed)?;
-    }
 
-    Ok(hash)
+    write_raw_object(repo, full)
 }
 
 /// Reads a tree object by its hash and returns entries (path, type, hash)
 pub fn read_tree_object(hash: &str) -> Result<Vec<(PathBuf, String, String)>> {
-    let path = Path::new(".ink/objects").join(&hash[..2]).join(&hash[2..]);
-    let data = read(path)?;
-    let decompressed = decompress(data)?;
-    let text = std::str::from_utf8(&decompressed)?;
+    let decompressed = read_raw_object(hash)?;
+    let text = std::str::from_utf8(&decompressed).map_err(|_| anyhow!("Tree {} is not valid UTF-8; run 'ink fsck'", hash))?;
 
     let (_, body) = text
         .split_once('\0')
@@ -204,11 +425,15 @@ pub fn read_tree_object(hash: &str) -> Result<Vec<(PathBuf, String, String)>> {
     Ok(entries)
 }
 
+/// Hashes content as a blob without writing it to the object store
+pub fn hash_blob(repo: &Repo, content: &[u8]) -> String {
+    let header = format!("blob {}\0", content.len());
+    repo.hash(&[header.as_bytes(), content].concat())
+}
+
 /// Reads a blob object by hash and returns its raw content
 pub fn read_blob_object(hash: &str) -> Result<Vec<u8>> {
-    let path = Path::new(".ink/objects").join(&hash[..2]).join(&hash[2..]);
-    let data = read(path)?;
-    let decompressed = decompress(data)?;
+    let decompressed = read_raw_object(hash)?;
 
     let body = decompressed
         .splitn(2, |b| *b == 0)
@@ -217,3 +442,43 @@ pub fn read_blob_object(hash: &str) -> Result<Vec<u8>> {
 
     Ok(body.to_vec())
 }
+
+/// Reads and parses a commit object by hash
+pub fn read_commit_object(hash: &str) -> Result<Commit> {
+    let decompressed = read_raw_object(hash)?;
+    let text = std::str::from_utf8(&decompressed).map_err(|_| anyhow!("Commit {} is not valid UTF-8; run 'ink fsck'", hash))?;
+
+    let (_, body) = text
+        .split_once('\0')
+        .ok_or_else(|| anyhow!("Invalid commit object: {}", hash))?;
+    let (headers, message) = body.split_once("\n\n").unwrap_or((body, ""));
+
+    let mut tree = None;
+    let mut parents = Vec::new();
+    let mut author = None;
+    let mut committer = None;
+
+    for line in headers.lines() {
+        if let Some(v) = line.strip_prefix("tree ") {
+            tree = Some(v.trim().to_string());
+        } else if let Some(v) = line.strip_prefix("parent ") {
+            // Root commits record the zero hash as their parent
+            if v.trim() != ZERO_HASH {
+                parents.push(v.trim().to_string());
+            }
+        } else if let Some(v) = line.strip_prefix("author ") {
+            author = Some(Signature::parse(v)?);
+        } else if let Some(v) = line.strip_prefix("committer ") {
+            committer = Some(Signature::parse(v)?);
+        }
+    }
+
+    let author = author.ok_or_else(|| anyhow!("No author in commit {}", hash))?;
+    Ok(Commit {
+        tree: tree.ok_or_else(|| anyhow!("No tree found in commit {}", hash))?,
+        parents,
+        committer: committer.unwrap_or_else(|| author.clone()),
+        author,
+        message: message.strip_suffix('\n').unwrap_or(message).to_string(),
+    })
+}
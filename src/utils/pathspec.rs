@@ -0,0 +1,189 @@
+use std::path::{Component, Path, PathBuf};
+use anyhow::{anyhow, Result};
+use glob::{MatchOptions, Pattern};
+
+/// One parsed pathspec argument
+#[derive(Debug, Clone)]
+struct PathspecItem {
+    raw: String,
+    /// Normalized path (lowercased for `:(icase)`), used for literal matching
+    spec: String,
+    pattern: Option<Pattern>,
+    exclude: bool,
+    icase: bool,
+    /// `:(glob)` magic: `*` does not cross `/`, only `**` does
+    strict_glob: bool,
+}
+
+/// A set of pathspecs, as accepted by every path-taking command.
+///
+/// Each item matches a path exactly, any path below it when it names a
+/// directory, or as a glob (`src/**/*.rs`). Magic prefixes are supported:
+/// `:(exclude)` (or `:!`, `:^`), `:(icase)`, `:(literal)` and `:(glob)`,
+/// and can be combined as `:(exclude,icase)pattern`.
+#[derive(Debug, Clone, Default)]
+pub struct Pathspec {
+    items: Vec<PathspecItem>,
+}
+
+impl Pathspec {
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> Result<Self> {
+        let items = args
+            .iter()
+            .map(|a| PathspecItem::parse(a.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Pathspec { items })
+    }
+
+    pub fn from_paths(paths: &[PathBuf]) -> Result<Self> {
+        let args: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+        Self::parse(&args)
+    }
+
+    /// True when no pathspec was given at all, so everything is included
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Whether a repository-relative path is selected by this pathspec
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = normalize(path);
+        let path = path.to_string_lossy();
+
+        let mut includes = self.items.iter().filter(|i| !i.exclude).peekable();
+        let included = includes.peek().is_none() || includes.any(|i| i.matches(&path));
+
+        included && !self.items.iter().filter(|i| i.exclude).any(|i| i.matches(&path))
+    }
+
+    /// Items that did not select any of `paths`, for "did not match any files" errors
+    pub fn unmatched<'a>(&'a self, paths: &[PathBuf]) -> Vec<&'a str> {
+        self.items
+            .iter()
+            .filter(|i| !i.exclude)
+            .filter(|i| !paths.iter().any(|p| i.matches(&normalize(p).to_string_lossy())))
+            .map(|i| i.raw.as_str())
+            .collect()
+    }
+}
+
+impl PathspecItem {
+    fn parse(arg: &str) -> Result<Self> {
+        let mut exclude = false;
+        let mut icase = false;
+        let mut literal = false;
+        let mut strict_glob = false;
+        let mut spec = arg;
+
+        if let Some(rest) = arg.strip_prefix(":(") {
+            let (magic, tail) = rest
+                .split_once(')')
+                .ok_or_else(|| anyhow!("Unterminated pathspec magic in '{}'", arg))?;
+            for word in magic.split(',').map(str::trim) {
+                match word {
+                    "exclude" => exclude = true,
+                    "icase" => icase = true,
+                    "literal" => literal = true,
+                    "glob" => strict_glob = true,
+                    "top" | "" => {}
+                    other => return Err(anyhow!("Unknown pathspec magic '{}' in '{}'", other, arg)),
+                }
+            }
+            spec = tail;
+        } else if let Some(rest) = arg.strip_prefix(":!").or_else(|| arg.strip_prefix(":^")) {
+            exclude = true;
+            spec = rest;
+        } else if let Some(rest) = arg.strip_prefix(':') {
+            spec = rest;
+        }
+
+        let mut spec = normalize_arg(Path::new(spec))
+            .map_err(|e| anyhow!("Invalid pathspec '{}': {}", arg, e))?
+            .to_string_lossy()
+            .into_owned();
+        if icase {
+            spec = spec.to_lowercase();
+        }
+
+        // A file that really has `*?[` in its name is meant as itself, not as a glob
+        let pattern = if !literal && spec.contains(['*', '?', '[']) && !Path::new(&spec).exists() {
+            Some(Pattern::new(&spec).map_err(|e| anyhow!("Invalid pathspec '{}': {}", arg, e))?)
+        } else {
+            None
+        };
+
+        Ok(PathspecItem {
+            raw: arg.to_string(),
+            spec,
+            pattern,
+            exclude,
+            icase,
+            strict_glob,
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let path = if self.icase { path.to_lowercase() } else { path.to_string() };
+
+        // Exact names are tried first, so a deleted `a[1].txt` still matches itself
+        if self.spec.is_empty()
+            || path == self.spec
+            || path.strip_prefix(&self.spec).is_some_and(|rest| rest.starts_with('/'))
+        {
+            return true;
+        }
+
+        self.pattern.as_ref().is_some_and(|pattern| {
+            let options = MatchOptions {
+                case_sensitive: true,
+                require_literal_separator: self.strict_glob,
+                require_literal_leading_dot: false,
+            };
+            // A glob naming a directory selects everything below it
+            ancestors_and_self(&path).any(|p| pattern.matches_with(p, options))
+        })
+    }
+}
+
+fn ancestors_and_self(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('/')
+        .map(move |(i, _)| &path[..i])
+        .chain(std::iter::once(path))
+}
+
+/// Resolves `.` and `..` in a pathspec argument and makes absolute paths
+/// relative to the repository, refusing anything that ends up outside it
+fn normalize_arg(path: &Path) -> Result<PathBuf> {
+    let outside = || anyhow!("'{}' is outside the repository", path.display());
+    let relative = if path.has_root() {
+        let root = std::env::current_dir()?;
+        path.strip_prefix(&root).map_err(|_| outside())?.to_path_buf()
+    } else {
+        path.to_path_buf()
+    };
+
+    let mut parts: Vec<String> = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(s) => parts.push(s.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop().ok_or_else(outside)?;
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(outside()),
+        }
+    }
+    Ok(PathBuf::from(parts.join("/")))
+}
+
+/// Strips `./` components and uses `/` separators, so `./src\a.rs` becomes `src/a.rs`
+fn normalize(path: &Path) -> PathBuf {
+    let parts: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    PathBuf::from(parts.join("/"))
+}
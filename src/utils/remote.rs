@@ -0,0 +1,347 @@
+use std::fs::{metadata, remove_file, write, File};
+use std::io::{copy, sink, BufRead, Read, Write};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use bincode::config::standard;
+use bincode::serde::{decode_from_slice, encode_to_vec};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::commands::branch::read_current_branch;
+use crate::commands::checkout::switch_tree;
+use crate::utils::config::get_config;
+use crate::utils::dir::remove_empty_parents_up_to;
+use crate::utils::hash::{load_hash_algo, StreamHasher};
+use crate::utils::history::{is_ancestor, missing_objects};
+use crate::utils::lfs;
+use crate::utils::object::{
+    is_hex, object_exists, open_object, parse_raw_object, write_blob_from, write_raw_object, ZERO_HASH,
+};
+use crate::utils::repo::Repo;
+use crate::utils::revision::{check_ref_name, list_refs, resolve_ref};
+
+/// Limit on a length-prefixed message such as a push request; objects travel in the pack stream instead
+const MAX_MESSAGE: u64 = 16 << 20;
+
+const MAX_ENTRY_LINE: u64 = 256;
+
+const MAX_OBJECT_HEADER: u64 = 64;
+
+/// What a repository offers to its peers: its hash algorithm and ref tips
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Advertisement {
+    pub hash_algo: String,
+    /// Branch HEAD points to, if it has any commits
+    pub head: Option<String>,
+    /// Full ref names (`refs/heads/main`) and the commits they point to
+    pub refs: Vec<(String, String)>,
+}
+
+impl Advertisement {
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.refs.iter().find(|(n, _)| n == name).map(|(_, h)| h)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FetchRequest {
+    pub wants: Vec<String>,
+    pub haves: Vec<String>,
+}
+
+/// What a pack stream stored: objects and large files that were not already present
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Received {
+    pub objects: usize,
+    pub lfs: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefUpdate {
+    pub name: String,
+    /// Value the pusher expects the ref to have; `ZERO_HASH` when creating it
+    pub old: String,
+    /// New value; `ZERO_HASH` deletes the ref
+    pub new: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushRequest {
+    pub updates: Vec<RefUpdate>,
+    pub force: bool,
+}
+
+/// Per-ref outcome of a push: `None` on success, or the reason it was rejected
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushResult {
+    pub results: Vec<(String, Option<String>)>,
+}
+
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    Ok(encode_to_vec(value, standard())?)
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let (value, _) = decode_from_slice(bytes, standard()).map_err(|e| anyhow!("Malformed remote message: {}", e))?;
+    Ok(value)
+}
+
+/// Advertises the branches of the repository in the current directory
+pub fn advertise() -> Result<Advertisement> {
+    let refs = list_refs("refs/heads")?;
+    let current = format!("refs/heads/{}", read_current_branch()?);
+    let head = refs.iter().any(|(n, _)| *n == current).then_some(current);
+
+    Ok(Advertisement {
+        hash_algo: load_hash_algo()?.to_string(),
+        head,
+        refs,
+    })
+}
+
+/// The objects a peer holding `haves` needs to reach `wants`
+pub fn upload_pack(req: &FetchRequest) -> Result<Vec<String>> {
+    for want in &req.wants {
+        if !object_exists(want) {
+            return Err(anyhow!("Remote does not have the requested commit {}", want));
+        }
+    }
+
+    // The peer may have commits we have never seen; they can't shorten the walk
+    let haves: Vec<String> = req.haves.iter().filter(|h| object_exists(h)).cloned().collect();
+    missing_objects(&req.wants, &haves)
+}
+
+/// Writes a length-prefixed message, for streams where more data follows it
+pub fn write_message<T: Serialize>(out: &mut impl Write, value: &T) -> Result<()> {
+    let bytes = encode(value)?;
+    out.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    out.write_all(&bytes)?;
+    Ok(())
+}
+
+pub fn read_message<T: DeserializeOwned>(input: &mut impl Read) -> Result<T> {
+    let mut len = [0u8; 8];
+    input.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > MAX_MESSAGE {
+        return Err(anyhow!("Remote message of {} bytes is too large", len));
+    }
+
+    let mut bytes = vec![0; len as usize];
+    input.read_exact(&mut bytes)?;
+    decode(&bytes)
+}
+
+/// Writes objects and then the stored contents of large files as a pack stream.
+/// Every entry is a header line followed by its bytes, copied a piece at a time:
+///
+/// ```text
+/// object <hash> <len>     the complete object, `<type> <size>\0<body>`
+/// lfs <algo>:<oid> <len>  the content of a large file
+/// end
+/// ```
+///
+/// Large files this store lacks are left out.
+pub fn write_pack(out: &mut impl Write, objects: &[String], lfs_ids: &[String]) -> Result<()> {
+    for hash in objects {
+        let (kind, size, body) = open_object(hash)?;
+        let header = format!("{} {}\0", kind, size);
+        writeln!(out, "object {} {}", hash, header.len() as u64 + size)?;
+        out.write_all(header.as_bytes())?;
+        if copy(&mut body.take(size), out)? != size {
+            return Err(anyhow!("Object {} is shorter than its header says; run 'ink fsck'", hash));
+        }
+    }
+
+    for id in lfs_ids {
+        let oid = lfs::parse_id(id)?.1;
+        let Ok(chunks) = lfs::manifest(oid) else { continue };
+        let paths: Vec<_> = chunks.iter().map(|c| lfs::store_path("chunks", c)).collect();
+        let len = paths.iter().map(|p| Ok(metadata(p)?.len())).sum::<Result<u64>>()?;
+
+        writeln!(out, "lfs {} {}", id, len)?;
+        for path in paths {
+            copy(&mut File::open(path)?, out)?;
+        }
+    }
+
+    writeln!(out, "end")?;
+    Ok(())
+}
+
+/// Stores the entries of a pack stream as they arrive, checking each against its hash
+pub fn read_pack(repo: &Repo, input: &mut impl BufRead) -> Result<Received> {
+    let mut received = Received::default();
+
+    while let Some((kind, name, len)) = next_entry(input)? {
+        let mut data = input.by_ref().take(len);
+        match kind.as_str() {
+            "object" if object_exists(&name) => {
+                copy(&mut data, &mut sink())?;
+            }
+            "object" => {
+                store_object(repo, &name, len, &mut data)?;
+                received.objects += 1;
+            }
+            _ => {
+                if lfs::store_from(&name, len, &mut data)? {
+                    received.lfs += 1;
+                }
+            }
+        }
+
+        if data.limit() != 0 {
+            return Err(anyhow!("Pack stream ended inside {} {}", kind, name));
+        }
+    }
+
+    Ok(received)
+}
+
+/// Reads a pack stream through, checking every entry without storing anything
+pub fn check_pack(repo: &Repo, input: &mut impl BufRead) -> Result<Received> {
+    let mut received = Received::default();
+
+    while let Some((kind, name, len)) = next_entry(input)? {
+        let (mut hasher, expected) = match kind.as_str() {
+            "object" => (repo.hasher(), name.as_str()),
+            _ => {
+                let (algo, oid) = lfs::parse_id(&name)?;
+                (StreamHasher::new(algo), oid)
+            }
+        };
+
+        if copy(&mut input.by_ref().take(len), &mut hasher)? != len {
+            return Err(anyhow!("Pack stream ended inside {} {}", kind, name));
+        }
+        if hasher.finish() != expected {
+            return Err(anyhow!("The {} {} is corrupt (hash mismatch)", kind, name));
+        }
+        match kind.as_str() {
+            "object" => received.objects += 1,
+            _ => received.lfs += 1,
+        }
+    }
+
+    Ok(received)
+}
+
+/// Reads the header line of the next entry: its kind, name and length
+fn next_entry(input: &mut impl BufRead) -> Result<Option<(String, String, u64)>> {
+    let mut line = String::new();
+    input.by_ref().take(MAX_ENTRY_LINE).read_line(&mut line)?;
+    let malformed = || anyhow!("Malformed pack stream entry: {:?}", line.trim_end());
+
+    let Some(fields) = line.strip_suffix('\n') else {
+        return Err(anyhow!("Pack stream ended early"));
+    };
+    if fields == "end" {
+        return Ok(None);
+    }
+
+    let mut parts = fields.split(' ');
+    let (Some(kind), Some(name), Some(len), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return Err(malformed());
+    };
+    let name_ok = match kind {
+        "object" => name.len() == ZERO_HASH.len() && is_hex(name),
+        "lfs" => lfs::parse_id(name).is_ok(),
+        _ => false,
+    };
+    if !name_ok {
+        return Err(malformed());
+    }
+
+    let len = len.parse().map_err(|_| malformed())?;
+    Ok(Some((kind.to_string(), name.to_string(), len)))
+}
+
+/// Stores one object from a pack stream. Blobs are streamed into the store;
+/// trees and commits are small enough to check before writing.
+fn store_object(repo: &Repo, hash: &str, len: u64, data: &mut impl BufRead) -> Result<()> {
+    let mut header = Vec::new();
+    data.by_ref().take(MAX_OBJECT_HEADER).read_until(0, &mut header)?;
+    let invalid = || anyhow!("Object {} has an invalid header", hash);
+    let text = header.strip_suffix(&[0]).ok_or_else(invalid)?;
+    let (kind, size) = std::str::from_utf8(text)?.split_once(' ').ok_or_else(invalid)?;
+    let size: u64 = size.parse().map_err(|_| invalid())?;
+    if header.len() as u64 + size != len {
+        return Err(invalid());
+    }
+
+    let stored = if kind == "blob" {
+        write_blob_from(repo, Path::new(".ink/objects"), size, data.by_ref().take(size))
+            .map_err(|e| anyhow!("Cannot store object {}: {}", hash, e))?
+    } else {
+        let mut full = header;
+        data.by_ref().take(size).read_to_end(&mut full)?;
+        if repo.hash(&full) != hash {
+            return Err(anyhow!("Object {} is corrupt (hash mismatch)", hash));
+        }
+        parse_raw_object(&full)?;
+        write_raw_object(repo, full)?
+    };
+
+    if stored != hash {
+        return Err(anyhow!("Object {} is corrupt (hash mismatch)", hash));
+    }
+    Ok(())
+}
+
+/// Applies a push read from `input`: the request, then a pack stream
+pub fn receive_pack(repo: &Repo, input: &mut impl BufRead) -> Result<PushResult> {
+    let req: PushRequest = read_message(input)?;
+    read_pack(repo, input)?;
+
+    let results = req
+        .updates
+        .iter()
+        .map(|update| {
+            let outcome = apply_update(repo, update, req.force).err().map(|e| e.to_string());
+            (update.name.clone(), outcome)
+        })
+        .collect();
+
+    Ok(PushResult { results })
+}
+
+fn apply_update(repo: &Repo, update: &RefUpdate, force: bool) -> Result<()> {
+    check_ref_name(&update.name)?;
+    let branch = update
+        .name
+        .strip_prefix("refs/heads/")
+        .ok_or_else(|| anyhow!("only branches can be pushed"))?;
+
+    let current = resolve_ref(&update.name)?.unwrap_or_else(|| ZERO_HASH.to_string());
+    if current != update.old {
+        return Err(anyhow!("stale info (the remote ref has changed; fetch first)"));
+    }
+
+    let deleting = update.new == ZERO_HASH;
+    if !deleting && !object_exists(&update.new) {
+        return Err(anyhow!("missing objects for {}", update.new));
+    }
+    if !force && !deleting && current != ZERO_HASH && !is_ancestor(&current, &update.new)? {
+        return Err(anyhow!("non-fast-forward"));
+    }
+
+    let path = Path::new(".ink").join(&update.name);
+
+    if branch == read_current_branch()? {
+        match get_config("receive.denyCurrentBranch")?.as_deref() {
+            Some("ignore") => {}
+            Some("updateInstead") if !deleting => switch_tree(repo, &current, &update.new)?,
+            _ => return Err(anyhow!("branch is currently checked out")),
+        }
+    }
+
+    if deleting {
+        remove_file(&path)?;
+        remove_empty_parents_up_to(&path, &Path::new(".ink").join("refs").join("heads"))?;
+    } else {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        write(&path, &update.new)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anyhow::Result;
+
+use crate::utils::chunk::load_chunking;
+use crate::utils::enums::{Encoding, HashAlgo};
+use crate::utils::hash::{hash_with, load_hash_algo, StreamHasher};
+use crate::utils::zip::{compress_with, load_compression};
+
+/// The repository settings every object read and write depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repo {
+    pub hash: HashAlgo,
+    pub encoding: Encoding,
+    pub level: Option<i32>,
+    /// Split large blobs into content-defined chunks (`core.chunking`)
+    pub chunking: bool,
+}
+
+impl Repo {
+    /// Reads the settings of the repository in the current directory. Commands
+    /// load them once and pass them to everything that hashes or writes objects.
+    pub fn load() -> Result<Self> {
+        let hash = load_hash_algo()?;
+        let (encoding, level) = load_compression()?;
+        Ok(Repo { hash, encoding, level, chunking: load_chunking()? })
+    }
+
+    pub fn hash(&self, data: &[u8]) -> String {
+        hash_with(self.hash, data)
+    }
+
+    pub fn hasher(&self) -> StreamHasher {
+        StreamHasher::new(self.hash)
+    }
+
+    pub fn compress(&self, content: Vec<u8>) -> Result<Vec<u8>> {
+        compress_with(content, self.encoding, self.level)
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Component, Path};
+use anyhow::{anyhow, Result};
+use walkdir::WalkDir;
+
+use crate::commands::commit::read_current_commit;
+use crate::commands::migrate_hash::HASH_MAP;
+use crate::utils::index::Index;
+use crate::utils::object::{read_commit_object, read_tree_object, ZERO_HASH};
+
+/// Checks that a ref name such as `refs/heads/main` is safe to use as a path
+/// under `.ink`, following the rules of git's check-ref-format
+pub fn check_ref_name(name: &str) -> Result<()> {
+    let invalid = |why: &str| Err(anyhow!("Invalid ref name '{}': {}", name.escape_debug(), why));
+    if name.is_empty() {
+        return invalid("empty name");
+    }
+    if name.starts_with('/') || name.ends_with('/') {
+        return invalid("starts or ends with '/'");
+    }
+    if name.contains("..") || name.contains("@{") {
+        return invalid("contains '..' or '@{'");
+    }
+    if let Some(c) = name.chars().find(|c| c.is_control() || " ~^:?*[\\".contains(*c)) {
+        return invalid(&format!("contains {:?}", c));
+    }
+    for segment in name.split('/') {
+        if segment.is_empty() {
+            return invalid("empty path segment");
+        }
+        if segment.starts_with('.') || segment.ends_with('.') {
+            return invalid("a segment starts or ends with '.'");
+        }
+        if segment.ends_with(".lock") {
+            return invalid("a segment ends with '.lock'");
+        }
+    }
+    Ok(())
+}
+
+/// Resolves a revision such as `HEAD`, `main`, `a1b2c3`, `HEAD~2` or `main^2` to a commit hash
+pub fn resolve_revision(spec: &str) -> Result<String> {
+    let split = spec.find(['^', '~']).unwrap_or(spec.len());
+    let (base, mut suffix) = spec.split_at(split);
+
+    let mut hash = resolve_base(base)?.ok_or_else(|| anyhow!("Unknown revision '{}'", spec))?;
+
+    while let Some(op) = suffix.chars().next() {
+        suffix = &suffix[1..];
+        let digits: String = suffix.chars().take_while(|c| c.is_ascii_digit()).collect();
+        suffix = &suffix[digits.len()..];
+        let n: usize = if digits.is_empty() { 1 } else { digits.parse()? };
+
+        match op {
+            // ~N follows the first parent N times
+            '~' => {
+                for _ in 0..n {
+                    hash = nth_parent(&hash, 1, spec)?;
+                }
+            }
+            // ^N selects the Nth parent; ^0 is the commit itself
+            '^' if n > 0 => hash = nth_parent(&hash, n, spec)?,
+            '^' => {}
+            _ => return Err(anyhow!("Invalid revision '{}'", spec)),
+        }
+    }
+
+    Ok(hash)
+}
+
+/// Resolves a name for any kind of object: a revision, `<rev>^{tree}`,
+/// `<rev>:<path>` for an entry in that commit's tree, or `:<path>` for a staged blob
+pub fn resolve_object(spec: &str) -> Result<String> {
+    if let Some((rev, path)) = spec.split_once(':') {
+        let path = path.trim_matches('/');
+        if rev.is_empty() {
+            return Index::load()?
+                .entries
+                .get(Path::new(path))
+                .map(|e| e.hash.clone())
+                .ok_or_else(|| anyhow!("Path '{}' is not in the index", path));
+        }
+        let tree = read_commit_object(&resolve_revision(rev)?)?.tree;
+        return find_in_tree(&tree, path).map_err(|e| anyhow!("{} (in '{}')", e, rev));
+    }
+
+    if let Some(rev) = spec.strip_suffix("^{tree}") {
+        return Ok(read_commit_object(&resolve_revision(rev)?)?.tree);
+    }
+    if let Some(rev) = spec.strip_suffix("^{commit}") {
+        return resolve_revision(rev);
+    }
+
+    resolve_revision(spec)
+}
+
+/// Follows `path` down from a tree; the empty path is the tree itself
+fn find_in_tree(tree: &str, path: &str) -> Result<String> {
+    let mut hash = tree.to_string();
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        hash = read_tree_object(&hash)?
+            .into_iter()
+            .find(|(name, _, _)| name.as_os_str() == part)
+            .map(|(_, _, hash)| hash)
+            .ok_or_else(|| anyhow!("Path '{}' does not exist", path))?;
+    }
+    Ok(hash)
+}
+
+fn nth_parent(hash: &str, n: usize, spec: &str) -> Result<String> {
+    read_commit_object(hash)?
+        .parents
+        .get(n - 1)
+        .cloned()
+        .ok_or_else(|| anyhow!("Revision '{}' goes past the first commit", spec))
+}
+
+fn resolve_base(base: &str) -> Result<Option<String>> {
+    if base == "HEAD" || base == "@" || base.is_empty() {
+        let head = read_current_commit()?;
+        if head == ZERO_HASH {
+            return Err(anyhow!("HEAD does not point to a commit yet"));
+        }
+        return Ok(Some(head));
+    }
+
+    // FETCH_HEAD lists every fetched tip; the first one is the remote's HEAD
+    if base == "FETCH_HEAD" {
+        let path = Path::new(".ink/FETCH_HEAD");
+        let first = if path.is_file() { read_to_string(path)?.split_whitespace().next().map(String::from) } else { None };
+        return Ok(first);
+    }
+
+    if let Some(hash) = resolve_ref(base)? {
+        return Ok(Some(hash));
+    }
+
+    match find_object_by_prefix(base)? {
+        Some(hash) => Ok(Some(hash)),
+        None => find_migrated(base),
+    }
+}
+
+/// Looks an object name from before `ink migrate-hash` up in the mapping table
+fn find_migrated(prefix: &str) -> Result<Option<String>> {
+    if prefix.len() < 4 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    let Ok(table) = read_to_string(HASH_MAP) else {
+        return Ok(None);
+    };
+
+    let prefix = prefix.to_ascii_lowercase();
+    let mut found = table
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, old)| old.starts_with(&prefix));
+    match (found.next(), found.next()) {
+        (Some((new, _)), None) => Ok(Some(new.to_string())),
+        (Some(_), Some(_)) => Err(anyhow!("Short hash '{}' is ambiguous", prefix)),
+        _ => Ok(None),
+    }
+}
+
+/// Looks a name up under the usual ref namespaces
+pub fn resolve_ref(name: &str) -> Result<Option<String>> {
+    // `..` or an absolute name could reach files outside refs/
+    if !Path::new(name).components().all(|c| matches!(c, Component::Normal(_))) {
+        return Ok(None);
+    }
+
+    let root = Path::new(".ink");
+    let candidates = [
+        root.join(name),
+        root.join("refs").join(name),
+        root.join("refs").join("heads").join(name),
+        root.join("refs").join("tags").join(name),
+        root.join("refs").join("remotes").join(name),
+    ];
+
+    for path in candidates {
+        // Only refs live under refs/; never treat arbitrary .ink files as refs
+        if path.starts_with(root.join("refs")) && path.is_file() {
+            let hash = read_to_string(&path)?.trim().to_string();
+            if !hash.is_empty() {
+                return Ok(Some(hash));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Expands a (possibly abbreviated) hex hash to the unique object it names
+pub fn find_object_by_prefix(prefix: &str) -> Result<Option<String>> {
+    if prefix.len() < 4 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+
+    let prefix = prefix.to_ascii_lowercase();
+    let dir = Path::new(".ink/objects").join(&prefix[..2]);
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+
+    let mut found: Vec<String> = Vec::new();
+    for entry in read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        let full = format!("{}{}", &prefix[..2], name);
+        if full.starts_with(&prefix) {
+            found.push(full);
+        }
+    }
+
+    match found.len() {
+        0 => Ok(None),
+        1 => Ok(found.pop()),
+        _ => Err(anyhow!("Short hash '{}' is ambiguous", prefix)),
+    }
+}
+
+/// Splits leading arguments that name revisions from the pathspecs that follow.
+///
+/// Like git, an argument that is neither a revision nor an existing path (or a
+/// glob/magic pathspec) is rejected; such paths must be given after `--`.
+pub fn split_revisions(args: &[String]) -> Result<(Vec<String>, Vec<String>)> {
+    let count = args
+        .iter()
+        .take_while(|a| resolve_revision(a).is_ok())
+        .count();
+
+    let paths = args[count..].to_vec();
+    for path in &paths {
+        let looks_like_pathspec = path.starts_with(':') || path.contains(['*', '?', '[']);
+        if !looks_like_pathspec && !Path::new(path).exists() {
+            return Err(anyhow!(
+                "Ambiguous argument '{}': unknown revision or path not in the working tree.\nUse '--' to separate paths from revisions.",
+                path
+            ));
+        }
+    }
+
+    Ok((args[..count].to_vec(), paths))
+}
+
+/// Every ref under `prefix` (e.g. `refs/heads`) as `(full name, hash)`, sorted by name.
+/// Refs that do not point to a commit yet are skipped.
+pub fn list_refs(prefix: &str) -> Result<Vec<(String, String)>> {
+    let root = Path::new(".ink");
+    let dir = root.join(prefix);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut refs = Vec::new();
+    for entry in WalkDir::new(&dir).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let hash = read_to_string(entry.path())?.trim().to_string();
+        if hash.is_empty() || hash == ZERO_HASH {
+            continue;
+        }
+
+        let name = entry.path().strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+        refs.push((name, hash));
+    }
+
+    Ok(refs)
+}
+
+/// Short names of the branches, remote-tracking branches and tags pointing at each commit
+pub fn ref_decorations() -> Result<HashMap<String, Vec<String>>> {
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (name, hash) in list_refs("refs/heads")?.into_iter().chain(list_refs("refs/remotes")?) {
+        let short = name
+            .strip_prefix("refs/heads/")
+            .or_else(|| name.strip_prefix("refs/remotes/"))
+            .unwrap_or(&name)
+            .to_string();
+        names.entry(hash).or_default().push(short);
+    }
+    for (name, hash) in list_refs("refs/tags")? {
+        let short = name.strip_prefix("refs/tags/").unwrap_or(&name);
+        names.entry(hash).or_default().push(format!("tag: {}", short));
+    }
+
+    Ok(names)
+}
@@ -0,0 +1,328 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, remove_file, write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+
+use crate::commands::checkout::{get_tree_entries, reset_tree};
+use crate::commands::commit::{read_current_commit, read_tree_of_commit, update_current_commit};
+use crate::commands::rebase;
+use crate::commands::status::collect;
+use crate::utils::editor::strip_comments;
+use crate::utils::history::{reachable_commits, sort_commits, walk_commits, Order};
+use crate::utils::index::Index;
+use crate::utils::log::log_action;
+use crate::utils::merge::{apply_change, is_resolved};
+use crate::utils::object::{create_commit, create_tree, read_commit_object, Commit, ZERO_HASH};
+use crate::utils::pathspec::Pathspec;
+use crate::utils::repo::Repo;
+use crate::utils::revision::resolve_revision;
+use crate::utils::signature::Signature;
+
+/// State of an interrupted cherry-pick or revert
+const DIR: &str = ".ink/sequencer";
+
+/// Message prepared for the commit that is waiting on conflict resolution
+const MERGE_MSG: &str = ".ink/MERGE_MSG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Pick,
+    Revert,
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Action::Pick => "pick",
+            Action::Revert => "revert",
+        }
+    }
+
+    /// The command name, for messages and the reflog
+    pub fn command(self) -> &'static str {
+        match self {
+            Action::Pick => "cherry-pick",
+            Action::Revert => "revert",
+        }
+    }
+}
+
+/// `--continue`, `--skip` or `--abort` for a sequence that stopped on a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Skip,
+    Abort,
+}
+
+/// Commits still to apply (the first one is the current step), the commit HEAD
+/// pointed at before starting, and the paths the current step left in conflict
+struct Sequencer {
+    head: String,
+    todo: Vec<(Action, String)>,
+    conflicts: Vec<PathBuf>,
+}
+
+impl Sequencer {
+    fn load() -> Result<Option<Self>> {
+        let dir = Path::new(DIR);
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+
+        let mut todo = Vec::new();
+        for line in read_to_string(dir.join("todo"))?.lines() {
+            let (action, hash) = line.split_once(' ').ok_or_else(|| anyhow!("Corrupt sequencer line: {}", line))?;
+            let action = match action {
+                "pick" => Action::Pick,
+                "revert" => Action::Revert,
+                other => return Err(anyhow!("Unknown sequencer action '{}'", other)),
+            };
+            todo.push((action, hash.to_string()));
+        }
+
+        let conflicts = match read_to_string(dir.join("conflicts")) {
+            Ok(text) => text.lines().map(PathBuf::from).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Some(Sequencer { head: read_to_string(dir.join("head"))?.trim().to_string(), todo, conflicts }))
+    }
+
+    fn save(&self) -> Result<()> {
+        let dir = Path::new(DIR);
+        create_dir_all(dir)?;
+        write(dir.join("head"), &self.head)?;
+
+        let todo: String = self.todo.iter().map(|(action, hash)| format!("{} {}\n", action.as_str(), hash)).collect();
+        write(dir.join("todo"), todo)?;
+
+        let conflicts: String = self.conflicts.iter().map(|p| format!("{}\n", p.display())).collect();
+        write(dir.join("conflicts"), conflicts)?;
+        Ok(())
+    }
+
+    fn remove() -> Result<()> {
+        if Path::new(DIR).is_dir() {
+            remove_dir_all(DIR)?;
+        }
+        if Path::new(MERGE_MSG).is_file() {
+            remove_file(MERGE_MSG)?;
+        }
+        Ok(())
+    }
+}
+
+/// The step an interrupted sequence stopped at and its unresolved paths, for `ink status`
+pub fn in_progress(repo: &Repo) -> Result<Option<(Action, String, Vec<PathBuf>)>> {
+    let Some(seq) = Sequencer::load()? else {
+        return Ok(None);
+    };
+    let Some((action, hash)) = seq.todo.first().cloned() else {
+        return Ok(None);
+    };
+
+    let index = Index::load()?;
+    let unresolved = seq.conflicts.into_iter().filter(|p| !is_resolved(repo, p, &index)).collect();
+    Ok(Some((action, hash, unresolved)))
+}
+
+/// Starts applying `revs` (commits or `<a>..<b>` ranges) on top of HEAD
+pub fn start(action: Action, revs: &[String]) -> Result<()> {
+    let repo = Repo::load()?;
+    if Sequencer::load()?.is_some() {
+        return Err(anyhow!(
+            "A cherry-pick or revert is already in progress.\n  (use \"ink {} --continue\", \"--skip\" or \"--abort\")",
+            action.command()
+        ));
+    }
+    if rebase::in_progress(&repo)?.is_some() {
+        return Err(anyhow!("A rebase is in progress; finish or abort it first"));
+    }
+
+    let head = read_current_commit()?;
+    if head == ZERO_HASH {
+        return Err(anyhow!("Cannot {} onto a branch without commits", action.command()));
+    }
+    let report = collect(&repo, &Pathspec::default())?;
+    if !report.staged.is_empty() || !report.unstaged.is_empty() {
+        return Err(anyhow!("Your local changes would be overwritten by {}; please commit or stash them first.", action.command()));
+    }
+
+    let mut todo = Vec::new();
+    for rev in revs {
+        todo.extend(expand(rev, action)?.into_iter().map(|hash| (action, hash)));
+    }
+    if todo.is_empty() {
+        return Err(anyhow!("Empty commit set passed"));
+    }
+
+    let mut seq = Sequencer { head, todo, conflicts: Vec::new() };
+    seq.save()?;
+    run_todo(&repo, &mut seq)
+}
+
+/// Resumes, skips or abandons the interrupted sequence
+pub fn resume(control: Control) -> Result<()> {
+    let repo = Repo::load()?;
+    let Some(mut seq) = Sequencer::load()? else {
+        return Err(anyhow!("No cherry-pick or revert in progress"));
+    };
+
+    match control {
+        Control::Abort => {
+            let current = read_current_commit()?;
+            update_current_commit(&seq.head)?;
+            reset_tree(&repo, &seq.head)?;
+            log_action(current, seq.head.clone(), "reset", "moving back after abort")?;
+            Sequencer::remove()?;
+            println!("Aborted; HEAD is back at {}", &seq.head[..7]);
+            return Ok(());
+        }
+        Control::Skip => {
+            reset_tree(&repo, &read_current_commit()?)?;
+        }
+        Control::Continue => {
+            let index = Index::load()?;
+            if let Some(path) = seq.conflicts.iter().find(|p| !is_resolved(&repo, p, &index)) {
+                return Err(anyhow!(
+                    "'{}' still has unresolved changes; fix it and run 'ink add {}' first",
+                    path.display(),
+                    path.display()
+                ));
+            }
+
+            if let Some((action, hash)) = seq.todo.first().cloned() {
+                let message = match read_to_string(MERGE_MSG) {
+                    Ok(text) => strip_comments(&text),
+                    Err(_) => message_for(action, &hash, &read_commit_object(&hash)?),
+                };
+                commit_step(&repo, action, &hash, &message)?;
+            }
+        }
+    }
+
+    if !seq.todo.is_empty() {
+        seq.todo.remove(0);
+    }
+    seq.conflicts.clear();
+    if Path::new(MERGE_MSG).is_file() {
+        remove_file(MERGE_MSG)?;
+    }
+    seq.save()?;
+    run_todo(&repo, &mut seq)
+}
+
+/// Applies steps until the list is done or one stops on a conflict
+fn run_todo(repo: &Repo, seq: &mut Sequencer) -> Result<()> {
+    while let Some((action, hash)) = seq.todo.first().cloned() {
+        let commit = read_commit_object(&hash)?;
+        let subject = commit.message.lines().next().unwrap_or("").to_string();
+
+        let parent = match commit.parents.as_slice() {
+            [] => None,
+            [parent] => Some(parent.clone()),
+            _ => {
+                return Err(anyhow!(
+                    "Commit {} is a merge; cherry-picking or reverting merges is not supported",
+                    &hash[..7]
+                ));
+            }
+        };
+        let parent_files = match &parent {
+            Some(parent) => get_tree_entries(&read_tree_of_commit(parent)?)?,
+            None => HashMap::new(),
+        };
+        let files = get_tree_entries(&commit.tree)?;
+
+        let label = format!("{}... {}", &hash[..7], subject);
+        let conflicts = match action {
+            Action::Pick => apply_change(repo, &parent_files, &files, ("HEAD", &label))?,
+            Action::Revert => apply_change(repo, &files, &parent_files, ("HEAD", &format!("parent of {}", label)))?,
+        };
+
+        let message = message_for(action, &hash, &commit);
+        if !conflicts.is_empty() {
+            for (path, reason) in &conflicts {
+                println!("CONFLICT ({}): {}", reason, path.display());
+            }
+            seq.conflicts = conflicts.into_iter().map(|(path, _)| path).collect();
+            seq.save()?;
+            write(MERGE_MSG, format!("{}\n", message))?;
+
+            return Err(anyhow!(
+                "could not {} {}... {}\nhint: after resolving the conflicts, mark them with \"ink add <paths>\"\nhint: and run \"ink {} --continue\", or use \"--skip\" or \"--abort\"",
+                action.command(),
+                &hash[..7],
+                subject,
+                action.command()
+            ));
+        }
+
+        commit_step(repo, action, &hash, &message)?;
+        seq.todo.remove(0);
+        seq.save()?;
+    }
+
+    Sequencer::remove()
+}
+
+/// Commits the index for a finished step, unless it ended up changing nothing
+fn commit_step(repo: &Repo, action: Action, hash: &str, message: &str) -> Result<()> {
+    let original = read_commit_object(hash)?;
+    let head = read_current_commit()?;
+    let tree = create_tree(repo)?;
+
+    if tree == read_tree_of_commit(&head)? {
+        println!("The {} of {} is empty; skipping it", action.command(), &hash[..7]);
+        return Ok(());
+    }
+
+    // A cherry-pick keeps the original author; a revert is authored by whoever reverts
+    let committer = Signature::committer()?;
+    let author = match action {
+        Action::Pick => original.author,
+        Action::Revert => Signature::author()?,
+    };
+
+    let new = create_commit(repo, &tree, std::slice::from_ref(&head), message, &author, &committer)?;
+    update_current_commit(&new)?;
+
+    let subject = message.lines().next().unwrap_or("");
+    log_action(head, new.clone(), action.command(), subject)?;
+    println!("[{}] {}", &new[..7], subject);
+    Ok(())
+}
+
+/// The message for a step, recording which commit it came from
+fn message_for(action: Action, hash: &str, commit: &Commit) -> String {
+    match action {
+        Action::Pick => format!("{}\n\n(cherry picked from commit {})", commit.message.trim_end(), hash),
+        Action::Revert => format!(
+            "Revert \"{}\"\n\nThis reverts commit {}.",
+            commit.message.lines().next().unwrap_or(""),
+            hash
+        ),
+    }
+}
+
+/// Resolves a revision or `<a>..<b>` range to commits in the order they are applied:
+/// oldest first for picks, newest first for reverts
+fn expand(rev: &str, action: Action) -> Result<Vec<String>> {
+    let Some((from, to)) = rev.split_once("..") else {
+        return Ok(vec![resolve_revision(rev)?]);
+    };
+
+    let excluded: HashSet<String> = reachable_commits(&[resolve_revision(if from.is_empty() { "HEAD" } else { from })?])?;
+    let tip = resolve_revision(if to.is_empty() { "HEAD" } else { to })?;
+    let mut commits: Vec<String> = sort_commits(walk_commits(&[tip])?, Order::Topo)
+        .into_iter()
+        .map(|(hash, _)| hash)
+        .filter(|hash| !excluded.contains(hash))
+        .collect();
+
+    if action == Action::Pick {
+        commits.reverse();
+    }
+    Ok(commits)
+}
@@ -0,0 +1,252 @@
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::sync::Once;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone};
+
+use crate::utils::config::{get_config, read_config_file};
+
+/// Where the author's name and email were configured before `user.name`/`user.email`
+const LEGACY_CONFIG: &str = ".ink/.inkconfig";
+
+/// An identity plus a point in time, as recorded on `author`/`committer` lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    pub timestamp: i64,
+    /// Offset from UTC in seconds (east positive)
+    pub offset: i32,
+}
+
+impl Signature {
+    /// Resolves the author from `INK_AUTHOR_*` env vars, then `user.name`/`user.email` config
+    pub fn author() -> Result<Self> {
+        Self::resolve("AUTHOR", None)
+    }
+
+    /// Like [`Signature::author`], but with the identity given as `Name <email>`
+    pub fn author_as(ident: &str) -> Result<Self> {
+        Self::resolve("AUTHOR", Some(ident))
+    }
+
+    /// Resolves the committer from `INK_COMMITTER_*` env vars, then `user.name`/`user.email` config
+    pub fn committer() -> Result<Self> {
+        Self::resolve("COMMITTER", None)
+    }
+
+    fn resolve(role: &str, ident: Option<&str>) -> Result<Self> {
+        let (name, email) = match ident {
+            Some(ident) => parse_identity(ident)?,
+            None => Self::identity(role)?,
+        };
+
+        let mut sig = Signature::now(name, email);
+        if let Ok(date) = env::var(format!("INK_{}_DATE", role)) {
+            sig = sig.with_date(&date)?;
+        }
+
+        Ok(sig)
+    }
+
+    fn identity(role: &str) -> Result<(String, String)> {
+        let mut name = env_or_config(&format!("INK_{}_NAME", role), "user.name")?;
+        let mut email = env_or_config(&format!("INK_{}_EMAIL", role), "user.email")?;
+
+        // Repositories from before user.name/user.email kept `author=`/`email=` here
+        let legacy = Path::new(LEGACY_CONFIG);
+        if name.is_none()
+            && let Some(old) = read_config_file(legacy, "author")?
+        {
+            legacy_hint();
+            name = Some(old);
+        }
+        if email.is_none()
+            && let Some(old) = read_config_file(legacy, "email")?
+        {
+            legacy_hint();
+            email = Some(old);
+        }
+
+        match (name, email) {
+            (Some(n), Some(e)) => Ok((n, e)),
+            _ => Err(anyhow!(
+                "No identity configured. Run\n\n  ink config --global user.name \"Your Name\"\n  ink config --global user.email \"you@example.com\"\n\nor set INK_{0}_NAME and INK_{0}_EMAIL.",
+                role
+            )),
+        }
+    }
+
+    pub fn now(name: String, email: String) -> Self {
+        let now = Local::now();
+        Signature {
+            name,
+            email,
+            timestamp: now.timestamp(),
+            offset: now.offset().fix().local_minus_utc(),
+        }
+    }
+
+    /// Replaces the time of this signature with a user supplied date
+    pub fn with_date(mut self, date: &str) -> Result<Self> {
+        let (timestamp, offset) = parse_date(date)?;
+        self.timestamp = timestamp;
+        self.offset = offset;
+        Ok(self)
+    }
+
+    /// Parses the stored form `Name <email> <timestamp> <+hhmm>`
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parts = s.rsplitn(3, ' ');
+        let tz = parts.next().ok_or_else(|| anyhow!("Malformed signature: {}", s))?;
+        let ts = parts.next().ok_or_else(|| anyhow!("Malformed signature: {}", s))?;
+        let ident = parts.next().ok_or_else(|| anyhow!("Malformed signature: {}", s))?;
+
+        // Older commits were written without an email
+        let (name, email) = parse_identity(ident).unwrap_or_else(|_| (ident.to_string(), String::new()));
+
+        Ok(Signature {
+            name,
+            email,
+            timestamp: ts.parse().map_err(|_| anyhow!("Malformed timestamp: {}", ts))?,
+            offset: parse_offset(tz)?,
+        })
+    }
+
+    pub fn datetime(&self) -> DateTime<FixedOffset> {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let tz = FixedOffset::east_opt(self.offset).unwrap_or(utc);
+        tz.timestamp_opt(self.timestamp, 0).single().unwrap_or_else(|| utc.timestamp_opt(0, 0).unwrap())
+    }
+
+    pub fn offset_string(&self) -> String {
+        format_offset(self.offset)
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}> {} {}", self.name, self.email, self.timestamp, self.offset_string())
+    }
+}
+
+/// Points users of the old identity file at the config keys that replace it, once per run
+fn legacy_hint() {
+    static HINT: Once = Once::new();
+    HINT.call_once(|| {
+        eprintln!(
+            "hint: {} is deprecated; move its author=/email= to 'ink config user.name' and 'ink config user.email'",
+            LEGACY_CONFIG
+        );
+    });
+}
+
+fn env_or_config(var: &str, key: &str) -> Result<Option<String>> {
+    match env::var(var) {
+        Ok(v) if !v.trim().is_empty() => Ok(Some(v.trim().to_string())),
+        _ => get_config(key),
+    }
+}
+
+/// Splits `Name <email>` into its parts
+pub fn parse_identity(ident: &str) -> Result<(String, String)> {
+    let open = ident.find('<');
+    let close = ident.rfind('>');
+
+    match (open, close) {
+        (Some(o), Some(c)) if o < c => {
+            let name = ident[..o].trim().to_string();
+            let email = ident[o + 1..c].trim().to_string();
+            if name.is_empty() {
+                return Err(anyhow!("Missing name in identity '{}'", ident));
+            }
+            Ok((name, email))
+        }
+        _ => Err(anyhow!("Identity '{}' is not of the form 'Name <email>'", ident)),
+    }
+}
+
+pub fn format_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let abs = offset.abs();
+    format!("{}{:02}{:02}", sign, abs / 3600, (abs % 3600) / 60)
+}
+
+pub fn parse_offset(tz: &str) -> Result<i32> {
+    let bad = || anyhow!("Malformed timezone offset: {}", tz);
+    let (sign, digits) = match tz.as_bytes().first() {
+        Some(b'+') => (1, &tz[1..]),
+        Some(b'-') => (-1, &tz[1..]),
+        _ => return Err(bad()),
+    };
+
+    // Checked before slicing, which would panic inside a multi-byte character
+    let digits = digits.replace(':', "");
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(bad());
+    }
+
+    let hours: i32 = digits[..2].parse().map_err(|_| bad())?;
+    let minutes: i32 = digits[2..].parse().map_err(|_| bad())?;
+    Ok(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parses `--date` style input into a unix timestamp and UTC offset.
+///
+/// Accepts `<unix> <+hhmm>` (optionally prefixed with `@`), RFC 3339, RFC 2822,
+/// and local `YYYY-MM-DD[ HH:MM[:SS]]`.
+pub fn parse_date(input: &str) -> Result<(i64, i32)> {
+    let input = input.trim();
+    let raw = input.strip_prefix('@').unwrap_or(input);
+
+    if let Some((ts, tz)) = raw.split_once(' ')
+        && let Ok(ts) = ts.parse::<i64>()
+    {
+        return Ok((ts, parse_offset(tz.trim())?));
+    }
+
+    if let Ok(ts) = raw.parse::<i64>() {
+        return Ok((ts, local_offset_at(ts)));
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok((dt.timestamp(), dt.offset().local_minus_utc()));
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc2822(input) {
+        return Ok((dt.timestamp(), dt.offset().local_minus_utc()));
+    }
+
+    for fmt in ["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%dT%H:%M:%S%z"] {
+        if let Ok(dt) = DateTime::parse_from_str(input, fmt) {
+            return Ok((dt.timestamp(), dt.offset().local_minus_utc()));
+        }
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(input, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        });
+
+    if let Some(naive) = naive
+        && let Some(dt) = Local.from_local_datetime(&naive).earliest()
+    {
+        return Ok((dt.timestamp(), dt.offset().fix().local_minus_utc()));
+    }
+
+    Err(anyhow!("Unrecognized date format: '{}'", input))
+}
+
+/// The local UTC offset in effect at the given instant (accounts for DST)
+fn local_offset_at(timestamp: i64) -> i32 {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|dt| dt.offset().fix().local_minus_utc())
+        .unwrap_or(0)
+}
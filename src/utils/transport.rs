@@ -0,0 +1,245 @@
+use std::env::current_exe;
+use std::fs::{remove_file, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use anyhow::{anyhow, Result};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::RequestBuilder;
+use tokio::runtime::Runtime;
+
+use crate::utils::bundle::{Bundle, BundleTransport};
+use crate::utils::config::get_config;
+use crate::utils::remote::{
+    decode, encode, read_message, read_pack, write_message, write_pack, Advertisement, FetchRequest, PushRequest,
+    PushResult, Received,
+};
+use crate::utils::repo::Repo;
+
+/// A way of talking to another repository. Objects and large files travel as
+/// pack streams (see `remote::write_pack`) and are stored as they arrive.
+pub trait Transport {
+    fn advertise(&mut self) -> Result<Advertisement>;
+    fn fetch(&mut self, repo: &Repo, req: &FetchRequest) -> Result<Received>;
+    /// Sends `objects` and the large file contents `lfs` along with the ref updates
+    fn push(&mut self, req: &PushRequest, objects: &[String], lfs: &[String]) -> Result<PushResult>;
+    /// Large file contents by id (`<algo>:<oid>`); ones the other side lacks are left out
+    fn fetch_lfs(&mut self, repo: &Repo, ids: &[String]) -> Result<Received>;
+}
+
+/// Opens a transport for a URL, a local repository path or a bundle file
+pub fn connect(url: &str) -> Result<Box<dyn Transport>> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return Ok(Box::new(HttpTransport::new(url)?));
+    }
+
+    let path = Path::new(url.strip_prefix("file://").unwrap_or(url));
+    if Bundle::is_bundle(path) {
+        return Ok(Box::new(BundleTransport::new(path)?));
+    }
+
+    Ok(Box::new(LocalTransport::new(url)?))
+}
+
+/// The URL configured for a named remote, or `name` itself if it looks like a URL or path
+pub fn remote_url(name: &str) -> Result<String> {
+    if let Some(url) = get_config(&format!("remote.{}.url", name))? {
+        return Ok(url);
+    }
+
+    if name.contains("://") || Path::new(name).join(".ink").is_dir() || Bundle::is_bundle(Path::new(name)) {
+        return Ok(name.to_string());
+    }
+
+    Err(anyhow!("'{}' does not appear to be an ink remote", name))
+}
+
+/// Talks to a repository on this machine by running `ink upload-pack` and
+/// `ink receive-pack` inside it, exchanging messages over stdin/stdout
+pub struct LocalTransport {
+    root: PathBuf,
+}
+
+impl LocalTransport {
+    pub fn new(url: &str) -> Result<Self> {
+        let root = PathBuf::from(url.strip_prefix("file://").unwrap_or(url));
+        if !root.join(".ink").is_dir() {
+            return Err(anyhow!("'{}' does not appear to be an ink repository", url));
+        }
+        Ok(LocalTransport { root })
+    }
+
+    /// Runs an ink service in the repository, writing its input with `send`
+    /// while `receive` consumes its output
+    fn call<T>(
+        &self,
+        args: &[&str],
+        send: impl FnOnce(&mut BufWriter<ChildStdin>) -> Result<()>,
+        receive: impl FnOnce(&mut BufReader<ChildStdout>) -> Result<T>,
+    ) -> Result<T> {
+        let mut child = Command::new(current_exe()?)
+            .args(args)
+            .current_dir(&self.root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Drained on the side, so a chatty service cannot block on a full stderr pipe
+        let mut stderr = child.stderr.take().unwrap();
+        let errors = thread::spawn(move || {
+            let mut text = String::new();
+            stderr.read_to_string(&mut text).ok();
+            text
+        });
+
+        let mut stdin = BufWriter::new(child.stdin.take().unwrap());
+        let sent = send(&mut stdin).and_then(|_| Ok(stdin.flush()?));
+        drop(stdin);
+        let received = sent.and_then(|_| receive(&mut BufReader::new(child.stdout.take().unwrap())));
+
+        let status = child.wait()?;
+        if !status.success() {
+            let stderr = errors.join().unwrap_or_default();
+            return Err(anyhow!("Remote {} failed: {}", args[0], stderr.trim()));
+        }
+        received
+    }
+}
+
+impl Transport for LocalTransport {
+    fn advertise(&mut self) -> Result<Advertisement> {
+        self.call(&["upload-pack", "--advertise"], |_| Ok(()), |out| {
+            let mut bytes = Vec::new();
+            out.read_to_end(&mut bytes)?;
+            decode(&bytes)
+        })
+    }
+
+    fn fetch(&mut self, repo: &Repo, req: &FetchRequest) -> Result<Received> {
+        self.call(&["upload-pack"], |input| Ok(input.write_all(&encode(req)?)?), |out| read_pack(repo, out))
+    }
+
+    fn push(&mut self, req: &PushRequest, objects: &[String], lfs: &[String]) -> Result<PushResult> {
+        let send = |input: &mut BufWriter<ChildStdin>| {
+            write_message(input, req)?;
+            write_pack(input, objects, lfs)
+        };
+        self.call(&["receive-pack"], send, read_message)
+    }
+
+    fn fetch_lfs(&mut self, repo: &Repo, ids: &[String]) -> Result<Received> {
+        let send = |input: &mut BufWriter<ChildStdin>| Ok(input.write_all(&encode(&ids)?)?);
+        self.call(&["upload-pack", "--lfs"], send, |out| read_pack(repo, out))
+    }
+}
+
+/// Talks to an `ink serve` instance over HTTP
+pub struct HttpTransport {
+    base: String,
+    client: reqwest::Client,
+    runtime: Runtime,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(HttpTransport {
+            base: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            runtime: Runtime::new()?,
+        })
+    }
+
+    fn post(&self, endpoint: &str) -> RequestBuilder {
+        self.client
+            .post(format!("{}/{}", self.base, endpoint))
+            .header(CONTENT_TYPE, "application/octet-stream")
+    }
+
+    /// Sends a request and copies the response into `out` as it arrives
+    fn call(&self, endpoint: &str, request: RequestBuilder, out: &mut impl Write) -> Result<()> {
+        self.runtime.block_on(async {
+            let mut response = request.send().await.map_err(|e| anyhow!("Cannot reach {}: {}", self.base, e))?;
+            let status = response.status();
+            if !status.is_success() {
+                let text = response.text().await?;
+                return Err(anyhow!("Remote {} failed ({}): {}", endpoint, status, text));
+            }
+
+            while let Some(chunk) = response.chunk().await? {
+                out.write_all(&chunk)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Posts a request whose answer is a pack stream. The stream is spooled to
+    /// disk first so that storing it never holds up the runtime.
+    fn fetch_pack(&self, repo: &Repo, endpoint: &str, body: Vec<u8>) -> Result<Received> {
+        let spool = Spool::new();
+        let mut file = BufWriter::new(File::create(&spool.path)?);
+        self.call(endpoint, self.post(endpoint).body(body), &mut file)?;
+        file.flush()?;
+        drop(file);
+
+        read_pack(repo, &mut BufReader::new(File::open(&spool.path)?))
+    }
+}
+
+impl Transport for HttpTransport {
+    fn advertise(&mut self) -> Result<Advertisement> {
+        let mut body = Vec::new();
+        self.call("info/refs", self.client.get(format!("{}/info/refs", self.base)), &mut body)?;
+        decode(&body)
+    }
+
+    fn fetch(&mut self, repo: &Repo, req: &FetchRequest) -> Result<Received> {
+        self.fetch_pack(repo, "upload-pack", encode(req)?)
+    }
+
+    /// The push is written to disk first, since the server needs its length up front
+    fn push(&mut self, req: &PushRequest, objects: &[String], lfs: &[String]) -> Result<PushResult> {
+        let spool = Spool::new();
+        let mut file = BufWriter::new(File::create(&spool.path)?);
+        write_message(&mut file, req)?;
+        write_pack(&mut file, objects, lfs)?;
+        file.flush()?;
+        drop(file);
+
+        let file = File::open(&spool.path)?;
+        let len = file.metadata()?.len();
+        let request = self
+            .post("receive-pack")
+            .header(CONTENT_LENGTH, len)
+            .body(reqwest::Body::from(tokio::fs::File::from_std(file)));
+
+        let mut body = Vec::new();
+        self.call("receive-pack", request, &mut body)?;
+        decode(&body)
+    }
+
+    fn fetch_lfs(&mut self, repo: &Repo, ids: &[String]) -> Result<Received> {
+        self.fetch_pack(repo, "lfs-objects", encode(&ids)?)
+    }
+}
+
+/// A temporary file under `.ink` holding a pack stream, removed when dropped
+struct Spool {
+    path: PathBuf,
+}
+
+impl Spool {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("spool-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed));
+        Spool { path: Path::new(".ink").join(name) }
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        remove_file(&self.path).ok();
+    }
+}
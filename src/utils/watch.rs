@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, read_to_string, remove_file, write};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+
+use crate::utils::dir::{is_in_ink, list_files_under, list_worktree_files};
+use crate::utils::ignore::is_ignored;
+use crate::utils::index::Index;
+use crate::utils::object::hash_file;
+use crate::utils::repo::Repo;
+
+/// Where a running `ink watch` advertises itself, as `<port> <pid>`
+const WATCH_FILE: &str = ".ink/watch";
+
+/// Files the daemon creates to make sure it has seen every earlier event
+const COOKIE_DIR: &str = ".ink/watch-cookies";
+
+/// Paths that may differ from the index, kept up to date by file system events
+pub struct WatchState {
+    repo: Repo,
+    dirty: HashSet<PathBuf>,
+    /// Index as of the last refresh, to notice entries changed by ink itself
+    index: HashMap<PathBuf, String>,
+    cookies: HashSet<String>,
+}
+
+/// The state plus a condition variable signalled whenever a cookie arrives
+pub type Shared = Arc<(Mutex<WatchState>, Condvar)>;
+
+impl WatchState {
+    /// Starts from a full scan: every modified, deleted or untracked path is dirty
+    pub fn scan(repo: Repo) -> Result<Self> {
+        let mut state = WatchState {
+            repo,
+            dirty: HashSet::new(),
+            index: HashMap::new(),
+            cookies: HashSet::new(),
+        };
+
+        state.dirty.extend(list_worktree_files());
+        state.dirty.extend(Index::load()?.entries.into_keys());
+        state.refresh()?;
+        Ok(state)
+    }
+
+    /// Records a changed path; directories contribute every file below them
+    pub fn mark(&mut self, path: &Path) {
+        if path.is_dir() {
+            self.dirty.extend(list_files_under(path));
+        } else {
+            self.dirty.insert(path.to_path_buf());
+        }
+    }
+
+    /// Picks up index changes made since the last refresh and drops dirty paths
+    /// that turned out to match the index after all
+    pub fn refresh(&mut self) -> Result<()> {
+        let index: HashMap<PathBuf, String> = Index::load()?
+            .entries
+            .into_iter()
+            .map(|(path, entry)| (path, entry.hash))
+            .collect();
+
+        // A clean file becomes dirty when its index entry changes under it
+        for path in index.keys().chain(self.index.keys()) {
+            if index.get(path) != self.index.get(path) {
+                self.dirty.insert(path.clone());
+            }
+        }
+        self.index = index;
+
+        let (repo, index) = (&self.repo, &self.index);
+        self.dirty.retain(|path| match (index.get(path), path.is_file()) {
+            (Some(hash), true) => hash_file(repo, path).ok().as_ref() != Some(hash),
+            (Some(_), false) => true,
+            (None, true) => !is_ignored(path),
+            (None, false) => false,
+        });
+
+        Ok(())
+    }
+
+    pub fn dirty(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.dirty.iter().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    pub fn add_cookie(&mut self, name: String) {
+        self.cookies.insert(name);
+    }
+}
+
+/// Whether an event for `path` (relative to the root) belongs to the working tree
+pub fn is_watched(path: &Path) -> bool {
+    !is_in_ink(path) && !is_ignored(path)
+}
+
+/// If `path` is one of the daemon's cookie files, its name
+pub fn cookie_name(path: &Path) -> Option<String> {
+    path.strip_prefix(COOKIE_DIR).ok().map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Answers status queries on a localhost port until asked to stop. Returns the address.
+pub fn serve(shared: Shared) -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    create_dir_all(COOKIE_DIR)?;
+    write(WATCH_FILE, format!("{} {}", addr.port(), std::process::id()))?;
+
+    thread::spawn(move || {
+        let mut next_cookie = 0u64;
+        for stream in listener.incoming().flatten() {
+            next_cookie += 1;
+            if let Err(e) = answer(stream, &shared, next_cookie) {
+                eprintln!("watch: {}", e);
+            }
+        }
+    });
+
+    Ok(addr)
+}
+
+fn answer(mut stream: TcpStream, shared: &Shared, cookie: u64) -> Result<()> {
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+
+    match request.trim() {
+        "dirty" => {
+            let paths = {
+                let mut state = wait_for_cookie(shared, cookie)?;
+                state.refresh()?;
+                state.dirty()
+            };
+
+            let mut reply = String::new();
+            for path in paths {
+                reply.push_str(&path.to_string_lossy());
+                reply.push('\n');
+            }
+            stream.write_all(reply.as_bytes())?;
+        }
+        "stop" => {
+            stream.write_all(b"ok\n")?;
+            shutdown();
+        }
+        // A bare connection just checks that the daemon is alive
+        "" => {}
+        other => return Err(anyhow!("unknown request '{}'", other)),
+    }
+
+    Ok(())
+}
+
+/// Writes a cookie file and waits until its event comes back, so every change
+/// made before the query has been recorded
+fn wait_for_cookie(shared: &Shared, cookie: u64) -> Result<std::sync::MutexGuard<'_, WatchState>> {
+    let (lock, cvar) = &**shared;
+    let name = cookie.to_string();
+    let path = Path::new(COOKIE_DIR).join(&name);
+    write(&path, "")?;
+
+    let state = lock.lock().unwrap();
+    let (mut state, _) = cvar
+        .wait_timeout_while(state, Duration::from_secs(1), |s| !s.cookies.contains(&name))
+        .unwrap();
+
+    state.cookies.remove(&name);
+    remove_file(path).ok();
+    Ok(state)
+}
+
+/// Removes the advertisement and exits the daemon
+pub fn shutdown() -> ! {
+    remove_file(WATCH_FILE).ok();
+    std::fs::remove_dir_all(COOKIE_DIR).ok();
+    std::process::exit(0)
+}
+
+fn connect() -> Option<(TcpStream, u32)> {
+    let contents = read_to_string(WATCH_FILE).ok()?;
+    let mut parts = contents.split_whitespace();
+    let port: u16 = parts.next()?.parse().ok()?;
+    let pid: u32 = parts.next()?.parse().ok()?;
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let stream = TcpStream::connect_timeout(&addr, Duration::from_millis(200)).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
+    Some((stream, pid))
+}
+
+/// Pid of the running daemon, if there is one
+pub fn running() -> Option<u32> {
+    connect().map(|(_, pid)| pid)
+}
+
+/// Asks a running daemon which paths may differ from the index.
+/// `None` means no daemon is available and callers should scan the tree.
+pub fn query_dirty() -> Option<Vec<PathBuf>> {
+    let (mut stream, _) = connect()?;
+    stream.write_all(b"dirty\n").ok()?;
+
+    let mut paths = Vec::new();
+    for line in BufReader::new(stream).lines() {
+        paths.push(PathBuf::from(line.ok()?));
+    }
+    Some(paths)
+}
+
+pub fn stop() -> bool {
+    let Some((mut stream, _)) = connect() else {
+        return false;
+    };
+    let mut reply = String::new();
+    stream.write_all(b"stop\n").is_ok() && BufReader::new(stream).read_line(&mut reply).is_ok()
+}
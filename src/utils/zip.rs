@@ -1,79 +1,176 @@
 use flate2::write::{ZlibEncoder, ZlibDecoder};
 use flate2::Compression;
-use std::fs::{read_to_string, write};
-use std::io::Write;
+use std::fs::{read_to_string, File};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::Path;
+use std::str::FromStr;
 use anyhow::{Result, anyhow};
 
-/// Compresses the input using zlib (if zip is enabled), or stores raw
-pub fn compress(content: Vec<u8>) -> Result<Vec<u8>> {
-    let zip = load_is_zip()?; // Default to true if config unreadable
+use crate::utils::chunk;
+use crate::utils::config::{get_config, write_config_file};
+use crate::utils::enums::Encoding;
 
-    if zip {
-        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(&content)?;
-        Ok(encoder.finish()?)
-    } else {
-        Ok(content)
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compresses with an explicit encoding; `level` of `None` uses the encoder's default
+pub fn compress_with(content: Vec<u8>, encoding: Encoding, level: Option<i32>) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::None => Ok(content),
+        Encoding::Zlib => {
+            let level = level.map_or(Compression::default(), |l| Compression::new(l as u32));
+            let mut encoder = ZlibEncoder::new(Vec::new(), level);
+            encoder.write_all(&content)?;
+            Ok(encoder.finish()?)
+        }
+        Encoding::Zstd => Ok(zstd::encode_all(content.as_slice(), level.unwrap_or(0))?),
     }
 }
 
-/// Decompresses zlib-compressed blob or returns raw if zip is disabled
+/// Decompresses an object whatever encoding it was stored with, reassembling chunked ones
 pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>> {
-    let zip = load_is_zip()?; // Default to true if config unreadable
-
-    if zip {
-        let mut decoder = ZlibDecoder::new(Vec::new());
-        decoder.write_all(&data)?;
-        Ok(decoder.finish()?)
-    } else {
-        Ok(data)
+    if chunk::is_manifest(&data) {
+        return chunk::reassemble(&data);
+    }
+    match detect(&data)? {
+        Encoding::None => Ok(data),
+        Encoding::Zlib => {
+            let mut decoder = ZlibDecoder::new(Vec::new());
+            decoder.write_all(&data)?;
+            Ok(decoder.finish()?)
+        }
+        Encoding::Zstd => Ok(zstd::decode_all(data.as_slice())?),
     }
 }
 
-pub fn load_is_zip() -> Result<bool> {
-    let path = Path::new(".ink/config");
-    let contents = read_to_string(path)?;
+/// Compresses into a file as data is written, for objects too large to hold in memory
+pub enum ObjectWriter {
+    Plain(File),
+    Zlib(ZlibEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
 
-    for line in contents.lines() {
-        if let Some((key, value)) = line.split_once('=') {
-            if key.trim() == "zip" {
-                return Ok(string_to_bool(value.trim())?);
+impl ObjectWriter {
+    pub fn new(file: File, encoding: Encoding, level: Option<i32>) -> Result<Self> {
+        Ok(match encoding {
+            Encoding::None => ObjectWriter::Plain(file),
+            Encoding::Zlib => {
+                let level = level.map_or(Compression::default(), |l| Compression::new(l as u32));
+                ObjectWriter::Zlib(ZlibEncoder::new(file, level))
             }
+            Encoding::Zstd => ObjectWriter::Zstd(zstd::Encoder::new(file, level.unwrap_or(0))?),
+        })
+    }
+
+    /// Flushes the compressor's trailer and returns the underlying file
+    pub fn finish(self) -> Result<File> {
+        let mut file = match self {
+            ObjectWriter::Plain(file) => file,
+            ObjectWriter::Zlib(encoder) => encoder.finish()?,
+            ObjectWriter::Zstd(encoder) => encoder.finish()?,
+        };
+        file.flush()?;
+        Ok(file)
+    }
+}
+
+impl Write for ObjectWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            ObjectWriter::Plain(file) => file.write(buf),
+            ObjectWriter::Zlib(encoder) => encoder.write(buf),
+            ObjectWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            ObjectWriter::Plain(file) => file.flush(),
+            ObjectWriter::Zlib(encoder) => encoder.flush(),
+            ObjectWriter::Zstd(encoder) => encoder.flush(),
         }
     }
-    Ok(false)
 }
 
-pub fn save_is_zip(is_zip: bool) -> Result<()> {
-    let path = Path::new(".ink/config");
+/// Decompresses a stored object as it is read, detecting its encoding from the first bytes
+pub fn decompress_reader(file: File) -> Result<Box<dyn Read>> {
+    let mut reader = BufReader::new(file);
+    if chunk::is_manifest(reader.fill_buf()?) {
+        let mut manifest = Vec::new();
+        reader.read_to_end(&mut manifest)?;
+        return chunk::reader(&manifest);
+    }
+    let encoding = detect(reader.fill_buf()?)?;
+    Ok(match encoding {
+        Encoding::None => Box::new(reader),
+        Encoding::Zlib => Box::new(flate2::bufread::ZlibDecoder::new(reader)),
+        Encoding::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+    })
+}
+
+/// Tells the encoding of a stored object from its first bytes. Uncompressed
+/// objects start with their type name, which neither a zlib header nor the
+/// zstd magic number can be mistaken for; chunk files start with `chunk`.
+pub fn detect(data: &[u8]) -> Result<Encoding> {
+    if data.starts_with(&ZSTD_MAGIC) {
+        return Ok(Encoding::Zstd);
+    }
+    if let [cmf, flg, ..] = data
+        && cmf & 0x0f == 8
+        && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0
+    {
+        return Ok(Encoding::Zlib);
+    }
+    if ["blob ", "tree ", "commit ", "chunk "].iter().any(|kind| data.starts_with(kind.as_bytes())) {
+        return Ok(Encoding::None);
+    }
+    Err(anyhow!("unrecognised object encoding"))
+}
 
-    let mut lines = if path.exists() {
-        read_to_string(path)?
-            .lines()
-            .map(|l| l.to_string())
-            .collect::<Vec<_>>()
-    } else {
-        Vec::new()
+/// The encoding and level new objects are written with: `core.compression` and
+/// `core.compressionLevel`, falling back to the `zip` flag chosen at init
+pub fn load_compression() -> Result<(Encoding, Option<i32>)> {
+    let encoding = match get_config("core.compression")? {
+        Some(name) => Encoding::from_str(&name)?,
+        None if load_is_zip()? => Encoding::Zlib,
+        None => Encoding::None,
     };
 
-    let mut found = false;
-    for line in lines.iter_mut() {
-        if line.trim_start().starts_with("zip=") {
-            *line = format!("zip={}", is_zip.to_string());
-            found = true;
-            break;
-        }
+    let level = match get_config("core.compressionLevel")? {
+        Some(level) => Some(level.parse::<i32>().map_err(|_| anyhow!("Invalid core.compressionLevel: {}", level))?),
+        None => None,
+    };
+    let valid = match (encoding, level) {
+        (Encoding::Zlib, Some(level)) => (0..=9).contains(&level),
+        (Encoding::Zstd, Some(level)) => (1..=22).contains(&level),
+        _ => true,
+    };
+    if !valid {
+        return Err(anyhow!(
+            "core.compressionLevel {} is out of range for {} (zlib takes 0-9, zstd 1-22)",
+            level.unwrap_or_default(),
+            encoding
+        ));
     }
 
-    if !found {
-        lines.push(format!("zip={}", is_zip.to_string()));
-    }
+    Ok((encoding, level))
+}
 
-    let output = lines.join("\n") + "\n";
-    write(path, output)?; // .as_bytes()
+pub fn load_is_zip() -> Result<bool> {
+    let path = Path::new(".ink/config");
+    let contents = read_to_string(path)?;
+
+    for line in contents.lines() {
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim() == "zip"
+        {
+            return string_to_bool(value.trim());
+        }
+    }
+    Ok(false)
+}
 
-    Ok(())
+pub fn save_is_zip(is_zip: bool) -> Result<()> {
+    write_config_file(Path::new(".ink/config"), "zip", &is_zip.to_string())
 }
 
 pub fn string_to_bool(input: &str) -> Result<bool> {
@@ -0,0 +1,104 @@
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+/// Runs `ink add -p` answering the prompts with `answers`, one per line
+fn add_patch(dir: &Path, answers: &str) -> String {
+    let mut child = ink_command(dir)
+        .args(["add", "-p"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(answers.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The staged content of `path`
+fn staged(dir: &Path, path: &str) -> String {
+    let entries = ink_stdout(dir, &["ls-files", "-s", path]);
+    let hash = entries.split_whitespace().nth(1).unwrap();
+    ink_stdout(dir, &["cat-file", "blob", hash])
+}
+
+/// Lines 1 to `n`, with the given line numbers replaced
+fn numbered(n: u32, edits: &[(u32, &str)]) -> String {
+    (1..=n)
+        .map(|i| match edits.iter().find(|(at, _)| *at == i) {
+            Some((_, text)) => format!("{}\n", text),
+            None => format!("{}\n", i),
+        })
+        .collect()
+}
+
+#[test]
+fn split_hunks_are_staged_separately() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    fs::write(dir.join("f.txt"), numbered(10, &[])).unwrap();
+    ink(dir, &["add", "f.txt"]);
+    ink(dir, &["commit", "-m", "numbers"]);
+
+    // Both edits fall in one hunk until it is split
+    let edited = numbered(10, &[(2, "two"), (5, "five")]);
+    fs::write(dir.join("f.txt"), &edited).unwrap();
+
+    let out = add_patch(dir, "s\ny\nn\n");
+    assert!(out.contains("Split into 2 hunks"), "{}", out);
+    assert_eq!(staged(dir, "f.txt"), numbered(10, &[(2, "two")]));
+    assert_eq!(fs::read_to_string(dir.join("f.txt")).unwrap(), edited);
+
+    // The remaining hunk is offered again next time
+    add_patch(dir, "y\n");
+    assert_eq!(staged(dir, "f.txt"), edited);
+}
+
+#[test]
+fn quitting_keeps_earlier_answers() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    ink(dir, &["add", "a.txt", "b.txt"]);
+    ink(dir, &["commit", "-m", "two files"]);
+
+    fs::write(dir.join("a.txt"), "A\n").unwrap();
+    fs::write(dir.join("b.txt"), "B\n").unwrap();
+    add_patch(dir, "y\nq\n");
+
+    assert_eq!(staged(dir, "a.txt"), "A\n");
+    assert_eq!(staged(dir, "b.txt"), "b\n");
+}
+
+#[test]
+fn partially_staged_large_files_are_stored_as_pointers() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["lfs", "track", "*.big"]);
+    fs::write(dir.join("f.big"), numbered(20, &[])).unwrap();
+    ink(dir, &["add", ".inklfs", "f.big"]);
+    ink(dir, &["commit", "-m", "large file"]);
+
+    // Hunks are made against the stored content, not the pointer text
+    fs::write(dir.join("f.big"), numbered(20, &[(2, "two"), (18, "eighteen")])).unwrap();
+    let out = add_patch(dir, "n\ny\n");
+    assert!(!out.contains("version ink-lfs"), "{}", out);
+
+    let pointer = staged(dir, "f.big");
+    assert!(pointer.starts_with("version ink-lfs/1\n"), "{}", pointer);
+
+    fs::remove_file(dir.join("f.big")).unwrap();
+    ink(dir, &["restore", "f.big"]);
+    assert_eq!(fs::read_to_string(dir.join("f.big")).unwrap(), numbered(20, &[(18, "eighteen")]));
+}
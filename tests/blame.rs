@@ -0,0 +1,19 @@
+mod common;
+
+use common::{ink_command, ink_stdout, init_repo};
+
+#[test]
+fn line_ranges_are_checked_without_overflowing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    for range in ["0,+0", "5,+0", "1,+0", "0", "0,1", "2,+18446744073709551615", "3,1"] {
+        let output = ink_command(dir).args(["blame", "-L", range, "README"]).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "-L {} was accepted", range);
+        assert!(stderr.starts_with("Error: "), "-L {}: {}", range, stderr);
+    }
+
+    assert_eq!(ink_stdout(dir, &["blame", "-L", "1,+5", "README"]).lines().count(), 1);
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ink, ink_stdout, init_repo};
+
+fn commit_file(dir: &Path, name: &str) {
+    fs::write(dir.join(name), format!("{}\n", name)).unwrap();
+    ink(dir, &["add", name]);
+    ink(dir, &["commit", "-m", name]);
+}
+
+#[test]
+fn ahead_and_behind_counts_follow_the_upstream() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    ink(tmp.path(), &["clone", "origin", "copy"]);
+    let copy = tmp.path().join("copy");
+
+    let status = ink_stdout(&copy, &["status"]);
+    assert!(status.contains("Your branch is up to date with 'origin/main'."), "{}", status);
+
+    commit_file(&copy, "local.txt");
+    commit_file(&origin, "remote1.txt");
+    commit_file(&origin, "remote2.txt");
+
+    // Counts come from the remote-tracking ref, so nothing changes until a fetch
+    let status = ink_stdout(&copy, &["status"]);
+    assert!(status.contains("ahead of 'origin/main' by 1 commit."), "{}", status);
+
+    ink(&copy, &["fetch"]);
+    let tracking = ink_stdout(&copy, &["rev-parse", "refs/remotes/origin/main"]);
+    assert_eq!(tracking, ink_stdout(&origin, &["rev-parse", "main"]));
+
+    let status = ink_stdout(&copy, &["status"]);
+    assert!(status.contains("have 1 and 2 different commits each"), "{}", status);
+    let short = ink_stdout(&copy, &["status", "--short", "--branch"]);
+    assert!(short.starts_with("## main...origin/main [ahead 1, behind 2]"), "{}", short);
+    let branches = ink_stdout(&copy, &["branch", "-vv"]);
+    assert!(branches.contains("[origin/main: ahead 1, behind 2]"), "{}", branches);
+}
+
+#[test]
+fn branches_without_an_upstream_show_no_counts() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    ink(tmp.path(), &["clone", "origin", "copy"]);
+    let copy = tmp.path().join("copy");
+
+    ink(&copy, &["checkout", "-b", "topic"]);
+    commit_file(&copy, "topic.txt");
+    let branches = ink_stdout(&copy, &["branch", "-vv"]);
+    let topic = branches.lines().find(|l| l.contains("topic")).unwrap();
+    assert!(!topic.contains('['), "{}", topic);
+
+    ink(&copy, &["branch", "--set-upstream-to", "origin/main"]);
+    let branches = ink_stdout(&copy, &["branch", "-vv"]);
+    let topic = branches.lines().find(|l| l.contains("topic")).unwrap();
+    assert!(topic.contains("[origin/main: ahead 1]"), "{}", topic);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+fn commit_file(dir: &Path, name: &str) {
+    fs::write(dir.join(name), format!("{}\n", name)).unwrap();
+    ink(dir, &["add", name]);
+    ink(dir, &["commit", "-m", name]);
+}
+
+#[test]
+fn clone_from_a_bundle_round_trips_history() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    commit_file(&origin, "a.txt");
+    ink(&origin, &["branch", "topic"]);
+
+    ink(&origin, &["bundle", "create", "../full.bundle", "--all"]);
+    let heads = ink_stdout(&origin, &["bundle", "list-heads", "../full.bundle"]);
+    assert!(heads.contains("refs/heads/main") && heads.contains("refs/heads/topic"), "{}", heads);
+
+    ink(tmp.path(), &["clone", "full.bundle", "copy"]);
+    let copy = tmp.path().join("copy");
+    assert_eq!(ink_stdout(&copy, &["rev-parse", "HEAD"]), ink_stdout(&origin, &["rev-parse", "main"]));
+    assert_eq!(fs::read_to_string(copy.join("a.txt")).unwrap(), "a.txt\n");
+    assert_eq!(ink_stdout(&copy, &["log", "--oneline"]), ink_stdout(&origin, &["log", "--oneline"]));
+    ink(&copy, &["fsck"]);
+}
+
+#[test]
+fn incremental_bundles_need_their_prerequisites() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    ink(tmp.path(), &["clone", "origin", "copy"]);
+    let copy = tmp.path().join("copy");
+    let base = ink_stdout(&origin, &["rev-parse", "main"]);
+
+    commit_file(&origin, "b.txt");
+    let range = format!("{}..main", base.trim());
+    ink(&origin, &["bundle", "create", "../update.bundle", &range]);
+
+    // A repository without the base commit cannot use it
+    let empty = tmp.path().join("empty");
+    fs::create_dir_all(&empty).unwrap();
+    ink(&empty, &["init"]);
+    let output = ink_command(&empty).args(["bundle", "verify", "../update.bundle"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(base.trim()));
+
+    // The clone has it, and fast-forwards from the bundle
+    let verify = ink_stdout(&copy, &["bundle", "verify", "../update.bundle"]);
+    assert!(verify.contains("requires 1 commit"), "{}", verify);
+    ink(&copy, &["pull", "../update.bundle", "main"]);
+    assert_eq!(ink_stdout(&copy, &["rev-parse", "HEAD"]), ink_stdout(&origin, &["rev-parse", "main"]));
+    assert_eq!(fs::read_to_string(copy.join("b.txt")).unwrap(), "b.txt\n");
+}
+
+#[test]
+fn damaged_bundles_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    ink(&origin, &["bundle", "create", "../repo.bundle", "main"]);
+
+    let path = tmp.path().join("repo.bundle");
+    let mut data = fs::read(&path).unwrap();
+    let last = data.len() - 20;
+    data[last] ^= 0xff;
+    fs::write(&path, data).unwrap();
+
+    let output = ink_command(&origin).args(["bundle", "verify", "../repo.bundle"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("corrupt"));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+fn is_ignored(dir: &Path, path: &str) -> bool {
+    ink_command(dir).args(["check-ignore", path]).output().unwrap().status.success()
+}
+
+#[test]
+fn negation_anchoring_and_directory_patterns() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    fs::write(dir.join(".inkignore"), "# logs\n*.log\n!keep.log\n/root.txt\nbuild/\n**/cache/*.tmp\n").unwrap();
+    fs::create_dir_all(dir.join("sub/build")).unwrap();
+    fs::create_dir_all(dir.join("a/b/cache")).unwrap();
+
+    assert!(is_ignored(dir, "debug.log"));
+    assert!(is_ignored(dir, "sub/debug.log"));
+    assert!(!is_ignored(dir, "keep.log"));
+
+    // Anchored to the directory holding the .inkignore
+    assert!(is_ignored(dir, "root.txt"));
+    assert!(!is_ignored(dir, "sub/root.txt"));
+
+    // Directory-only patterns match directories at any depth, but not files
+    assert!(is_ignored(dir, "sub/build"));
+    fs::write(dir.join("build"), "a file\n").unwrap();
+    assert!(!is_ignored(dir, "build"));
+
+    assert!(is_ignored(dir, "a/b/cache/x.tmp"));
+    assert!(!is_ignored(dir, "a/b/x.tmp"));
+}
+
+#[test]
+fn nested_files_override_their_parents() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    fs::write(dir.join(".inkignore"), "*.dat\n").unwrap();
+    fs::create_dir_all(dir.join("data")).unwrap();
+    fs::write(dir.join("data/.inkignore"), "!wanted.dat\n").unwrap();
+    fs::write(dir.join("data/wanted.dat"), "x\n").unwrap();
+    fs::write(dir.join("data/other.dat"), "x\n").unwrap();
+
+    let verbose = ink_stdout(dir, &["check-ignore", "-v", "data/other.dat"]);
+    assert_eq!(verbose, ".inkignore:1:*.dat\tdata/other.dat\n");
+    assert!(!is_ignored(dir, "data/wanted.dat"));
+
+    // add . and add <dir> agree with check-ignore
+    ink(dir, &["add", "."]);
+    let staged = ink_stdout(dir, &["ls-files"]);
+    assert!(staged.contains("data/wanted.dat"), "{}", staged);
+    assert!(!staged.contains("data/other.dat"), "{}", staged);
+}
+
+#[test]
+fn info_exclude_applies_without_an_inkignore() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    fs::create_dir_all(dir.join(".ink/info")).unwrap();
+    fs::write(dir.join(".ink/info/exclude"), "secret.env\n").unwrap();
+    assert!(is_ignored(dir, "secret.env"));
+    assert!(!is_ignored(dir, "public.env"));
+}
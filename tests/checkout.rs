@@ -0,0 +1,27 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+#[test]
+fn rejected_checkout_does_not_create_the_branch() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    let hook = dir.join(".ink/hooks/pre-checkout");
+    fs::create_dir_all(hook.parent().unwrap()).unwrap();
+    fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = ink_command(dir).args(["checkout", "-b", "feature"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(!dir.join(".ink/refs/heads/feature").exists());
+    assert!(!ink_stdout(dir, &["branch"]).contains("feature"));
+
+    fs::write(&hook, "#!/bin/sh\nexit 0\n").unwrap();
+    ink(dir, &["checkout", "-b", "feature"]);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "feature"]), ink_stdout(dir, &["rev-parse", "main"]));
+}
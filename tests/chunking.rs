@@ -0,0 +1,76 @@
+mod common;
+
+use std::fs;
+
+use common::{ink, ink_stdout, init_repo};
+
+/// Deterministic bytes that do not compress or repeat, so chunk boundaries come from the content
+fn noise(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as u8
+        })
+        .collect()
+}
+
+/// The number after `label` in `ink stats` output, e.g. "Chunked blobs:  2 holding ..."
+fn stat(stats: &str, label: &str) -> f64 {
+    let line = stats.lines().find(|l| l.starts_with(label)).unwrap_or_else(|| panic!("no {} in\n{}", label, stats));
+    let value = line[label.len()..].split_whitespace().next().unwrap();
+    value.trim_end_matches('x').trim_end_matches(',').parse().unwrap()
+}
+
+#[test]
+fn edited_versions_of_a_large_file_share_chunks() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["config", "core.chunking", "true"]);
+
+    let original = noise(4 * 1024 * 1024, 1);
+    fs::write(dir.join("data.bin"), &original).unwrap();
+    ink(dir, &["add", "data.bin"]);
+    ink(dir, &["commit", "-m", "v1"]);
+
+    // An insertion shifts everything after it; content-defined boundaries realign
+    let mut edited = original.clone();
+    edited.splice(1_000_000..1_000_000, b"a small insertion".iter().copied());
+    fs::write(dir.join("data.bin"), &edited).unwrap();
+    ink(dir, &["add", "data.bin"]);
+    ink(dir, &["commit", "-m", "v2"]);
+
+    let stats = ink_stdout(dir, &["stats"]);
+    assert_eq!(stat(&stats, "Chunked blobs:"), 2.0, "{}", stats);
+    assert!(stat(&stats, "Dedup ratio:") > 1.7, "{}", stats);
+
+    // Both versions read back whole
+    ink(dir, &["fsck"]);
+    ink(dir, &["restore", "--source", "HEAD~1", "data.bin"]);
+    assert!(fs::read(dir.join("data.bin")).unwrap() == original, "v1 did not reassemble");
+    ink(dir, &["restore", "--source", "HEAD", "--force", "data.bin"]);
+    assert!(fs::read(dir.join("data.bin")).unwrap() == edited, "v2 did not reassemble");
+}
+
+#[test]
+fn small_blobs_and_disabled_chunking_store_whole_objects() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    fs::write(dir.join("data.bin"), noise(2 * 1024 * 1024, 2)).unwrap();
+    ink(dir, &["add", "data.bin"]);
+    ink(dir, &["commit", "-m", "unchunked"]);
+    assert!(ink_stdout(dir, &["stats"]).contains("Chunked blobs:  none"));
+
+    ink(dir, &["config", "core.chunking", "true"]);
+    fs::write(dir.join("small.txt"), "small\n").unwrap();
+    ink(dir, &["add", "small.txt"]);
+    ink(dir, &["commit", "-m", "small"]);
+    assert!(ink_stdout(dir, &["stats"]).contains("Chunked blobs:  none"));
+
+    // recompress splits the large blob that predates the setting
+    ink(dir, &["recompress"]);
+    assert_eq!(stat(&ink_stdout(dir, &["stats"]), "Chunked blobs:"), 1.0);
+    ink(dir, &["fsck"]);
+}
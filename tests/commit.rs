@@ -0,0 +1,93 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+/// The body of a commit object, as printed by `cat-file -p`
+fn show(dir: &std::path::Path, rev: &str) -> String {
+    ink_stdout(dir, &["cat-file", "-p", rev])
+}
+
+fn message(commit: &str) -> &str {
+    commit.split_once("\n\n").unwrap().1
+}
+
+#[test]
+fn amend_replaces_the_tip_and_keeps_its_parents() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let base = ink_stdout(dir, &["rev-parse", "HEAD"]);
+
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    ink(dir, &["add", "a.txt"]);
+    ink(dir, &["commit", "-m", "add a"]);
+    let tip = ink_stdout(dir, &["rev-parse", "HEAD"]);
+
+    fs::write(dir.join("b.txt"), "two\n").unwrap();
+    ink(dir, &["add", "b.txt"]);
+    ink(dir, &["commit", "--amend", "-m", "add a and b"]);
+    let amended = ink_stdout(dir, &["rev-parse", "HEAD"]);
+
+    assert_ne!(amended, tip);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "main"]), amended);
+    let commit = show(dir, "HEAD");
+    assert!(commit.contains(&format!("parent {}", base.trim())), "{}", commit);
+    assert_eq!(message(&commit).trim(), "add a and b");
+    assert!(ink_stdout(dir, &["ls-tree", "--name-only", "HEAD"]).contains("b.txt"));
+}
+
+#[test]
+fn amend_without_a_message_reuses_the_old_one() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    ink(dir, &["commit", "--amend", "--no-edit", "--allow-empty"]);
+    assert_eq!(message(&show(dir, "HEAD")).trim(), "initial");
+}
+
+#[test]
+fn empty_commits_need_allow_empty() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let before = ink_stdout(dir, &["rev-parse", "HEAD"]);
+
+    ink(dir, &["commit", "-m", "nothing"]);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "HEAD"]), before);
+
+    ink(dir, &["commit", "--allow-empty", "-m", "nothing"]);
+    let after = ink_stdout(dir, &["rev-parse", "HEAD"]);
+    assert_ne!(after, before);
+    assert!(show(dir, "HEAD").contains(&format!("parent {}", before.trim())));
+}
+
+#[test]
+fn messages_come_from_paragraphs_files_and_the_editor() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    ink(dir, &["commit", "--allow-empty", "-m", "subject", "-m", "body"]);
+    assert_eq!(message(&show(dir, "HEAD")), "subject\n\nbody\n");
+
+    fs::write(dir.join("msg.txt"), "from a file\n").unwrap();
+    ink(dir, &["commit", "--allow-empty", "-F", "msg.txt"]);
+    assert_eq!(message(&show(dir, "HEAD")).trim(), "from a file");
+
+    // The editor sees the staged changes in the template; comment lines are dropped
+    fs::write(dir.join("c.txt"), "three\n").unwrap();
+    ink(dir, &["add", "c.txt"]);
+    let editor = dir.join("editor.sh");
+    fs::write(&editor, "#!/bin/sh\ncp \"$1\" template.txt\nprintf 'edited\\n# dropped\\n' > \"$1\"\n").unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+    let output = ink_command(dir).env("INK_EDITOR", &editor).args(["commit"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    assert_eq!(message(&show(dir, "HEAD")).trim(), "edited");
+    let template = fs::read_to_string(dir.join("template.txt")).unwrap();
+    assert!(template.lines().any(|l| l.starts_with('#') && l.contains("c.txt")), "{}", template);
+}
@@ -0,0 +1,44 @@
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// An `ink` command run in `dir`, isolated from the user's own configuration
+pub fn ink_command(dir: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_ink"));
+    cmd.current_dir(dir)
+        .env("HOME", dir)
+        .env("RUST_BACKTRACE", "0")
+        .env("INK_AUTHOR_NAME", "Test")
+        .env("INK_AUTHOR_EMAIL", "test@example.com")
+        .env("INK_COMMITTER_NAME", "Test")
+        .env("INK_COMMITTER_EMAIL", "test@example.com");
+    cmd
+}
+
+/// Runs `ink <args>` in `dir` and returns its output, failing the test if it fails
+pub fn ink(dir: &Path, args: &[&str]) -> Output {
+    let output = ink_command(dir).args(args).output().expect("cannot run ink");
+    assert!(
+        output.status.success(),
+        "ink {} failed:\n{}{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// Stdout of a successful `ink <args>`
+pub fn ink_stdout(dir: &Path, args: &[&str]) -> String {
+    String::from_utf8(ink(dir, args).stdout).unwrap()
+}
+
+/// A new repository in `dir` with one committed file
+pub fn init_repo(dir: &Path) {
+    std::fs::create_dir_all(dir).unwrap();
+    ink(dir, &["init"]);
+    std::fs::write(dir.join("README"), "hello\n").unwrap();
+    ink(dir, &["add", "README"]);
+    ink(dir, &["commit", "-m", "initial"]);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+fn object_file(dir: &Path, hash: &str) -> PathBuf {
+    dir.join(".ink/objects").join(&hash[..2]).join(&hash[2..])
+}
+
+/// `ink fsck`, returning whether it passed and everything it printed
+fn fsck(dir: &Path) -> (bool, String) {
+    let output = ink_command(dir).arg("fsck").output().unwrap();
+    let text = String::from_utf8_lossy(&output.stdout).into_owned() + &String::from_utf8_lossy(&output.stderr);
+    (output.status.success(), text)
+}
+
+#[test]
+fn fresh_and_committed_repositories_are_clean() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    ink(dir, &["init"]);
+    let (ok, text) = fsck(dir);
+    assert!(ok, "{}", text);
+
+    fs::write(dir.join("README"), "hello\n").unwrap();
+    ink(dir, &["add", "README"]);
+    ink(dir, &["commit", "-m", "initial"]);
+    ink(dir, &["checkout", "-b", "side"]);
+    let (ok, text) = fsck(dir);
+    assert!(ok, "{}", text);
+}
+
+#[test]
+fn corrupted_object_is_reported() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let blob = ink_stdout(dir, &["rev-parse", "HEAD:README"]).trim().to_string();
+
+    fs::write(object_file(dir, &blob), b"blob 6\0jello\n").unwrap();
+    let (ok, text) = fsck(dir);
+    assert!(!ok);
+    assert!(text.contains(&blob), "{}", text);
+}
+
+#[test]
+fn missing_object_is_reported() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let blob = ink_stdout(dir, &["rev-parse", "HEAD:README"]).trim().to_string();
+
+    fs::remove_file(object_file(dir, &blob)).unwrap();
+    let (ok, text) = fsck(dir);
+    assert!(!ok);
+    assert!(text.contains(&blob), "{}", text);
+}
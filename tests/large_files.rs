@@ -0,0 +1,117 @@
+mod common;
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::Command;
+
+use common::{ink, ink_command};
+
+/// Address space ink may use for heap data; far less than the files below
+const MEMORY_LIMIT_KIB: u64 = 64 * 1024;
+
+/// Runs `ink <args>` with its data segment limited, so any step that reads a
+/// whole large file into memory fails instead of passing
+fn ink_limited(dir: &Path, args: &[&str]) {
+    let base = ink_command(dir);
+    let mut cmd = Command::new("sh");
+    cmd.current_dir(dir)
+        .envs(base.get_envs().filter_map(|(k, v)| Some((k, v?))))
+        .arg("-c")
+        .arg(format!("ulimit -d {} && exec \"$0\" \"$@\"", MEMORY_LIMIT_KIB))
+        .arg(base.get_program())
+        .args(args);
+    let output = cmd.output().unwrap();
+    assert!(
+        output.status.success(),
+        "ink {} failed under the memory limit:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// A sparse file of `size` bytes with some data near the start and at the very end
+fn write_sparse(path: &Path, size: u64) {
+    let mut file = File::create(path).unwrap();
+    file.set_len(size).unwrap();
+    file.seek(SeekFrom::Start(5 << 20)).unwrap();
+    file.write_all(&b"large file ".repeat(1000)).unwrap();
+    file.seek(SeekFrom::End(-10)).unwrap();
+    file.write_all(b"end-marker").unwrap();
+}
+
+/// Compares two files a buffer at a time
+fn assert_same_content(a: &Path, b: &Path) {
+    assert_eq!(fs::metadata(a).unwrap().len(), fs::metadata(b).unwrap().len());
+    let (mut a, mut b) = (File::open(a).unwrap(), File::open(b).unwrap());
+    let (mut buf_a, mut buf_b) = (vec![0; 1 << 20], vec![0; 1 << 20]);
+    let mut offset = 0;
+    loop {
+        let n = a.read(&mut buf_a).unwrap();
+        if n == 0 {
+            break;
+        }
+        b.read_exact(&mut buf_b[..n]).unwrap();
+        assert!(buf_a[..n] == buf_b[..n], "contents differ within {} bytes of offset {}", n, offset);
+        offset += n;
+    }
+}
+
+/// Commits a large sparse file, switches away and back, and checks the file comes back intact
+fn round_trip(size: u64, config: &[(&str, &str)]) {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = tmp.path().join("repo");
+    fs::create_dir(&repo).unwrap();
+    ink(&repo, &["init"]);
+    for (key, value) in config {
+        ink(&repo, &["config", key, value]);
+    }
+    fs::write(repo.join("README"), "small\n").unwrap();
+    ink(&repo, &["add", "README"]);
+    ink(&repo, &["commit", "-m", "initial"]);
+    ink(&repo, &["branch", "before"]);
+
+    let expected = tmp.path().join("expected.bin");
+    write_sparse(&expected, size);
+    fs::copy(&expected, repo.join("big.bin")).unwrap();
+
+    ink_limited(&repo, &["add", "big.bin"]);
+    ink_limited(&repo, &["commit", "-m", "add big file"]);
+    ink_limited(&repo, &["checkout", "before"]);
+    assert!(!repo.join("big.bin").exists());
+    ink_limited(&repo, &["checkout", "main"]);
+    assert_same_content(&repo.join("big.bin"), &expected);
+
+    // Cloning and pushing stream objects between repositories too
+    ink_limited(tmp.path(), &["clone", "repo", "copy"]);
+    let copy = tmp.path().join("copy");
+    assert_same_content(&copy.join("big.bin"), &expected);
+
+    let other = tmp.path().join("other.bin");
+    write_sparse(&other, size / 4);
+    fs::copy(&other, copy.join("other.bin")).unwrap();
+    ink_limited(&copy, &["checkout", "-b", "more"]);
+    ink_limited(&copy, &["add", "other.bin"]);
+    ink_limited(&copy, &["commit", "-m", "add another big file"]);
+    ink_limited(&copy, &["push", "origin", "more"]);
+    ink_limited(&repo, &["checkout", "more"]);
+    assert_same_content(&repo.join("other.bin"), &other);
+    ink_limited(&repo, &["checkout", "main"]);
+
+    // Converting the store streams blobs too
+    ink_limited(&repo, &["migrate-hash", "sha256"]);
+    fs::remove_file(repo.join("big.bin")).unwrap();
+    ink_limited(&repo, &["restore", "big.bin"]);
+    assert_same_content(&repo.join("big.bin"), &expected);
+    ink(&repo, &["fsck"]);
+}
+
+#[test]
+fn large_file_round_trip_streams() {
+    round_trip(160 << 20, &[]);
+}
+
+#[test]
+fn large_chunked_file_round_trip_streams() {
+    round_trip(96 << 20, &[("core.chunking", "true")]);
+}
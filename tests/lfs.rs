@@ -0,0 +1,49 @@
+mod common;
+
+use std::fs;
+
+use common::{ink, ink_stdout, init_repo};
+
+#[test]
+fn large_files_stay_clean_across_migrate_hash() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["branch", "before"]);
+    ink(dir, &["lfs", "track", "*.bin"]);
+    let content: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    fs::write(dir.join("data.bin"), &content).unwrap();
+    ink(dir, &["add", ".inklfs", "data.bin"]);
+    ink(dir, &["commit", "-m", "add large file"]);
+
+    ink(dir, &["migrate-hash", "sha256"]);
+    assert!(ink_stdout(dir, &["status"]).contains("working tree clean"));
+
+    // Switching away and back must not see the file as modified either
+    ink(dir, &["checkout", "before"]);
+    ink(dir, &["checkout", "main"]);
+    assert_eq!(fs::read(dir.join("data.bin")).unwrap(), content);
+    assert!(ink_stdout(dir, &["status"]).contains("working tree clean"));
+}
+
+#[test]
+fn bundles_carry_large_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    ink(&origin, &["lfs", "track", "*.bin"]);
+    let content: Vec<u8> = (0..9_000_000u32).map(|i| (i * 13 % 253) as u8).collect();
+    fs::write(origin.join("data.bin"), &content).unwrap();
+    ink(&origin, &["add", ".inklfs", "data.bin"]);
+    ink(&origin, &["commit", "-m", "add large file"]);
+
+    let bundle = tmp.path().join("repo.bundle");
+    ink(&origin, &["bundle", "create", bundle.to_str().unwrap(), "main"]);
+    ink(&origin, &["bundle", "verify", bundle.to_str().unwrap()]);
+
+    ink(tmp.path(), &["clone", "repo.bundle", "copy"]);
+    let copy = tmp.path().join("copy");
+    assert!(fs::read(copy.join("data.bin")).unwrap() == content, "the clone holds a pointer, not the content");
+    assert!(ink_stdout(&copy, &["lfs", "ls"]).contains("data.bin"));
+    ink(&copy, &["fsck"]);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ink, ink_stdout, init_repo};
+
+fn commit_file(dir: &Path, name: &str, message: &str) {
+    fs::write(dir.join(name), format!("{}\n", name)).unwrap();
+    ink(dir, &["add", name]);
+    ink(dir, &["commit", "-m", message]);
+}
+
+fn short(dir: &Path, rev: &str) -> String {
+    ink_stdout(dir, &["rev-parse", "--short", rev]).trim().to_string()
+}
+
+/// main and topic diverge from "initial" and main then merges topic
+fn merged_history(dir: &Path) {
+    init_repo(dir);
+    ink(dir, &["checkout", "-b", "topic"]);
+    commit_file(dir, "b.txt", "on topic");
+    ink(dir, &["checkout", "main"]);
+    commit_file(dir, "c.txt", "on main");
+
+    let tree = ink_stdout(dir, &["write-tree"]);
+    let merge = ink_stdout(dir, &["commit-tree", tree.trim(), "-p", "main", "-p", "topic", "-m", "merge"]);
+    ink(dir, &["update-ref", "refs/heads/main", merge.trim()]);
+    ink(dir, &["update-ref", "refs/tags/v1", "topic"]);
+}
+
+#[test]
+fn graph_draws_lanes_for_a_merge() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    merged_history(dir);
+
+    let expected = format!(
+        "* {} (HEAD -> main) merge\n|\\\n| * {} (topic, tag: v1) on topic\n* | {} on main\n|/\n* {} initial\n",
+        short(dir, "main"),
+        short(dir, "topic"),
+        short(dir, "main^1"),
+        short(dir, "main~2"),
+    );
+    assert_eq!(ink_stdout(dir, &["log", "--graph", "--oneline", "--decorate"]), expected);
+}
+
+#[test]
+fn all_includes_branches_not_reachable_from_head() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["checkout", "-b", "side"]);
+    commit_file(dir, "side.txt", "side only");
+    ink(dir, &["checkout", "main"]);
+
+    assert!(!ink_stdout(dir, &["log", "--oneline"]).contains("side only"));
+    let all = ink_stdout(dir, &["log", "--oneline", "--all", "--decorate"]);
+    assert!(all.contains(&format!("{} (side) side only", short(dir, "side"))), "{}", all);
+}
+
+#[test]
+fn topological_order_keeps_each_line_of_history_together() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    merged_history(dir);
+
+    let subjects = |args: &[&str]| -> Vec<String> {
+        ink_stdout(dir, args).lines().map(|l| l.split_once(' ').unwrap().1.to_string()).collect()
+    };
+    let topo = subjects(&["log", "--oneline", "--topo-order"]);
+    assert_eq!(topo.first().unwrap(), "merge");
+    assert_eq!(topo.last().unwrap(), "initial");
+
+    // Every commit comes before its parents in both orders
+    for order in ["--topo-order", "--date-order"] {
+        let shown = subjects(&["log", "--oneline", order]);
+        let at = |s: &str| shown.iter().position(|x| x == s).unwrap();
+        assert!(at("merge") < at("on main") && at("merge") < at("on topic"), "{:?}", shown);
+        assert!(at("on main") < at("initial") && at("on topic") < at("initial"), "{:?}", shown);
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ink, ink_command, init_repo};
+
+/// Commits `content` as `path` on the current branch
+fn commit_file(dir: &Path, path: &str, content: &[u8], message: &str) {
+    fs::write(dir.join(path), content).unwrap();
+    ink(dir, &["add", path]);
+    ink(dir, &["commit", "-m", message]);
+}
+
+/// `ink cherry-pick --continue`, returning whether it succeeded and its stderr
+fn try_continue(dir: &Path) -> (bool, String) {
+    let output = ink_command(dir).args(["cherry-pick", "--continue"]).output().unwrap();
+    (output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[test]
+fn binary_conflict_stays_unresolved_until_staged() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    commit_file(dir, "image.bin", b"\0base", "add image");
+    ink(dir, &["branch", "theirs"]);
+    commit_file(dir, "image.bin", b"\0ours", "ours");
+    ink(dir, &["checkout", "theirs"]);
+    commit_file(dir, "image.bin", b"\0theirs", "theirs");
+    ink(dir, &["checkout", "main"]);
+
+    let picked = ink_command(dir).args(["cherry-pick", "theirs"]).output().unwrap();
+    assert!(!picked.status.success());
+    assert_eq!(fs::read(dir.join("image.bin")).unwrap(), b"\0ours");
+
+    let (ok, stderr) = try_continue(dir);
+    assert!(!ok, "continued with an unstaged binary conflict");
+    assert!(stderr.contains("image.bin"), "{}", stderr);
+
+    ink(dir, &["add", "image.bin"]);
+    assert!(try_continue(dir).0);
+}
+
+#[test]
+fn modify_delete_conflict_stays_unresolved_until_staged() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    commit_file(dir, "notes.txt", b"one\n", "add notes");
+    ink(dir, &["branch", "theirs"]);
+    commit_file(dir, "notes.txt", b"one\ntwo\n", "ours");
+    ink(dir, &["checkout", "theirs"]);
+    fs::remove_file(dir.join("notes.txt")).unwrap();
+    ink(dir, &["add", "notes.txt"]);
+    ink(dir, &["commit", "-m", "delete notes"]);
+    ink(dir, &["checkout", "main"]);
+
+    let picked = ink_command(dir).args(["cherry-pick", "theirs"]).output().unwrap();
+    assert!(!picked.status.success());
+    assert_eq!(fs::read(dir.join("notes.txt")).unwrap(), b"one\ntwo\n");
+    assert!(!try_continue(dir).0, "continued with an unstaged modify/delete conflict");
+
+    ink(dir, &["add", "notes.txt"]);
+    assert!(try_continue(dir).0);
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use std::fs;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+#[test]
+fn interrupted_migration_is_finished_by_a_rerun() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["checkout", "-b", "feature"]);
+    fs::write(dir.join("more.txt"), "more\n").unwrap();
+    ink(dir, &["add", "more.txt"]);
+    ink(dir, &["commit", "-m", "more"]);
+    ink(dir, &["checkout", "main"]);
+
+    // A directory where the hash map goes makes the last write of the swap fail
+    fs::create_dir(dir.join(".ink/hash-map")).unwrap();
+    let output = ink_command(dir).args(["migrate-hash", "sha256"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(dir.join(".ink/MIGRATE_HASH").exists());
+
+    let other = ink_command(dir).args(["migrate-hash", "blake3"]).output().unwrap();
+    assert!(!other.status.success());
+    assert!(String::from_utf8_lossy(&other.stderr).contains("ink migrate-hash sha256"));
+
+    fs::remove_dir(dir.join(".ink/hash-map")).unwrap();
+    ink(dir, &["migrate-hash", "sha256"]);
+    assert!(!dir.join(".ink/MIGRATE_HASH").exists());
+    assert!(!dir.join(".ink/objects-old").exists());
+
+    ink(dir, &["fsck"]);
+    ink(dir, &["checkout", "feature"]);
+    assert_eq!(fs::read_to_string(dir.join("more.txt")).unwrap(), "more\n");
+    assert_eq!(ink_stdout(dir, &["ls-files"]), "README\nmore.txt\n");
+}
+
+#[test]
+fn undecodable_branch_index_stops_the_migration() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["branch", "side"]);
+    fs::write(dir.join(".ink/refs/INDEXES/side"), [0xff; 16]).unwrap();
+
+    let output = ink_command(dir).args(["migrate-hash", "sha256"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("branch 'side'"));
+    assert!(!dir.join(".ink/MIGRATE_HASH").exists());
+    assert_eq!(fs::read_to_string(dir.join(".ink/objects/hash")).unwrap().trim(), "blake3");
+}
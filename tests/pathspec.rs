@@ -0,0 +1,42 @@
+mod common;
+
+use std::fs;
+
+use common::{ink, ink_command, ink_stdout};
+
+#[test]
+fn file_names_with_glob_characters_match_exactly() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    ink(dir, &["init"]);
+    fs::write(dir.join("a[1].txt"), "bracket\n").unwrap();
+    fs::write(dir.join("a1.txt"), "plain\n").unwrap();
+
+    ink(dir, &["add", "a[1].txt"]);
+    assert_eq!(ink_stdout(dir, &["ls-files"]), "a[1].txt\n");
+
+    // Without such a file the argument is still a glob
+    ink(dir, &["add", "a[0-9].txt"]);
+    assert_eq!(ink_stdout(dir, &["ls-files"]), "a1.txt\na[1].txt\n");
+}
+
+#[test]
+fn paths_outside_the_repository_are_refused() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("repo");
+    fs::create_dir(&dir).unwrap();
+    ink(&dir, &["init"]);
+    fs::write(tmp.path().join("outside.txt"), "no\n").unwrap();
+    fs::write(dir.join("inside.txt"), "yes\n").unwrap();
+
+    for arg in ["../outside.txt", tmp.path().join("outside.txt").to_str().unwrap()] {
+        let output = ink_command(&dir).args(["add", arg]).output().unwrap();
+        assert!(!output.status.success(), "added {}", arg);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("outside the repository"));
+    }
+
+    // `..` that stays inside, and absolute paths within the repository, are fine
+    let absolute = dir.join("inside.txt");
+    ink(&dir, &["add", "sub/../inside.txt", absolute.to_str().unwrap()]);
+    assert_eq!(ink_stdout(&dir, &["ls-files"]), "inside.txt\n");
+}
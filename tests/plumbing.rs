@@ -0,0 +1,112 @@
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+fn fails(dir: &Path, args: &[&str]) -> String {
+    let output = ink_command(dir).args(args).output().unwrap();
+    assert!(!output.status.success(), "ink {} succeeded", args.join(" "));
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn hash_stdin(dir: &Path, content: &[u8]) -> String {
+    let mut child = ink_command(dir)
+        .args(["hash-object", "-w", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(content).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[test]
+fn objects_can_be_built_and_read_back_by_hand() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    // hash-object without -w only computes the name
+    fs::write(dir.join("loose.txt"), "loose\n").unwrap();
+    let name = ink_stdout(dir, &["hash-object", "loose.txt"]);
+    fails(dir, &["cat-file", "-e", name.trim()]);
+
+    let blob = hash_stdin(dir, b"loose\n");
+    assert_eq!(blob, name.trim());
+    assert_eq!(ink_stdout(dir, &["cat-file", "-t", &blob]), "blob\n");
+    assert_eq!(ink_stdout(dir, &["cat-file", "-s", &blob]), "6\n");
+    assert_eq!(ink_stdout(dir, &["cat-file", "blob", &blob]), "loose\n");
+    assert!(fails(dir, &["cat-file", "tree", &blob]).contains("not a tree"));
+
+    // write-tree records the index as it is, commit-tree and update-ref publish it
+    ink(dir, &["add", "loose.txt"]);
+    let tree = ink_stdout(dir, &["write-tree"]);
+    let head = ink_stdout(dir, &["rev-parse", "HEAD"]);
+    let commit = ink_stdout(dir, &["commit-tree", tree.trim(), "-p", head.trim(), "-m", "by hand"]);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "HEAD"]), head, "commit-tree must not move the branch");
+
+    ink(dir, &["update-ref", "refs/heads/main", commit.trim(), head.trim()]);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "main"]), commit);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "HEAD^{tree}"]), tree);
+    assert!(ink_stdout(dir, &["status"]).contains("nothing to commit"));
+}
+
+#[test]
+fn update_ref_checks_names_and_old_values() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let head = ink_stdout(dir, &["rev-parse", "HEAD"]);
+    let head = head.trim();
+
+    for bad in ["refs/heads/a..b", "refs/heads/x.lock", "refs/heads/sp ace", "refs//double", "heads/outside"] {
+        fails(dir, &["update-ref", bad, head]);
+    }
+    assert!(!dir.join(".ink/refs/heads/x.lock").exists());
+
+    ink(dir, &["update-ref", "refs/tags/v1", head, "0000"]);
+    assert!(fails(dir, &["update-ref", "refs/tags/v1", head, "0000"]).contains("expected"));
+
+    let blob = hash_stdin(dir, b"not a commit\n");
+    assert!(fails(dir, &["update-ref", "refs/tags/v2", &blob]).contains("blob"));
+
+    ink(dir, &["update-ref", "-d", "refs/tags/v1", head]);
+    assert!(!dir.join(".ink/refs/tags/v1").exists());
+    fails(dir, &["update-ref", "-d", "refs/heads/main"]);
+}
+
+#[test]
+fn listings_are_nul_terminated_with_z() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub/tab\there"), "x\n").unwrap();
+    ink(dir, &["add", "sub"]);
+    ink(dir, &["commit", "-m", "tab in a name"]);
+
+    assert_eq!(ink_stdout(dir, &["ls-files", "-z"]), "README\0sub/tab\there\0");
+    assert_eq!(ink_stdout(dir, &["ls-tree", "-r", "-z", "--name-only", "HEAD"]), "README\0sub/tab\there\0");
+
+    let tree = ink_stdout(dir, &["ls-tree", "HEAD"]);
+    assert!(tree.lines().any(|l| l.starts_with("040000 tree ") && l.ends_with("\tsub")), "{}", tree);
+}
+
+#[test]
+fn rev_parse_resolves_and_abbreviates() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let head = ink_stdout(dir, &["rev-parse", "HEAD"]);
+
+    assert_eq!(ink_stdout(dir, &["rev-parse", "--short", "HEAD"]), format!("{}\n", &head[..7]));
+    assert_eq!(ink_stdout(dir, &["rev-parse", &head[..10]]), head);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "--abbrev-ref", "HEAD"]), "main\n");
+    assert!(fails(dir, &["rev-parse", "--verify", "nope"]).contains("Needed a single revision"));
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+fn commit_file(dir: &Path, name: &str, content: &str, message: &str) {
+    fs::write(dir.join(name), content).unwrap();
+    ink(dir, &["add", name]);
+    ink(dir, &["commit", "-m", message]);
+}
+
+fn subjects(dir: &Path) -> Vec<String> {
+    ink_stdout(dir, &["log", "--oneline"]).lines().map(|l| l.split_once(' ').unwrap().1.to_string()).collect()
+}
+
+/// A feature branch with three commits, based on a main that has moved on
+fn diverged(dir: &Path) {
+    init_repo(dir);
+    ink(dir, &["checkout", "-b", "feature"]);
+    commit_file(dir, "a.txt", "a\n", "add a");
+    commit_file(dir, "b.txt", "b\n", "add b");
+    commit_file(dir, "a.txt", "a fixed\n", "fix a");
+    ink(dir, &["checkout", "main"]);
+    commit_file(dir, "main.txt", "main\n", "main moves on");
+    ink(dir, &["checkout", "feature"]);
+}
+
+/// An editor script that runs `sed <expr>` on the todo list and leaves messages alone
+fn todo_editor(dir: &Path, expr: &str) -> std::path::PathBuf {
+    let editor = dir.join("../editor.sh");
+    let script = format!("#!/bin/sh\ncase \"$1\" in\n*git-rebase-todo) sed -i '{}' \"$1\" ;;\nesac\n", expr);
+    fs::write(&editor, script).unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+    editor
+}
+
+#[test]
+fn rebase_replays_commits_onto_the_upstream() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = &tmp.path().join("repo");
+    diverged(dir);
+
+    ink(dir, &["rebase", "main"]);
+    assert_eq!(subjects(dir), ["fix a", "add b", "add a", "main moves on", "initial"]);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "feature~3"]), ink_stdout(dir, &["rev-parse", "main"]));
+    assert!(dir.join("main.txt").exists());
+
+    let reflog = fs::read_to_string(dir.join(".ink/logs/refs/heads/feature")).unwrap();
+    assert!(reflog.contains("rebase (start)") && reflog.contains("rebase (finish)"), "{}", reflog);
+    assert_eq!(reflog.matches("rebase (pick)").count(), 3, "{}", reflog);
+}
+
+#[test]
+fn interactive_todo_can_drop_and_fix_up() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = &tmp.path().join("repo");
+    diverged(dir);
+
+    // pick "add a", drop "add b", fold "fix a" into "add a"
+    let editor = todo_editor(dir, "2s/^pick/drop/; 3s/^pick/fixup/");
+    let output = ink_command(dir).env("INK_EDITOR", &editor).args(["rebase", "-i", "main"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    assert_eq!(subjects(dir), ["add a", "main moves on", "initial"]);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a fixed\n");
+    assert!(!dir.join("b.txt").exists());
+}
+
+#[test]
+fn edit_stops_until_continue() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = &tmp.path().join("repo");
+    diverged(dir);
+
+    let editor = todo_editor(dir, "1s/^pick/edit/");
+    let output = ink_command(dir).env("INK_EDITOR", &editor).args(["rebase", "-i", "main"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(dir.join(".ink/rebase-merge").is_dir());
+    assert_eq!(subjects(dir), ["add a", "main moves on", "initial"]);
+
+    commit_file(dir, "extra.txt", "extra\n", "inserted while stopped");
+    ink(dir, &["rebase", "--continue"]);
+    assert!(!dir.join(".ink/rebase-merge").exists());
+    assert_eq!(subjects(dir), ["fix a", "add b", "inserted while stopped", "add a", "main moves on", "initial"]);
+}
+
+#[test]
+fn conflicts_stop_the_rebase_until_resolved() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = &tmp.path().join("repo");
+    init_repo(dir);
+    ink(dir, &["checkout", "-b", "feature"]);
+    commit_file(dir, "README", "feature\n", "feature edit");
+    ink(dir, &["checkout", "main"]);
+    commit_file(dir, "README", "main\n", "main edit");
+    ink(dir, &["checkout", "feature"]);
+    let before = ink_stdout(dir, &["rev-parse", "HEAD"]);
+
+    assert!(!ink_command(dir).args(["rebase", "main"]).output().unwrap().status.success());
+    assert!(!ink_command(dir).args(["rebase", "--continue"]).output().unwrap().status.success());
+
+    // --abort puts everything back
+    ink(dir, &["rebase", "--abort"]);
+    assert_eq!(ink_stdout(dir, &["rev-parse", "HEAD"]), before);
+    assert_eq!(fs::read_to_string(dir.join("README")).unwrap(), "feature\n");
+
+    assert!(!ink_command(dir).args(["rebase", "main"]).output().unwrap().status.success());
+    fs::write(dir.join("README"), "resolved\n").unwrap();
+    ink(dir, &["add", "README"]);
+    ink(dir, &["rebase", "--continue"]);
+    assert_eq!(subjects(dir), ["feature edit", "main edit", "initial"]);
+    assert_eq!(fs::read_to_string(dir.join("README")).unwrap(), "resolved\n");
+}
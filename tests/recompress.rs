@@ -0,0 +1,95 @@
+mod common;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use common::{ink, ink_stdout, init_repo};
+
+/// How the objects in the store are encoded, by their leading bytes
+fn encodings(dir: &Path) -> BTreeSet<&'static str> {
+    let mut found = BTreeSet::new();
+    for path in files_under(&dir.join(".ink/objects")) {
+        // Objects live in two-character fan-out directories, beside files such as `hash`
+        if path.parent().unwrap().file_name().unwrap().len() != 2 {
+            continue;
+        }
+        let data = fs::read(&path).unwrap();
+        found.insert(match data.as_slice() {
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => "zstd",
+            [0x78, ..] => "zlib",
+            _ => "none",
+        });
+    }
+    found
+}
+
+fn files_under(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(files_under(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn commit_file(dir: &Path, name: &str, content: &str) {
+    fs::write(dir.join(name), content).unwrap();
+    ink(dir, &["add", name]);
+    ink(dir, &["commit", "-m", name]);
+}
+
+#[test]
+fn encodings_mix_and_recompress_round_trips() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    commit_file(dir, "plain.txt", "stored uncompressed\n");
+    assert_eq!(encodings(dir), BTreeSet::from(["none"]));
+
+    // Changing the setting only affects new objects; old ones stay readable
+    ink(dir, &["config", "core.compression", "zlib"]);
+    commit_file(dir, "zlib.txt", "stored with zlib\n");
+    ink(dir, &["config", "core.compression", "zstd"]);
+    ink(dir, &["config", "core.compressionLevel", "19"]);
+    commit_file(dir, "zstd.txt", "stored with zstd\n");
+    assert_eq!(encodings(dir), BTreeSet::from(["none", "zlib", "zstd"]));
+    ink(dir, &["fsck"]);
+    let log = ink_stdout(dir, &["log", "--oneline"]);
+
+    ink(dir, &["recompress"]);
+    assert_eq!(encodings(dir), BTreeSet::from(["zstd"]));
+    assert!(ink_stdout(dir, &["recompress"]).contains("already stored as zstd"));
+
+    ink(dir, &["config", "core.compression", "none"]);
+    ink(dir, &["recompress"]);
+    assert_eq!(encodings(dir), BTreeSet::from(["none"]));
+
+    // Everything still reads back the same
+    ink(dir, &["fsck"]);
+    assert_eq!(ink_stdout(dir, &["log", "--oneline"]), log);
+    for name in ["plain.txt", "zlib.txt", "zstd.txt"] {
+        fs::remove_file(dir.join(name)).unwrap();
+    }
+    ink(dir, &["restore", "."]);
+    assert_eq!(fs::read_to_string(dir.join("zlib.txt")).unwrap(), "stored with zlib\n");
+    assert_eq!(fs::read_to_string(dir.join("zstd.txt")).unwrap(), "stored with zstd\n");
+}
+
+#[test]
+fn invalid_levels_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["config", "core.compression", "zlib"]);
+    ink(dir, &["config", "core.compressionLevel", "42"]);
+
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    let output = common::ink_command(dir).args(["add", "a.txt"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("compressionLevel"));
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::fs;
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+#[test]
+fn staged_resets_the_index_but_not_the_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    fs::write(dir.join("README"), "changed\n").unwrap();
+    ink(dir, &["add", "README"]);
+    ink(dir, &["restore", "--staged", "README"]);
+
+    assert!(ink_stdout(dir, &["diff", "--staged", "--name-only"]).is_empty());
+    assert_eq!(ink_stdout(dir, &["diff", "--name-only"]), "README\n");
+    assert_eq!(fs::read_to_string(dir.join("README")).unwrap(), "changed\n");
+}
+
+#[test]
+fn source_restores_an_older_version_of_a_directory() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/a.txt"), "a1\n").unwrap();
+    ink(dir, &["add", "src"]);
+    ink(dir, &["commit", "-m", "v1"]);
+    let v1 = ink_stdout(dir, &["rev-parse", "HEAD"]);
+
+    fs::write(dir.join("src/a.txt"), "a2\n").unwrap();
+    fs::write(dir.join("src/b.txt"), "b2\n").unwrap();
+    ink(dir, &["add", "src"]);
+    ink(dir, &["commit", "-m", "v2"]);
+
+    // Files the source does not have are removed; the index is left alone
+    ink(dir, &["restore", "--source", v1.trim(), "src"]);
+    assert_eq!(fs::read_to_string(dir.join("src/a.txt")).unwrap(), "a1\n");
+    assert!(!dir.join("src/b.txt").exists());
+    assert!(ink_stdout(dir, &["diff", "--staged", "--name-only"]).is_empty());
+
+    // With --staged as well, the index follows
+    ink(dir, &["restore", "--source", v1.trim(), "--staged", "--worktree", "src"]);
+    assert_eq!(ink_stdout(dir, &["diff", "--staged", "--name-only"]), "src/a.txt\nsrc/b.txt\n");
+}
+
+#[test]
+fn unstaged_changes_need_force() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    fs::write(dir.join("README"), "staged\n").unwrap();
+    ink(dir, &["add", "README"]);
+    fs::write(dir.join("README"), "unstaged\n").unwrap();
+
+    let output = ink_command(dir).args(["restore", "--source", "HEAD", "README"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("local modifications"));
+    assert_eq!(fs::read_to_string(dir.join("README")).unwrap(), "unstaged\n");
+
+    let output = ink_command(dir).args(["restore", "README"]).output().unwrap();
+    assert!(!output.status.success());
+
+    ink(dir, &["restore", "--force", "README"]);
+    assert_eq!(fs::read_to_string(dir.join("README")).unwrap(), "staged\n");
+
+    fs::write(dir.join("README"), "unstaged\n").unwrap();
+    ink(dir, &["restore", "--source", "HEAD", "--force", "README"]);
+    assert_eq!(fs::read_to_string(dir.join("README")).unwrap(), "hello\n");
+}
+
+#[test]
+fn deleted_files_come_back() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+
+    fs::remove_file(dir.join("README")).unwrap();
+    ink(dir, &["restore", "README"]);
+    assert_eq!(fs::read_to_string(dir.join("README")).unwrap(), "hello\n");
+}
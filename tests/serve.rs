@@ -0,0 +1,127 @@
+mod common;
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Stdio};
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+/// A running `ink serve`, stopped when dropped
+struct Server {
+    child: Child,
+    url: String,
+    // Kept open so the server's request log never hits a closed pipe
+    _log: BufReader<ChildStdout>,
+}
+
+impl Server {
+    fn start(dir: &Path) -> Server {
+        let mut child = ink_command(dir)
+            .args(["serve", "--port", "0"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("cannot start ink serve");
+
+        // "Serving <dir> on http://127.0.0.1:<port>"
+        let mut log = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        log.read_line(&mut line).unwrap();
+        let url = line.split_whitespace().last().expect("no address printed").to_string();
+        Server { child, url, _log: log }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+#[test]
+fn push_and_fetch_over_http() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    ink(&origin, &["config", "receive.denyCurrentBranch", "updateInstead"]);
+    let server = Server::start(&origin);
+
+    let alice = tmp.path().join("alice");
+    let bob = tmp.path().join("bob");
+    ink(tmp.path(), &["clone", &server.url, "alice"]);
+    ink(tmp.path(), &["clone", &server.url, "bob"]);
+    assert_eq!(fs::read_to_string(alice.join("README")).unwrap(), "hello\n");
+
+    fs::write(alice.join("notes.txt"), "from alice\n").unwrap();
+    ink(&alice, &["add", "notes.txt"]);
+    ink(&alice, &["commit", "-m", "add notes"]);
+    ink(&alice, &["push"]);
+
+    let pushed = ink_stdout(&alice, &["rev-parse", "HEAD"]);
+    assert_eq!(ink_stdout(&origin, &["rev-parse", "main"]), pushed);
+    assert_eq!(fs::read_to_string(origin.join("notes.txt")).unwrap(), "from alice\n");
+
+    ink(&bob, &["fetch"]);
+    assert_eq!(ink_stdout(&bob, &["rev-parse", "origin/main"]), pushed);
+    ink(&bob, &["pull"]);
+    assert_eq!(fs::read_to_string(bob.join("notes.txt")).unwrap(), "from alice\n");
+}
+
+#[test]
+fn push_larger_than_the_request_limit() {
+    let tmp = tempfile::tempdir().unwrap();
+    let origin = tmp.path().join("origin");
+    init_repo(&origin);
+    let server = Server::start(&origin);
+
+    let alice = tmp.path().join("alice");
+    ink(tmp.path(), &["clone", &server.url, "alice"]);
+
+    // Larger than the server's 64 MiB limit on fetch requests; sparse, so it is quick to write
+    let big = fs::File::create(alice.join("big.bin")).unwrap();
+    big.set_len(70 << 20).unwrap();
+    drop(big);
+    let mut big = fs::OpenOptions::new().append(true).open(alice.join("big.bin")).unwrap();
+    big.write_all(b"end of the large file").unwrap();
+    drop(big);
+
+    ink(&alice, &["checkout", "-b", "large"]);
+    ink(&alice, &["add", "big.bin"]);
+    ink(&alice, &["commit", "-m", "add a large file"]);
+    ink(&alice, &["push", "origin", "large"]);
+    assert_eq!(ink_stdout(&origin, &["rev-parse", "large"]), ink_stdout(&alice, &["rev-parse", "HEAD"]));
+
+    ink(tmp.path(), &["clone", &server.url, "bob"]);
+    let bob = tmp.path().join("bob");
+    ink(&bob, &["pull", "origin", "large"]);
+    assert_eq!(fs::metadata(bob.join("big.bin")).unwrap().len(), (70 << 20) + 21);
+    ink(&bob, &["fsck"]);
+}
+
+#[test]
+fn oversized_body_is_refused_without_reading_it() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+    let server = Server::start(tmp.path());
+
+    let addr = server.url.trim_start_matches("http://");
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"POST /upload-pack HTTP/1.1\r\nContent-Length: 1099511627776\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+}
+
+#[test]
+fn non_loopback_bind_needs_allow_remote() {
+    let tmp = tempfile::tempdir().unwrap();
+    init_repo(tmp.path());
+
+    let output = ink_command(tmp.path()).args(["serve", "--host", "0.0.0.0", "--port", "0"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--allow-remote"));
+}
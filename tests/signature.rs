@@ -0,0 +1,48 @@
+mod common;
+
+use std::fs;
+
+use common::{ink, ink_command, ink_stdout};
+
+#[test]
+fn malformed_date_offsets_are_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    ink(dir, &["init"]);
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    ink(dir, &["add", "a.txt"]);
+
+    for date in ["0 +1é1", "0 +é12", "0 +12345", "0 +1x00"] {
+        let output = ink_command(dir).args(["commit", "-m", "dated", "--date", date]).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "--date {:?} was accepted", date);
+        assert!(stderr.contains("Malformed timezone offset"), "--date {:?}: {}", date, stderr);
+    }
+
+    ink(dir, &["commit", "-m", "dated", "--date", "0 +0130"]);
+}
+
+#[test]
+fn legacy_identity_file_is_still_read() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    ink(dir, &["init"]);
+    fs::write(dir.join(".ink/.inkconfig"), "author=Old Name\nemail=old@example.com\n").unwrap();
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    ink(dir, &["add", "a.txt"]);
+
+    let output = ink_command(dir)
+        .args(["commit", "-m", "legacy"])
+        .env_remove("INK_AUTHOR_NAME")
+        .env_remove("INK_AUTHOR_EMAIL")
+        .env_remove("INK_COMMITTER_NAME")
+        .env_remove("INK_COMMITTER_EMAIL")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ink config user.name"));
+
+    let commit = ink_stdout(dir, &["cat-file", "-p", "HEAD"]);
+    assert!(commit.contains("author Old Name <old@example.com>"), "{}", commit);
+    assert!(commit.contains("committer Old Name <old@example.com>"), "{}", commit);
+}
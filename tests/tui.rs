@@ -0,0 +1,110 @@
+mod common;
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+/// `ink tui` running on a pseudo-terminal, driven by writing keys to it
+struct Tui {
+    child: Child,
+    keys: File,
+}
+
+impl Tui {
+    fn start(dir: &Path) -> Tui {
+        let (mut master, mut slave) = (0, 0);
+        let size = libc::winsize { ws_row: 40, ws_col: 120, ws_xpixel: 0, ws_ypixel: 0 };
+        let status = unsafe { libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) };
+        assert_eq!(status, 0, "cannot open a pseudo-terminal");
+        let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+        let child = ink_command(dir)
+            .arg("tui")
+            .stdin(Stdio::from(slave.try_clone().unwrap()))
+            .stdout(Stdio::from(slave.try_clone().unwrap()))
+            .stderr(Stdio::from(slave))
+            .spawn()
+            .expect("cannot start ink tui");
+
+        // The screen is never read, but must be drained so drawing never blocks
+        let mut screen = File::from(master.try_clone().unwrap());
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            while matches!(screen.read(&mut buf), Ok(n) if n > 0) {}
+        });
+
+        Tui { child, keys: File::from(master) }
+    }
+
+    fn press(&mut self, keys: &str) {
+        for key in keys.bytes() {
+            self.keys.write_all(&[key]).unwrap();
+            sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Quits with `q` and waits for the process to exit
+    fn quit(mut self) {
+        self.press("q");
+        let start = Instant::now();
+        while self.child.try_wait().unwrap().is_none() {
+            assert!(start.elapsed() < Duration::from_secs(10), "ink tui did not quit");
+            sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Polls `check` until it holds, failing the test after a few seconds
+fn eventually(what: &str, mut check: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !check() {
+        assert!(start.elapsed() < Duration::from_secs(10), "timed out waiting until {}", what);
+        sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn stage_and_unstage_from_the_status_pane() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    fs::write(dir.join("new.txt"), "new\n").unwrap();
+
+    let mut tui = Tui::start(dir);
+    tui.press("s");
+    eventually("new.txt is staged", || ink_stdout(dir, &["ls-files"]).contains("new.txt"));
+
+    // The staged entry is listed first after the refresh
+    tui.press("u");
+    eventually("new.txt is unstaged", || !ink_stdout(dir, &["ls-files"]).contains("new.txt"));
+    tui.quit();
+
+    assert_eq!(fs::read_to_string(dir.join("new.txt")).unwrap(), "new\n");
+}
+
+#[test]
+fn enter_checks_out_the_selected_branch() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    ink(dir, &["branch", "topic"]);
+
+    let mut tui = Tui::start(dir);
+    // Tab moves to the branch list, sorted as main, topic
+    tui.press("\tj\r");
+    eventually("topic is checked out", || ink_stdout(dir, &["branch"]).contains("* topic"));
+    tui.quit();
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use common::{ink, ink_command, ink_stdout, init_repo};
+
+/// A running `ink watch`, stopped when dropped
+struct Watcher {
+    child: Child,
+    dir: std::path::PathBuf,
+    _log: BufReader<ChildStdout>,
+}
+
+impl Watcher {
+    fn start(dir: &Path, args: &[&str]) -> Watcher {
+        let mut child = ink_command(dir)
+            .arg("watch")
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("cannot start ink watch");
+
+        // "Watching <dir> (status queries on <addr>)" once events are being delivered
+        let mut log = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        log.read_line(&mut line).unwrap();
+        assert!(line.starts_with("Watching"), "{}", line);
+        Watcher { child, dir: dir.to_path_buf(), _log: log }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        ink_command(&self.dir).args(["watch", "--stop"]).output().ok();
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Polls `check` until it holds, failing the test after a few seconds
+fn eventually(what: &str, mut check: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !check() {
+        assert!(start.elapsed() < Duration::from_secs(10), "timed out waiting until {}", what);
+        sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn saved_tracked_files_are_staged() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let _watcher = Watcher::start(dir, &["--stage"]);
+
+    fs::write(dir.join("README"), "saved\n").unwrap();
+    eventually("README is staged", || ink_stdout(dir, &["diff", "--staged", "--name-only"]) == "README\n");
+    assert!(ink_stdout(dir, &["diff", "--name-only"]).is_empty());
+    assert!(!dir.join(".ink/index.lock").exists());
+
+    // New files stay untracked
+    fs::write(dir.join("new.txt"), "new\n").unwrap();
+    eventually("new.txt is reported", || ink_stdout(dir, &["watch", "--list"]).contains("new.txt"));
+    assert!(!ink_stdout(dir, &["ls-files"]).contains("new.txt"));
+}
+
+#[test]
+fn status_asks_the_daemon_for_dirty_paths() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    fs::write(dir.join("ignored.log"), "x\n").unwrap();
+    fs::write(dir.join(".inkignore"), "*.log\n").unwrap();
+    ink(dir, &["add", ".inkignore"]);
+    ink(dir, &["commit", "-m", "ignore logs"]);
+    let _watcher = Watcher::start(dir, &[]);
+
+    fs::write(dir.join("README"), "edited\n").unwrap();
+    fs::write(dir.join("other.log"), "x\n").unwrap();
+    eventually("README is dirty", || ink_stdout(dir, &["watch", "--list"]).contains("README"));
+    assert!(!ink_stdout(dir, &["watch", "--list"]).contains(".log"));
+
+    let short = ink_stdout(dir, &["status", "--short"]);
+    assert_eq!(short, " M README\n");
+}
+
+#[test]
+fn autosave_commits_without_touching_the_branch() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    init_repo(dir);
+    let head = ink_stdout(dir, &["rev-parse", "HEAD"]);
+    let _watcher = Watcher::start(dir, &["--autosave", "1"]);
+
+    fs::write(dir.join("README"), "draft\n").unwrap();
+    eventually("an autosave exists", || {
+        ink_command(dir).args(["rev-parse", "--verify", "refs/autosave/main"]).output().unwrap().status.success()
+    });
+
+    assert_eq!(ink_stdout(dir, &["rev-parse", "HEAD"]), head);
+    assert!(ink_stdout(dir, &["diff", "--staged", "--name-only"]).is_empty());
+    let saved = ink_stdout(dir, &["ls-tree", "refs/autosave/main"]);
+    let hash = saved.lines().find(|l| l.ends_with("README")).unwrap().split_whitespace().nth(2).unwrap();
+    assert_eq!(ink_stdout(dir, &["cat-file", "blob", hash]), "draft\n");
+}